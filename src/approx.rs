@@ -0,0 +1,335 @@
+use float_eq::{
+    AssertFloatEq, AssertFloatEqAll, FloatEq, FloatEqAll, FloatEqDebugUlpsDiff, FloatEqUlpsTol,
+};
+use num_traits::{Float, NumCast};
+use std::fmt::Debug;
+
+/// the tolerance every `PartialEq` impl in the crate compares with.
+///
+/// matrix inverses are rounded to 5 decimal places, so anything tighter than
+/// this makes `m * m.inverse() == identity` fail on ordinary transforms.
+pub const EPSILON: f64 = 0.0001;
+
+/// the float types `ApproxEq` compares component by component.
+pub trait ApproxScalar:
+    Float
+    + Debug
+    + FloatEq<Tol = Self>
+    + FloatEqAll<AllTol = Self>
+    + FloatEqUlpsTol<UlpsTol = <Self as ApproxScalar>::Ulps>
+    + FloatEqDebugUlpsDiff<DebugUlpsDiff = Option<<Self as ApproxScalar>::Ulps>>
+    + AssertFloatEq<DebugAbsDiff = Self, DebugTol = Self>
+    + AssertFloatEqAll<AllDebugTol = Self>
+{
+    /// how many representable floats apart two values may be.
+    type Ulps: Copy + Debug + PartialEq;
+
+    fn eq_ulps_tol(&self, other: &Self, tol: &Self::Ulps) -> bool;
+}
+
+impl ApproxScalar for f32 {
+    type Ulps = u32;
+
+    fn eq_ulps_tol(&self, other: &Self, tol: &u32) -> bool {
+        self.eq_ulps(other, tol)
+    }
+}
+
+impl ApproxScalar for f64 {
+    type Ulps = u64;
+
+    fn eq_ulps_tol(&self, other: &Self, tol: &u64) -> bool {
+        self.eq_ulps(other, tol)
+    }
+}
+
+pub trait ApproxEq {
+    type Scalar: ApproxScalar;
+
+    /// whether `f` holds for every pair of corresponding components. values
+    /// with mismatched shapes (e.g. matrices of different sizes) never match.
+    fn all_components<F>(&self, other: &Self, f: F) -> bool
+    where
+        F: Fn(&Self::Scalar, &Self::Scalar) -> bool;
+
+    /// `|a - b| <= tol` for every component.
+    fn approx_eq_abs(&self, other: &Self, tol: Self::Scalar) -> bool {
+        self.all_components(other, |a, b| a.eq_abs(b, &tol))
+    }
+
+    /// `|a - b| <= tol * max(|a|, |b|)` for every component.
+    fn approx_eq_rel(&self, other: &Self, tol: Self::Scalar) -> bool {
+        self.all_components(other, |a, b| a.eq_rmax(b, &tol))
+    }
+
+    /// every component is at most `tol` representable floats away.
+    fn approx_eq_ulps(&self, other: &Self, tol: <Self::Scalar as ApproxScalar>::Ulps) -> bool {
+        self.all_components(other, |a, b| a.eq_ulps_tol(b, &tol))
+    }
+
+    /// compares with the crate-wide absolute `EPSILON`.
+    fn approx_eq(&self, other: &Self) -> bool {
+        self.approx_eq_abs(other, <Self::Scalar as NumCast>::from(EPSILON).unwrap())
+    }
+}
+
+impl ApproxEq for f32 {
+    type Scalar = f32;
+
+    fn all_components<F>(&self, other: &Self, f: F) -> bool
+    where
+        F: Fn(&f32, &f32) -> bool,
+    {
+        f(self, other)
+    }
+}
+
+impl ApproxEq for f64 {
+    type Scalar = f64;
+
+    fn all_components<F>(&self, other: &Self, f: F) -> bool
+    where
+        F: Fn(&f64, &f64) -> bool,
+    {
+        f(self, other)
+    }
+}
+
+/// asserts that two `ApproxEq` values are equal, either within the default
+/// `EPSILON` or an explicit `abs <= tol`, `rel <= tol` or `ulps <= tol`.
+#[macro_export]
+macro_rules! assert_approx_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::__assert_approx!($left, $right, approx_eq(), true, "≈")
+    };
+    ($left:expr, $right:expr, abs <= $tol:expr $(,)?) => {
+        $crate::__assert_approx!($left, $right, approx_eq_abs($tol), true, "≈")
+    };
+    ($left:expr, $right:expr, rel <= $tol:expr $(,)?) => {
+        $crate::__assert_approx!($left, $right, approx_eq_rel($tol), true, "≈")
+    };
+    ($left:expr, $right:expr, ulps <= $tol:expr $(,)?) => {
+        $crate::__assert_approx!($left, $right, approx_eq_ulps($tol), true, "≈")
+    };
+}
+
+/// the negation of `assert_approx_eq!`, taking the same tolerances.
+#[macro_export]
+macro_rules! assert_approx_ne {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::__assert_approx!($left, $right, approx_eq(), false, "≉")
+    };
+    ($left:expr, $right:expr, abs <= $tol:expr $(,)?) => {
+        $crate::__assert_approx!($left, $right, approx_eq_abs($tol), false, "≉")
+    };
+    ($left:expr, $right:expr, rel <= $tol:expr $(,)?) => {
+        $crate::__assert_approx!($left, $right, approx_eq_rel($tol), false, "≉")
+    };
+    ($left:expr, $right:expr, ulps <= $tol:expr $(,)?) => {
+        $crate::__assert_approx!($left, $right, approx_eq_ulps($tol), false, "≉")
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __assert_approx {
+    ($left:expr, $right:expr, $method:ident($($tol:expr)?), $expected:literal, $op:literal) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if $crate::approx::ApproxEq::$method(left, right $(, $tol)?) != $expected {
+                    panic!(
+                        "assertion failed: `(left {} right)`\n  left: `{:?}`\n right: `{:?}`",
+                        $op, left, right
+                    );
+                }
+            }
+        }
+    };
+}
+
+/// implements float_eq's comparison traits field by field, like
+/// `derive_float_eq` does, for a struct generic over its float type. the derive
+/// only handles concrete types.
+macro_rules! float_eq_generic {
+    ($name:ident { $($field:ident),+ }, $ulps:ident, $debug_ulps_diff:ident) => {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        pub struct $ulps<T: $crate::approx::ApproxScalar> {
+            $(pub $field: T::Ulps,)+
+        }
+
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        pub struct $debug_ulps_diff<T: $crate::approx::ApproxScalar> {
+            $(pub $field: Option<T::Ulps>,)+
+        }
+
+        impl<T: $crate::approx::ApproxScalar> ::float_eq::FloatEqUlpsTol for $name<T>
+        {
+            type UlpsTol = $ulps<T>;
+        }
+
+        impl<T: $crate::approx::ApproxScalar> ::float_eq::FloatEqDebugUlpsDiff for $name<T> {
+            type DebugUlpsDiff = $debug_ulps_diff<T>;
+        }
+
+        impl<T: $crate::approx::ApproxScalar> ::float_eq::FloatEq for $name<T>
+        {
+            type Tol = Self;
+
+            fn eq_abs(&self, other: &Self, tol: &Self) -> bool {
+                $(self.$field.eq_abs(&other.$field, &tol.$field))&&+
+            }
+
+            fn eq_rmax(&self, other: &Self, tol: &Self) -> bool {
+                $(self.$field.eq_rmax(&other.$field, &tol.$field))&&+
+            }
+
+            fn eq_rmin(&self, other: &Self, tol: &Self) -> bool {
+                $(self.$field.eq_rmin(&other.$field, &tol.$field))&&+
+            }
+
+            fn eq_r1st(&self, other: &Self, tol: &Self) -> bool {
+                $(self.$field.eq_r1st(&other.$field, &tol.$field))&&+
+            }
+
+            fn eq_r2nd(&self, other: &Self, tol: &Self) -> bool {
+                $(self.$field.eq_r2nd(&other.$field, &tol.$field))&&+
+            }
+
+            fn eq_ulps(&self, other: &Self, tol: &$ulps<T>) -> bool {
+                $(self.$field.eq_ulps(&other.$field, &tol.$field))&&+
+            }
+        }
+
+        impl<T: $crate::approx::ApproxScalar> ::float_eq::FloatEqAll for $name<T>
+        {
+            type AllTol = T;
+
+            fn eq_abs_all(&self, other: &Self, tol: &T) -> bool {
+                $(self.$field.eq_abs(&other.$field, tol))&&+
+            }
+
+            fn eq_rmax_all(&self, other: &Self, tol: &T) -> bool {
+                $(self.$field.eq_rmax(&other.$field, tol))&&+
+            }
+
+            fn eq_rmin_all(&self, other: &Self, tol: &T) -> bool {
+                $(self.$field.eq_rmin(&other.$field, tol))&&+
+            }
+
+            fn eq_r1st_all(&self, other: &Self, tol: &T) -> bool {
+                $(self.$field.eq_r1st(&other.$field, tol))&&+
+            }
+
+            fn eq_r2nd_all(&self, other: &Self, tol: &T) -> bool {
+                $(self.$field.eq_r2nd(&other.$field, tol))&&+
+            }
+
+            fn eq_ulps_all(&self, other: &Self, tol: &T::Ulps) -> bool {
+                $(self.$field.eq_ulps(&other.$field, tol))&&+
+            }
+        }
+
+        impl<T: $crate::approx::ApproxScalar> ::float_eq::AssertFloatEq for $name<T> {
+            type DebugAbsDiff = Self;
+            type DebugTol = Self;
+
+            fn debug_abs_diff(&self, other: &Self) -> Self {
+                $name { $($field: self.$field.debug_abs_diff(&other.$field)),+ }
+            }
+
+            fn debug_ulps_diff(&self, other: &Self) -> $debug_ulps_diff<T> {
+                $debug_ulps_diff { $($field: self.$field.debug_ulps_diff(&other.$field)),+ }
+            }
+
+            fn debug_abs_tol(&self, other: &Self, tol: &Self) -> Self {
+                $name { $($field: self.$field.debug_abs_tol(&other.$field, &tol.$field)),+ }
+            }
+
+            fn debug_rmax_tol(&self, other: &Self, tol: &Self) -> Self {
+                $name { $($field: self.$field.debug_rmax_tol(&other.$field, &tol.$field)),+ }
+            }
+
+            fn debug_rmin_tol(&self, other: &Self, tol: &Self) -> Self {
+                $name { $($field: self.$field.debug_rmin_tol(&other.$field, &tol.$field)),+ }
+            }
+
+            fn debug_r1st_tol(&self, other: &Self, tol: &Self) -> Self {
+                $name { $($field: self.$field.debug_r1st_tol(&other.$field, &tol.$field)),+ }
+            }
+
+            fn debug_r2nd_tol(&self, other: &Self, tol: &Self) -> Self {
+                $name { $($field: self.$field.debug_r2nd_tol(&other.$field, &tol.$field)),+ }
+            }
+
+            fn debug_ulps_tol(&self, other: &Self, tol: &$ulps<T>) -> $ulps<T> {
+                $ulps { $($field: self.$field.debug_ulps_tol(&other.$field, &tol.$field)),+ }
+            }
+        }
+
+        impl<T: $crate::approx::ApproxScalar> ::float_eq::AssertFloatEqAll for $name<T> {
+            type AllDebugTol = Self;
+
+            fn debug_abs_all_tol(&self, other: &Self, tol: &T) -> Self {
+                $name { $($field: self.$field.debug_abs_tol(&other.$field, tol)),+ }
+            }
+
+            fn debug_rmax_all_tol(&self, other: &Self, tol: &T) -> Self {
+                $name { $($field: self.$field.debug_rmax_tol(&other.$field, tol)),+ }
+            }
+
+            fn debug_rmin_all_tol(&self, other: &Self, tol: &T) -> Self {
+                $name { $($field: self.$field.debug_rmin_tol(&other.$field, tol)),+ }
+            }
+
+            fn debug_r1st_all_tol(&self, other: &Self, tol: &T) -> Self {
+                $name { $($field: self.$field.debug_r1st_tol(&other.$field, tol)),+ }
+            }
+
+            fn debug_r2nd_all_tol(&self, other: &Self, tol: &T) -> Self {
+                $name { $($field: self.$field.debug_r2nd_tol(&other.$field, tol)),+ }
+            }
+
+            fn debug_ulps_all_tol(&self, other: &Self, tol: &T::Ulps) -> $ulps<T> {
+                $ulps { $($field: self.$field.debug_ulps_tol(&other.$field, tol)),+ }
+            }
+        }
+    };
+}
+
+pub(crate) use float_eq_generic;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approx_eq_scalar() {
+        assert!(1.0_f64.approx_eq(&1.00005));
+        assert!(!1.0_f64.approx_eq(&1.0002));
+        assert!(1.0_f32.approx_eq(&1.00005));
+    }
+
+    #[test]
+    fn test_approx_eq_tolerances() {
+        assert!(1000.0_f64.approx_eq_rel(&1000.1, 0.001));
+        assert!(!1000.0_f64.approx_eq_abs(&1000.1, 0.001));
+        assert!(1.0_f64.approx_eq_ulps(&(1.0 + f64::EPSILON), 1));
+        assert!(!1.0_f64.approx_eq_ulps(&(1.0 + 2.0 * f64::EPSILON), 1));
+    }
+
+    #[test]
+    fn test_assert_approx_macros() {
+        assert_approx_eq!(0.1_f64 + 0.2, 0.3);
+        assert_approx_eq!(0.1_f64 + 0.2, 0.3, abs <= f64::EPSILON);
+        assert_approx_eq!(100.0_f64, 100.05, rel <= 0.001);
+        assert_approx_eq!(0.1_f64 + 0.2, 0.3, ulps <= 1);
+        assert_approx_ne!(1.0_f64, 1.1);
+        assert_approx_ne!(100.0_f64, 100.5, rel <= 0.001);
+    }
+
+    #[test]
+    #[should_panic(expected = "assertion failed: `(left ≈ right)`")]
+    fn test_assert_approx_eq_fail() {
+        assert_approx_eq!(1.0_f64, 1.1);
+    }
+}
@@ -0,0 +1,222 @@
+//! whole-canvas pixel arithmetic and blend modes, for post-processing passes.
+//!
+//! every operation returns a new canvas. `map` and `zip` keep the alpha plane
+//! of `self`, `add` and `lerp` work on alpha like the premultiplied colors,
+//! and the blend modes treat `other` as a layer on top of `self` and composite
+//! it over. the binary ones panic if the canvases differ in size.
+
+use crate::{canvas::Canvas, color::Color, rgba::Rgba};
+
+fn per_channel(a: Color, b: Color, f: impl Fn(f64, f64) -> f64) -> Color {
+    Color::new(f(a.r, b.r), f(a.g, b.g), f(a.b, b.b))
+}
+
+impl Canvas {
+    /// applies `f` to every pixel.
+    pub fn map<F>(&self, f: F) -> Canvas
+    where
+        F: Fn(Color) -> Color,
+    {
+        Canvas {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().map(|&c| f(c)).collect(),
+            alpha: self.alpha.clone(),
+        }
+    }
+
+    /// combines corresponding pixels of two canvases with `f`. `f` sees the
+    /// premultiplied colors and the result keeps `self`'s alpha, so this
+    /// assumes both canvases have the same alpha.
+    pub fn zip<F>(&self, other: &Canvas, f: F) -> Canvas
+    where
+        F: Fn(Color, Color) -> Color,
+    {
+        assert!(
+            self.width == other.width && self.height == other.height,
+            "can't combine a {}x{} canvas with a {}x{} one",
+            self.width,
+            self.height,
+            other.width,
+            other.height
+        );
+
+        Canvas {
+            width: self.width,
+            height: self.height,
+            pixels: self
+                .pixels
+                .iter()
+                .zip(other.pixels.iter())
+                .map(|(&a, &b)| f(a, b))
+                .collect(),
+            alpha: self.alpha.clone(),
+        }
+    }
+
+    /// sums the two, e.g. to combine separately rendered light passes. alpha
+    /// adds up too, stopping at 1.
+    pub fn add(&self, other: &Canvas) -> Canvas {
+        let mut out = self.zip(other, |a, b| a + b);
+        for (alpha, &b) in out.alpha.iter_mut().zip(other.alpha.iter()) {
+            *alpha = (*alpha + b).min(1.0);
+        }
+        out
+    }
+
+    /// blends the straight colors of `other` onto `self` with `f`, then
+    /// composites the result source-over, as the w3c compositing spec does.
+    /// where `self` is transparent, `other` shows through unblended.
+    fn blend<F>(&self, other: &Canvas, f: F) -> Canvas
+    where
+        F: Fn(f64, f64) -> f64,
+    {
+        assert!(
+            self.width == other.width && self.height == other.height,
+            "can't blend a {}x{} canvas with a {}x{} one",
+            self.width,
+            self.height,
+            other.width,
+            other.height
+        );
+
+        let mut out = Canvas::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (dst, src) = (self.rgba(x, y), other.rgba(x, y));
+                let (backdrop, source) = (dst.to_straight(), src.to_straight());
+                let mixed =
+                    source * (1.0 - dst.alpha) + per_channel(backdrop, source, &f) * dst.alpha;
+                out.write_rgba(x, y, Rgba::from_straight(mixed, src.alpha).over(dst));
+            }
+        }
+
+        out
+    }
+
+    /// the hadamard product, e.g. to apply an ambient occlusion pass.
+    pub fn multiply(&self, other: &Canvas) -> Canvas {
+        self.blend(other, |a, b| a * b)
+    }
+
+    /// the inverse of multiplying the inverses, which only ever brightens.
+    pub fn screen(&self, other: &Canvas) -> Canvas {
+        self.blend(other, |a, b| 1.0 - (1.0 - a) * (1.0 - b))
+    }
+
+    /// multiplies where `self` is dark and screens where it's light.
+    pub fn overlay(&self, other: &Canvas) -> Canvas {
+        self.blend(other, |a, b| {
+            if a < 0.5 {
+                2.0 * a * b
+            } else {
+                1.0 - 2.0 * (1.0 - a) * (1.0 - b)
+            }
+        })
+    }
+
+    /// the absolute difference, handy for spotting changes between renders.
+    pub fn difference(&self, other: &Canvas) -> Canvas {
+        self.blend(other, |a, b| (a - b).abs())
+    }
+
+    /// linearly interpolates from `self` at `t = 0` to `other` at `t = 1`,
+    /// alpha included.
+    pub fn lerp(&self, other: &Canvas, t: f64) -> Canvas {
+        let mut out = self.zip(other, |a, b| a + (b - a) * t);
+        for (alpha, &b) in out.alpha.iter_mut().zip(other.alpha.iter()) {
+            *alpha += (b - *alpha) * t;
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canvas(colors: &[Color]) -> Canvas {
+        let mut c = Canvas::new(colors.len(), 1);
+        c.pixels = colors.to_vec();
+        c
+    }
+
+    #[test]
+    fn test_map() {
+        let c = canvas(&[Color::new(0.1, 0.2, 0.3), Color::new(1.0, 2.0, 3.0)]);
+        let doubled = c.map(|p| p * 2.0);
+
+        assert_eq!(
+            doubled.pixels,
+            vec![Color::new(0.2, 0.4, 0.6), Color::new(2.0, 4.0, 6.0)]
+        );
+        assert_eq!(doubled.alpha, c.alpha);
+    }
+
+    #[test]
+    fn test_blend_modes() {
+        let a = canvas(&[Color::new(0.2, 0.5, 0.8)]);
+        let b = canvas(&[Color::new(0.5, 0.5, 0.25)]);
+
+        assert_eq!(a.add(&b)[(0, 0)], Color::new(0.7, 1.0, 1.05));
+        assert_eq!(a.multiply(&b)[(0, 0)], Color::new(0.1, 0.25, 0.2));
+        assert_eq!(a.screen(&b)[(0, 0)], Color::new(0.6, 0.75, 0.85));
+        assert_eq!(a.overlay(&b)[(0, 0)], Color::new(0.2, 0.5, 0.7));
+        assert_eq!(a.difference(&b)[(0, 0)], Color::new(0.3, 0.0, 0.55));
+        assert_eq!(a.lerp(&b, 0.0), a);
+        assert_eq!(a.lerp(&b, 1.0).pixels, b.pixels);
+        assert_eq!(a.lerp(&b, 0.5)[(0, 0)], Color::new(0.35, 0.5, 0.525));
+    }
+
+    #[test]
+    fn test_blend_translucent() {
+        // half of the blend shows, over half of the backdrop.
+        let a = canvas(&[Color::new(0.2, 0.5, 0.8)]);
+        let mut b = Canvas::new_transparent(1, 1);
+        b.write_rgba(0, 0, Rgba::from_straight(Color::new(0.5, 0.5, 0.25), 0.5));
+
+        let multiplied = a.multiply(&b);
+        assert_eq!(multiplied[(0, 0)], Color::new(0.15, 0.375, 0.5));
+        assert_eq!(multiplied.alpha, vec![1.0]);
+        assert_eq!(a.screen(&b)[(0, 0)], Color::new(0.4, 0.625, 0.825));
+
+        // a fully transparent source leaves the backdrop alone.
+        let clear = Canvas::new_transparent(1, 1);
+        assert_eq!(a.difference(&clear), a);
+
+        // and over a transparent backdrop, the source isn't blended at all.
+        let blended = clear.multiply(&b);
+        assert_eq!(blended.rgba(0, 0), b.rgba(0, 0));
+    }
+
+    #[test]
+    fn test_zip_keeps_alpha() {
+        let mut a = Canvas::new_transparent(1, 1);
+        a.alpha[0] = 0.5;
+        let b = Canvas::new(1, 1);
+
+        assert_eq!(a.zip(&b, |x, y| x + y).alpha, vec![0.5]);
+    }
+
+    #[test]
+    fn test_add_and_lerp_alpha() {
+        let mut a = Canvas::new_transparent(1, 1);
+        a.write_rgba(0, 0, Rgba::from_straight(Color::new(1.0, 0.0, 0.0), 0.25));
+        let mut b = Canvas::new_transparent(1, 1);
+        b.write_rgba(0, 0, Rgba::from_straight(Color::new(0.0, 0.0, 1.0), 0.5));
+
+        let sum = a.add(&b);
+        assert_eq!(sum[(0, 0)], Color::new(0.25, 0.0, 0.5));
+        assert_eq!(sum.alpha, vec![0.75]);
+        assert_eq!(b.add(&b).add(&b).alpha, vec![1.0]);
+
+        assert_eq!(a.lerp(&b, 1.0), b);
+        assert_eq!(a.lerp(&b, 0.5).alpha, vec![0.375]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zip_size_mismatch() {
+        Canvas::new(2, 2).add(&Canvas::new(2, 1));
+    }
+}
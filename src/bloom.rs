@@ -0,0 +1,132 @@
+//! bloom: light bleeding out of anything brighter than white, the way it does
+//! through a real lens. run it on the linear render before tone mapping.
+
+use crate::{canvas::Canvas, color::Color, resample::Filter};
+
+/// how bright things have to be to glow, and how far and how strongly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bloom {
+    /// only the part of a color beyond this brightness glows.
+    pub threshold: f64,
+    /// how much of the excess light is spread into the glow.
+    pub intensity: f64,
+    /// the blur, in pixels, at the finest scale. nan or infinite means no
+    /// blur, as for `gaussian_blur`.
+    pub sigma: f64,
+    /// how many scales to blur at, each half the resolution of the last. more
+    /// give a wider, softer halo.
+    pub levels: usize,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            intensity: 0.1,
+            sigma: 2.0,
+            levels: 5,
+        }
+    }
+}
+
+/// the part of `c` above `threshold`, measured by its largest component so the
+/// hue is kept.
+fn excess(c: Color, threshold: f64) -> Color {
+    let brightest = c.r.max(c.g).max(c.b);
+    if brightest <= threshold {
+        Color::new(0.0, 0.0, 0.0)
+    } else {
+        c * ((brightest - threshold) / brightest)
+    }
+}
+
+impl Canvas {
+    /// the glow alone: the over-bright parts of the canvas blurred at each
+    /// scale and averaged, scaled by the bloom's intensity.
+    pub fn bloom_glow(&self, bloom: &Bloom) -> Canvas {
+        let (width, height) = (self.width, self.height);
+        let mut glow = Canvas::new(width, height);
+        let mut level = self.map(|c| excess(c, bloom.threshold));
+
+        let levels = bloom.levels.max(1);
+        for i in 0..levels {
+            let blurred = level.gaussian_blur(bloom.sigma);
+            glow = glow.add(&blurred.resize(width, height, Filter::Bilinear));
+
+            if i + 1 < levels {
+                let (w, h) = ((level.width / 2).max(1), (level.height / 2).max(1));
+                level = blurred.resize(w, h, Filter::Bilinear);
+            }
+        }
+
+        glow.map(|c| c * (bloom.intensity / levels as f64))
+    }
+
+    /// adds the glow back onto the canvas. the result is still linear and
+    /// usually wants `tone_mapped` next.
+    pub fn bloom(&self, bloom: &Bloom) -> Canvas {
+        self.add(&self.bloom_glow(bloom))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    #[test]
+    fn test_excess() {
+        assert_eq!(
+            excess(Color::new(0.5, 1.0, 0.2), 1.0),
+            Color::new(0.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            excess(Color::new(4.0, 2.0, 0.0), 1.0),
+            Color::new(3.0, 1.5, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_nothing_bright() {
+        let mut c = Canvas::new(8, 8);
+        c.write_pixel(3, 3, Color::new(1.0, 0.9, 0.8));
+
+        assert_eq!(c.bloom(&Bloom::default()), c);
+    }
+
+    #[test]
+    fn test_bloom_spreads() {
+        let mut c = Canvas::new(64, 64);
+        c.write_pixel(32, 32, Color::new(101.0, 101.0, 101.0));
+        let bloom = Bloom::default();
+
+        let glow = c.bloom_glow(&bloom);
+        let near = glow[(34, 32)].r;
+        let far = glow[(44, 32)].r;
+        assert!(near > far && far > 0.0);
+
+        // the glow carries `intensity` of the excess light, give or take the
+        // resampling.
+        let total: f64 = glow.pixels.iter().map(|p| p.r).sum();
+        assert_approx_eq!(total, bloom.intensity * 100.0, rel <= 0.15);
+
+        let out = c.bloom(&bloom);
+        assert!(out[(34, 32)].r > 0.0);
+        assert!(out.is_opaque());
+    }
+
+    #[test]
+    fn test_bloom_bad_sigma() {
+        let mut c = Canvas::new(16, 16);
+        c.write_pixel(8, 8, Color::new(3.0, 3.0, 3.0));
+
+        for sigma in [f64::NAN, f64::INFINITY] {
+            let glow = c.bloom_glow(&Bloom {
+                sigma,
+                ..Bloom::default()
+            });
+            assert!(glow.pixels.iter().all(|p| p.r.is_finite()));
+            assert!(glow[(8, 8)].r > 0.0);
+        }
+    }
+}
@@ -1,11 +1,13 @@
-use crate::color::Color;
-use std::{fs::File, io::Write, path::Path};
+use crate::{color::Color, rgba::Rgba};
 
+/// pixels are stored premultiplied by their coverage in `alpha`, so a fully
+/// opaque canvas reads exactly like one without an alpha channel.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Canvas {
     pub width: usize,
     pub height: usize,
     pub pixels: Vec<Color>,
+    pub alpha: Vec<f64>,
 }
 
 impl Canvas {
@@ -14,49 +16,37 @@ impl Canvas {
             width,
             height,
             pixels: vec![Color::new(0.0, 0.0, 0.0); width * height],
+            alpha: vec![1.0; width * height],
         }
     }
 
-    pub fn write_pixel(&mut self, x: usize, y: usize, color: Color) -> &Self {
-        self[(x, y)] = color;
-        self
+    /// a canvas with nothing on it. render into it with `write_pixel` and any
+    /// pixel whose ray misses stays transparent, ready to composite over a
+    /// plate.
+    pub fn new_transparent(width: usize, height: usize) -> Self {
+        Self {
+            alpha: vec![0.0; width * height],
+            ..Self::new(width, height)
+        }
     }
 
-    pub fn write_to_ppm(&self, path: &Path) -> std::io::Result<()> {
-        let mut f = File::create(path)?;
-        let headers = format!("P3\n{} {}\n255\n", self.width, self.height);
-        let mut pixels = String::new();
-
-        let mut i = 0;
-
-        for pixel in self.pixels.iter() {
-            let pixel_int = pixel.to_int(255);
-            pixels.push_str(&format!("{} {} {} ", pixel_int.r, pixel_int.g, pixel_int.b));
+    /// writes a fully opaque pixel.
+    pub fn write_pixel(&mut self, x: usize, y: usize, color: Color) -> &Self {
+        self.write_rgba(x, y, Rgba::opaque(color))
+    }
 
-            i += 1;
+    pub fn write_rgba(&mut self, x: usize, y: usize, rgba: Rgba) -> &Self {
+        self[(x, y)] = rgba.color;
+        self.alpha[x + y * self.width] = rgba.alpha;
+        self
+    }
 
-            if i == self.width {
-                i = 0;
-                pixels.push('\n');
-            }
-        }
+    pub fn rgba(&self, x: usize, y: usize) -> Rgba {
+        Rgba::premultiplied(self[(x, y)], self.alpha[x + y * self.width])
+    }
 
-        let mut contents = String::new();
-        contents.push_str(&headers);
-        contents.push_str(
-            &pixels
-                .trim()
-                .lines()
-                .map(|part| part.trim())
-                .collect::<Vec<&str>>()
-                .join("\n"),
-        );
-        contents.push('\n');
-
-        match f.write(contents.as_bytes()) {
-            Ok(_) => Ok(()),
-            Err(e) => panic!("error writing to file: {}", e),
-        }
+    pub fn is_opaque(&self) -> bool {
+        self.alpha.iter().all(|&a| a >= 1.0)
     }
 }
 
@@ -89,10 +79,6 @@ impl std::ops::IndexMut<(usize, usize)> for Canvas {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::{
-        fs,
-        io::{prelude::*, BufReader},
-    };
 
     #[test]
     fn test_canvas() {
@@ -127,44 +113,17 @@ mod tests {
     }
 
     #[test]
-    fn test_write_empty_ppm() {
-        let c = Canvas::new(5, 3);
-        c.write_to_ppm(Path::new("test_write_empty_ppm.ppm"))
-            .unwrap();
-
-        let file = File::open("test_write_empty_ppm.ppm").unwrap();
-        let mut buf_reader = BufReader::new(file);
-        let mut content = String::new();
-        buf_reader.read_to_string(&mut content).unwrap();
-
-        assert_eq!(content, "P3\n5 3\n255\n0 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n0 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n0 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n");
-
-        fs::remove_file("test_write_empty_ppm.ppm").unwrap();
-    }
-
-    #[test]
-    fn test_write_ppm() {
-        let mut c = Canvas::new(5, 3);
-        let c1 = Color::new(1.5, 0.0, 0.0);
-        let c2 = Color::new(0.0, 0.5, 0.0);
-        let c3 = Color::new(-0.5, 0.0, 1.0);
-
-        c.write_pixel(0, 0, c1);
-        c.write_pixel(1, 0, Color::new(0.0, 0.5, 0.5));
-        c.write_pixel(2, 1, c2);
-        c.write_pixel(4, 2, c3);
-
-        println!("{:?}", c.pixels);
-
-        c.write_to_ppm(Path::new("test_write_ppm.ppm")).unwrap();
-
-        let file = File::open("test_write_ppm.ppm").unwrap();
-        let mut buf_reader = BufReader::new(file);
-        let mut content = String::new();
-        buf_reader.read_to_string(&mut content).unwrap();
-
-        assert_eq!(content, "P3\n5 3\n255\n255 0 0 0 128 128 0 0 0 0 0 0 0 0 0\n0 0 0 0 0 0 0 128 0 0 0 0 0 0 0\n0 0 0 0 0 0 0 0 0 0 0 0 0 0 255\n");
-
-        fs::remove_file("test_write_ppm.ppm").unwrap();
+    fn test_transparent_canvas() {
+        let mut c = Canvas::new_transparent(2, 2);
+        assert!(!c.is_opaque());
+        assert_eq!(c.rgba(0, 0), Rgba::transparent());
+
+        c.write_pixel(1, 0, Color::new(1.0, 0.5, 0.0));
+        c.write_rgba(0, 1, Rgba::from_straight(Color::new(1.0, 1.0, 1.0), 0.25));
+
+        assert_eq!(c.rgba(1, 0), Rgba::opaque(Color::new(1.0, 0.5, 0.0)));
+        assert_eq!(c[(0, 1)], Color::new(0.25, 0.25, 0.25));
+        assert_eq!(c.alpha, vec![0.0, 1.0, 0.25, 0.0]);
+        assert!(Canvas::new(2, 2).is_opaque());
     }
 }
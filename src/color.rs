@@ -1,75 +1,89 @@
-use float_eq::{derive_float_eq, float_eq};
-use num_traits::identities::Zero;
-use std::f64::{self, EPSILON};
+use crate::approx::{float_eq_generic, ApproxEq, ApproxScalar};
+use num_traits::{identities::Zero, Float};
 use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
 
-#[derive_float_eq(
-    ulps_tol = "ColorUlps",
-    ulps_tol_derive = "Clone, Copy, Debug, PartialEq",
-    debug_ulps_diff = "ColorDebugUlpsDiff",
-    debug_ulps_diff_derive = "Clone, Copy, Debug, PartialEq",
-    all_tol = "f64"
-)]
 #[derive(Clone, Copy, Debug)]
-pub struct Color {
-    pub r: f64,
-    pub g: f64,
-    pub b: f64,
+pub struct GenericColor<T> {
+    pub r: T,
+    pub g: T,
+    pub b: T,
 }
 
-impl Color {
-    pub fn new(r: f64, g: f64, b: f64) -> Self {
+pub type Color = GenericColor<f64>;
+pub type Color32 = GenericColor<f32>;
+
+float_eq_generic!(GenericColor { r, g, b }, ColorUlps, ColorDebugUlpsDiff);
+
+impl<T: Float> GenericColor<T> {
+    pub fn new(r: T, g: T, b: T) -> Self {
         Self { r, g, b }
     }
 
+    /// quantizes each component to `0..=max`, rounding to the nearest level
+    /// and clipping anything outside [0, 1]. nan comes out as 0.
     pub fn to_int(self, max: u32) -> ColorInt {
-        let r: u32;
-        let g: u32;
-        let b: u32;
-
-        if self.r >= 1.0 {
-            r = max;
-        } else if self.r < 0.0 {
-            r = 0;
-        } else {
-            r = (self.r * max as f64).ceil() as u32;
-        }
+        let scale = |c: T| -> u32 {
+            if c >= T::one() {
+                max
+            } else if c < T::zero() {
+                0
+            } else {
+                (c * T::from(max).unwrap()).round().to_u32().unwrap_or(0)
+            }
+        };
 
-        if self.g >= 1.0 {
-            g = max;
-        } else if self.g < 0.0 {
-            g = 0;
-        } else {
-            g = (self.g * max as f64).ceil() as u32;
+        ColorInt {
+            r: scale(self.r),
+            g: scale(self.g),
+            b: scale(self.b),
         }
+    }
 
-        if self.b >= 1.0 {
-            b = max;
-        } else if self.b < 0.0 {
-            b = 0;
-        } else {
-            b = (self.b * max as f64).ceil() as u32;
+    /// like `to_int`, but rounds up once the fractional part of a level
+    /// reaches `threshold` rather than 0.5. varying `threshold` across (0, 1]
+    /// from pixel to pixel dithers the output.
+    pub fn to_int_dithered(self, max: u32, threshold: f64) -> ColorInt {
+        let (max_t, offset) = (
+            T::from(max).unwrap(),
+            T::one() - T::from(threshold).unwrap(),
+        );
+        let scale = |c: T| -> u32 {
+            (c * max_t + offset)
+                .floor()
+                .max(T::zero())
+                .min(max_t)
+                .to_u32()
+                .unwrap_or(0)
+        };
+
+        ColorInt {
+            r: scale(self.r),
+            g: scale(self.g),
+            b: scale(self.b),
         }
+    }
+}
+
+impl<T: ApproxScalar> ApproxEq for GenericColor<T> {
+    type Scalar = T;
 
-        ColorInt { r, g, b }
+    fn all_components<F>(&self, other: &Self, f: F) -> bool
+    where
+        F: Fn(&T, &T) -> bool,
+    {
+        f(&self.r, &other.r) && f(&self.g, &other.g) && f(&self.b, &other.b)
     }
 }
 
-impl PartialEq for Color {
+impl<T: ApproxScalar> PartialEq for GenericColor<T> {
     fn eq(&self, other: &Self) -> bool {
-        let cmp = Color {
-            r: 1.0 * EPSILON,
-            g: 1.0 * EPSILON,
-            b: 1.0 * EPSILON,
-        };
-
-        float_eq!(self, other, abs <= cmp)
+        self.approx_eq(other)
     }
 }
 
-impl Eq for Color {}
+impl<T: ApproxScalar> Eq for GenericColor<T> {}
 
-impl Add for Color {
+impl<T: Float> Add for GenericColor<T> {
     type Output = Self;
 
     fn add(self, other: Self) -> Self {
@@ -81,7 +95,7 @@ impl Add for Color {
     }
 }
 
-impl AddAssign for Color {
+impl<T: Float> AddAssign for GenericColor<T> {
     fn add_assign(&mut self, other: Self) {
         *self = Self {
             r: self.r + other.r,
@@ -91,7 +105,7 @@ impl AddAssign for Color {
     }
 }
 
-impl Sub for Color {
+impl<T: Float> Sub for GenericColor<T> {
     type Output = Self;
 
     fn sub(self, other: Self) -> Self {
@@ -103,7 +117,7 @@ impl Sub for Color {
     }
 }
 
-impl SubAssign for Color {
+impl<T: Float> SubAssign for GenericColor<T> {
     fn sub_assign(&mut self, other: Self) {
         *self = Self {
             r: self.r - other.r,
@@ -113,7 +127,7 @@ impl SubAssign for Color {
     }
 }
 
-impl Neg for Color {
+impl<T: Float> Neg for GenericColor<T> {
     type Output = Self;
 
     fn neg(self) -> Self {
@@ -125,10 +139,10 @@ impl Neg for Color {
     }
 }
 
-impl Mul<f64> for Color {
+impl<T: Float> Mul<T> for GenericColor<T> {
     type Output = Self;
 
-    fn mul(self, other: f64) -> Self {
+    fn mul(self, other: T) -> Self {
         Self {
             r: self.r * other,
             g: self.g * other,
@@ -137,7 +151,7 @@ impl Mul<f64> for Color {
     }
 }
 
-impl Mul<Color> for Color {
+impl<T: Float> Mul<GenericColor<T>> for GenericColor<T> {
     type Output = Self;
 
     fn mul(self, other: Self) -> Self {
@@ -149,12 +163,12 @@ impl Mul<Color> for Color {
     }
 }
 
-impl Zero for Color {
+impl<T: ApproxScalar> Zero for GenericColor<T> {
     fn zero() -> Self {
         Self {
-            r: 0.0,
-            g: 0.0,
-            b: 0.0,
+            r: T::zero(),
+            g: T::zero(),
+            b: T::zero(),
         }
     }
 
@@ -170,9 +184,347 @@ pub struct ColorInt {
     pub b: u32,
 }
 
+// the color spaces below all hold three f64 components and compare like
+// `Color` does, within the crate's `EPSILON`.
+macro_rules! color_space {
+    ($(#[$doc:meta])* $name:ident { $a:ident, $b:ident, $c:ident }) => {
+        $(#[$doc])*
+        #[derive(Clone, Copy, Debug)]
+        pub struct $name {
+            pub $a: f64,
+            pub $b: f64,
+            pub $c: f64,
+        }
+
+        impl $name {
+            pub fn new($a: f64, $b: f64, $c: f64) -> Self {
+                Self { $a, $b, $c }
+            }
+        }
+
+        impl ApproxEq for $name {
+            type Scalar = f64;
+
+            fn all_components<F>(&self, other: &Self, f: F) -> bool
+            where
+                F: Fn(&f64, &f64) -> bool,
+            {
+                f(&self.$a, &other.$a) && f(&self.$b, &other.$b) && f(&self.$c, &other.$c)
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.approx_eq(other)
+            }
+        }
+    };
+}
+
+color_space!(
+    /// hue in degrees `[0, 360)`, saturation and value in `[0, 1]`.
+    Hsv { h, s, v }
+);
+color_space!(
+    /// hue in degrees `[0, 360)`, saturation and lightness in `[0, 1]`.
+    Hsl { h, s, l }
+);
+color_space!(
+    /// cie 1931 xyz with a d65 white point, `y` being relative luminance.
+    Xyz { x, y, z }
+);
+color_space!(
+    /// cie l*a*b* relative to d65, `l` running from 0 to 100.
+    Lab { l, a, b }
+);
+color_space!(
+    /// björn ottosson's perceptual oklab space, `l` running from 0 to 1.
+    Oklab { l, a, b }
+);
+
+/// the d65 white point in xyz.
+pub const D65: Xyz = Xyz {
+    x: 0.95047,
+    y: 1.0,
+    z: 1.08883,
+};
+
+// hsv and hsl share the hue calculation, and `chroma_to_rgb` undoes it.
+fn hue(c: Color, max: f64, delta: f64) -> f64 {
+    if delta == 0.0 {
+        return 0.0;
+    }
+
+    let h = if max == c.r {
+        ((c.g - c.b) / delta).rem_euclid(6.0)
+    } else if max == c.g {
+        (c.b - c.r) / delta + 2.0
+    } else {
+        (c.r - c.g) / delta + 4.0
+    };
+
+    h * 60.0
+}
+
+fn chroma_to_rgb(h: f64, chroma: f64, m: f64) -> Color {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    Color::new(r + m, g + m, b + m)
+}
+
+// the cie lab companding function and its inverse.
+fn lab_f(t: f64) -> f64 {
+    let delta: f64 = 6.0 / 29.0;
+    if t > delta.powi(3) {
+        t.cbrt()
+    } else {
+        t / (3.0 * delta * delta) + 4.0 / 29.0
+    }
+}
+
+fn lab_f_inverse(t: f64) -> f64 {
+    let delta = 6.0 / 29.0;
+    if t > delta {
+        t.powi(3)
+    } else {
+        3.0 * delta * delta * (t - 4.0 / 29.0)
+    }
+}
+
+/// conversions between the renderer's linear rgb (srgb primaries, d65 white)
+/// and other color spaces. hsv and hsl are computed directly on the linear
+/// values, so encode first if you want them to match an image editor.
+impl Color {
+    pub fn to_hsv(self) -> Hsv {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let delta = max - min;
+        let s = if max == 0.0 { 0.0 } else { delta / max };
+
+        Hsv::new(hue(self, max, delta), s, max)
+    }
+
+    pub fn from_hsv(hsv: Hsv) -> Color {
+        let chroma = hsv.v * hsv.s;
+        chroma_to_rgb(hsv.h, chroma, hsv.v - chroma)
+    }
+
+    pub fn to_hsl(self) -> Hsl {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let delta = max - min;
+        let l = (max + min) / 2.0;
+        let s = if delta == 0.0 {
+            0.0
+        } else {
+            delta / (1.0 - (2.0 * l - 1.0).abs())
+        };
+
+        Hsl::new(hue(self, max, delta), s, l)
+    }
+
+    pub fn from_hsl(hsl: Hsl) -> Color {
+        let chroma = (1.0 - (2.0 * hsl.l - 1.0).abs()) * hsl.s;
+        chroma_to_rgb(hsl.h, chroma, hsl.l - chroma / 2.0)
+    }
+
+    pub fn to_xyz(self) -> Xyz {
+        let (r, g, b) = (self.r, self.g, self.b);
+        Xyz::new(
+            0.4124564 * r + 0.3575761 * g + 0.1804375 * b,
+            0.2126729 * r + 0.7151522 * g + 0.0721750 * b,
+            0.0193339 * r + 0.1191920 * g + 0.9503041 * b,
+        )
+    }
+
+    pub fn from_xyz(xyz: Xyz) -> Color {
+        let (x, y, z) = (xyz.x, xyz.y, xyz.z);
+        Color::new(
+            3.2404542 * x - 1.5371385 * y - 0.4985314 * z,
+            -0.9692660 * x + 1.8760108 * y + 0.0415560 * z,
+            0.0556434 * x - 0.2040259 * y + 1.0572252 * z,
+        )
+    }
+
+    pub fn to_lab(self) -> Lab {
+        let xyz = self.to_xyz();
+        let (fx, fy, fz) = (
+            lab_f(xyz.x / D65.x),
+            lab_f(xyz.y / D65.y),
+            lab_f(xyz.z / D65.z),
+        );
+
+        Lab::new(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+    }
+
+    pub fn from_lab(lab: Lab) -> Color {
+        let fy = (lab.l + 16.0) / 116.0;
+        let fx = fy + lab.a / 500.0;
+        let fz = fy - lab.b / 200.0;
+
+        Color::from_xyz(Xyz::new(
+            D65.x * lab_f_inverse(fx),
+            D65.y * lab_f_inverse(fy),
+            D65.z * lab_f_inverse(fz),
+        ))
+    }
+
+    pub fn to_oklab(self) -> Oklab {
+        let (r, g, b) = (self.r, self.g, self.b);
+        let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+        let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+        let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+        Oklab::new(
+            0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+        )
+    }
+
+    pub fn from_oklab(lab: Oklab) -> Color {
+        let l = (lab.l + 0.3963377774 * lab.a + 0.2158037573 * lab.b).powi(3);
+        let m = (lab.l - 0.1055613458 * lab.a - 0.0638541728 * lab.b).powi(3);
+        let s = (lab.l - 0.0894841775 * lab.a - 1.2914855480 * lab.b).powi(3);
+
+        Color::new(
+            4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+            -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+            -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+        )
+    }
+
+    /// the ciede2000 perceptual difference between two colors. around 1.0 is
+    /// the smallest difference most people notice.
+    pub fn delta_e2000(self, other: Color) -> f64 {
+        self.to_lab().delta_e2000(other.to_lab())
+    }
+}
+
+/// the cie 1931 2° standard observer at `wavelength` nanometres, using the
+/// multi-lobe gaussian fit from wyman, sloan and shirley's "simple analytic
+/// approximations to the cie xyz color matching functions".
+pub fn cie_1931_cmf(wavelength: f64) -> Xyz {
+    let g = |mu: f64, below: f64, above: f64| {
+        let sigma = if wavelength < mu { below } else { above };
+        (-0.5 * ((wavelength - mu) / sigma).powi(2)).exp()
+    };
+
+    Xyz::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+impl Color {
+    /// the color of a blackbody radiator at `temperature` kelvin, e.g. 3200
+    /// for tungsten or 6500 for daylight.
+    ///
+    /// planck's law is integrated against the cie 1931 observer over 360 to
+    /// 830nm and the result converted to linear rgb. components the rgb gamut
+    /// can't reach are clipped to 0, and the brightest is scaled to 1.0, so
+    /// multiply by an intensity to use it as a light.
+    pub fn from_kelvin(temperature: f64) -> Color {
+        // hc / k, in nanometre kelvin.
+        const C2: f64 = 1.438_776_9e7;
+
+        let mut xyz = Xyz::new(0.0, 0.0, 0.0);
+        for wavelength in (360..=830).map(|w| w as f64) {
+            let radiance = wavelength.powi(-5) / ((C2 / (wavelength * temperature)).exp() - 1.0);
+            let cmf = cie_1931_cmf(wavelength);
+            xyz.x += cmf.x * radiance;
+            xyz.y += cmf.y * radiance;
+            xyz.z += cmf.z * radiance;
+        }
+
+        let rgb = Color::from_xyz(xyz);
+        let rgb = Color::new(rgb.r.max(0.0), rgb.g.max(0.0), rgb.b.max(0.0));
+        let max = rgb.r.max(rgb.g).max(rgb.b);
+        if max > 0.0 && max.is_finite() {
+            rgb * (1.0 / max)
+        } else {
+            Color::new(0.0, 0.0, 0.0)
+        }
+    }
+}
+
+impl Lab {
+    /// ciede2000, following sharma, wu and dalal's implementation notes.
+    pub fn delta_e2000(self, other: Lab) -> f64 {
+        let pow7 = |x: f64| x.powi(7);
+        let hue_angle = |a: f64, b: f64| {
+            if a == 0.0 && b == 0.0 {
+                0.0
+            } else {
+                b.atan2(a).to_degrees().rem_euclid(360.0)
+            }
+        };
+
+        let c_bar = (self.a.hypot(self.b) + other.a.hypot(other.b)) / 2.0;
+        let g = 0.5 * (1.0 - (pow7(c_bar) / (pow7(c_bar) + pow7(25.0))).sqrt());
+
+        let (a1, a2) = ((1.0 + g) * self.a, (1.0 + g) * other.a);
+        let (c1, c2) = (a1.hypot(self.b), a2.hypot(other.b));
+        let (h1, h2) = (hue_angle(a1, self.b), hue_angle(a2, other.b));
+
+        let delta_l = other.l - self.l;
+        let delta_c = c2 - c1;
+        let delta_h = if c1 * c2 == 0.0 {
+            0.0
+        } else if (h2 - h1).abs() <= 180.0 {
+            h2 - h1
+        } else if h2 - h1 > 180.0 {
+            h2 - h1 - 360.0
+        } else {
+            h2 - h1 + 360.0
+        };
+        let delta_big_h = 2.0 * (c1 * c2).sqrt() * (delta_h.to_radians() / 2.0).sin();
+
+        let l_bar = (self.l + other.l) / 2.0;
+        let c_bar = (c1 + c2) / 2.0;
+        let h_bar = if c1 * c2 == 0.0 {
+            h1 + h2
+        } else if (h1 - h2).abs() <= 180.0 {
+            (h1 + h2) / 2.0
+        } else if h1 + h2 < 360.0 {
+            (h1 + h2 + 360.0) / 2.0
+        } else {
+            (h1 + h2 - 360.0) / 2.0
+        };
+
+        let cos = |degrees: f64| degrees.to_radians().cos();
+        let t = 1.0 - 0.17 * cos(h_bar - 30.0)
+            + 0.24 * cos(2.0 * h_bar)
+            + 0.32 * cos(3.0 * h_bar + 6.0)
+            - 0.20 * cos(4.0 * h_bar - 63.0);
+        let delta_theta = 30.0 * (-((h_bar - 275.0) / 25.0).powi(2)).exp();
+        let r_c = 2.0 * (pow7(c_bar) / (pow7(c_bar) + pow7(25.0))).sqrt();
+        let s_l = 1.0 + 0.015 * (l_bar - 50.0).powi(2) / (20.0 + (l_bar - 50.0).powi(2)).sqrt();
+        let s_c = 1.0 + 0.045 * c_bar;
+        let s_h = 1.0 + 0.015 * c_bar * t;
+        let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+        let (l, c, h) = (delta_l / s_l, delta_c / s_c, delta_big_h / s_h);
+        (l * l + c * c + h * h + r_t * c * h).sqrt()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::assert_approx_eq;
+    use float_eq::{assert_float_eq, assert_float_ne};
 
     #[test]
     fn test_color() {
@@ -183,6 +535,54 @@ mod tests {
         assert_eq!(c.b, 1.7);
     }
 
+    #[test]
+    fn test_color_to_int() {
+        let c = Color::new(0.5, 0.001, 0.998).to_int(255);
+        assert_eq!((c.r, c.g, c.b), (128, 0, 254));
+
+        let c = Color::new(-0.5, 1.5, 0.0021).to_int(255);
+        assert_eq!((c.r, c.g, c.b), (0, 255, 1));
+
+        let c = Color::new(0.25, 0.5, 0.75).to_int(1);
+        assert_eq!((c.r, c.g, c.b), (0, 1, 1));
+
+        let c = Color::new(f64::NAN, 0.5, f64::NAN).to_int(255);
+        assert_eq!((c.r, c.g, c.b), (0, 128, 0));
+    }
+
+    #[test]
+    fn test_color_to_int_dithered() {
+        let c = Color::new(0.5, 0.001, 0.998).to_int_dithered(255, 0.5);
+        assert_eq!((c.r, c.g, c.b), (128, 0, 254));
+
+        let c = Color::new(0.1, 0.2, 0.5).to_int_dithered(1, 0.15);
+        assert_eq!((c.r, c.g, c.b), (0, 1, 1));
+
+        let c = Color::new(-0.5, 1.5, 1.0).to_int_dithered(255, 1.0);
+        assert_eq!((c.r, c.g, c.b), (0, 255, 255));
+
+        let c = Color::new(f64::NAN, 0.0, 0.0).to_int_dithered(255, 0.5);
+        assert_eq!(c.r, 0);
+    }
+
+    #[test]
+    fn test_color_float_eq() {
+        let a = Color::new(0.9, 0.6, 0.75);
+        let b = Color::new(0.9, 0.6, 0.75 + f64::EPSILON / 2.0);
+
+        assert_float_eq!(a, b, ulps_all <= 1);
+        assert_float_eq!(
+            a,
+            b,
+            abs <= Color {
+                r: 0.0,
+                g: 0.0,
+                b: 1e-12
+            }
+        );
+        assert_float_ne!(a, b, ulps <= ColorUlps { r: 0, g: 0, b: 0 });
+    }
+
     #[test]
     fn test_add_color() {
         let a = Color::new(0.9, 0.6, 0.75);
@@ -218,4 +618,131 @@ mod tests {
 
         assert_eq!(a * b, result);
     }
+
+    #[test]
+    fn test_color_f32() {
+        let a = Color32::new(1.0, 0.2, 0.4);
+        let b = Color32::new(0.9, 1.0, 0.1);
+
+        assert_eq!(a * b, Color32::new(0.9, 0.2, 0.04));
+        assert_eq!(Color32::zero() + a, a);
+    }
+
+    #[test]
+    fn test_hsv_hsl() {
+        let orange = Color::new(1.0, 0.5, 0.0);
+        assert_eq!(orange.to_hsv(), Hsv::new(30.0, 1.0, 1.0));
+        assert_eq!(orange.to_hsl(), Hsl::new(30.0, 1.0, 0.5));
+
+        let c = Color::new(0.2, 0.4, 0.6);
+        assert_eq!(c.to_hsv(), Hsv::new(210.0, 2.0 / 3.0, 0.6));
+        assert_eq!(c.to_hsl(), Hsl::new(210.0, 0.5, 0.4));
+        assert_eq!(Color::new(0.7, 0.1, 0.3).to_hsv().h, 340.0);
+
+        let grey = Color::new(0.5, 0.5, 0.5);
+        assert_eq!(grey.to_hsl(), Hsl::new(0.0, 0.0, 0.5));
+
+        for c in [
+            orange,
+            c,
+            grey,
+            Color::new(0.7, 0.1, 0.3),
+            Color::new(0.1, 0.9, 0.4),
+        ] {
+            assert_eq!(Color::from_hsv(c.to_hsv()), c);
+            assert_eq!(Color::from_hsl(c.to_hsl()), c);
+        }
+    }
+
+    #[test]
+    fn test_xyz_lab() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        assert_approx_eq!(white.to_xyz(), D65, abs <= 1e-6);
+        assert_eq!(white.to_lab(), Lab::new(100.0, 0.0, 0.0));
+
+        let red = Color::new(1.0, 0.0, 0.0).to_lab();
+        assert_approx_eq!(red, Lab::new(53.2408, 80.0925, 67.2032), abs <= 0.001);
+
+        for c in [Color::new(0.2, 0.4, 0.6), Color::new(0.001, 0.002, 0.0005)] {
+            assert_eq!(Color::from_xyz(c.to_xyz()), c);
+            assert_eq!(Color::from_lab(c.to_lab()), c);
+        }
+    }
+
+    #[test]
+    fn test_oklab() {
+        let white = Color::new(1.0, 1.0, 1.0).to_oklab();
+        assert_approx_eq!(white, Oklab::new(1.0, 0.0, 0.0), abs <= 1e-4);
+
+        let red = Color::new(1.0, 0.0, 0.0).to_oklab();
+        assert_approx_eq!(red, Oklab::new(0.62796, 0.22486, 0.12585), abs <= 1e-4);
+
+        let c = Color::new(0.2, 0.4, 0.6);
+        assert_eq!(Color::from_oklab(c.to_oklab()), c);
+    }
+
+    #[test]
+    fn test_delta_e2000() {
+        // reference pairs from sharma, wu and dalal's test data.
+        let pairs = [
+            ((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485), 2.0425),
+            ((50.0, 0.0, 0.0), (50.0, -1.0, 2.0), 2.3669),
+            ((50.0, 2.5, 0.0), (73.0, 25.0, -18.0), 27.1492),
+            (
+                (60.2574, -34.0099, 36.2677),
+                (60.4626, -34.1751, 39.4387),
+                1.2644,
+            ),
+            (
+                (22.7233, 20.0904, -46.6940),
+                (23.0331, 14.9730, -42.5619),
+                2.0373,
+            ),
+            (
+                (90.8027, -2.0831, 1.4410),
+                (91.1528, -1.6435, 0.0447),
+                1.4441,
+            ),
+        ];
+
+        for ((l1, a1, b1), (l2, a2, b2), expected) in pairs {
+            let (x, y) = (Lab::new(l1, a1, b1), Lab::new(l2, a2, b2));
+            assert_approx_eq!(x.delta_e2000(y), expected, abs <= 1e-4);
+            assert_approx_eq!(y.delta_e2000(x), expected, abs <= 1e-4);
+        }
+
+        let c = Color::new(0.3, 0.5, 0.7);
+        assert_eq!(c.delta_e2000(c), 0.0);
+    }
+
+    #[test]
+    fn test_cie_1931_cmf() {
+        // peaks of the tabulated functions.
+        assert_approx_eq!(cie_1931_cmf(555.0).y, 1.0, abs <= 0.01);
+        assert_approx_eq!(cie_1931_cmf(600.0).x, 1.0622, abs <= 0.01);
+        assert_approx_eq!(cie_1931_cmf(445.0).z, 1.7826, abs <= 0.03);
+        assert!(cie_1931_cmf(830.0).y < 0.001);
+    }
+
+    #[test]
+    fn test_from_kelvin() {
+        // chromaticity should sit on the planckian locus.
+        let xyz = Color::from_kelvin(6500.0).to_xyz();
+        let sum = xyz.x + xyz.y + xyz.z;
+        assert_approx_eq!(xyz.x / sum, 0.3135, abs <= 0.002);
+        assert_approx_eq!(xyz.y / sum, 0.3236, abs <= 0.002);
+
+        let daylight = Color::from_kelvin(6500.0);
+        assert!(daylight.r > 0.9 && daylight.g > 0.9 && daylight.b > 0.9);
+
+        let tungsten = Color::from_kelvin(3200.0);
+        assert_eq!(tungsten.r, 1.0);
+        assert!(tungsten.g < tungsten.r && tungsten.b < tungsten.g);
+
+        let sky = Color::from_kelvin(15000.0);
+        assert_eq!(sky.b, 1.0);
+        assert!(sky.r < sky.g && sky.g < sky.b);
+
+        assert_eq!(Color::from_kelvin(0.0), Color::new(0.0, 0.0, 0.0));
+    }
 }
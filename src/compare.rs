@@ -0,0 +1,257 @@
+//! comparing renders, for regression tests that should shrug off numeric
+//! drift but catch real changes.
+//!
+//! everything works on the stored (premultiplied) pixels and panics if the
+//! canvases differ in size.
+
+use crate::{canvas::Canvas, color::Color};
+
+/// how far apart two renders are.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiffStats {
+    /// the mean squared error over every channel of every pixel.
+    pub mse: f64,
+    /// the peak signal to noise ratio in decibels, taking 1.0 as the peak.
+    /// infinite for identical canvases.
+    pub psnr: f64,
+    /// the largest difference in any one channel.
+    pub max_error: f64,
+    /// the mean structural similarity of the luminance, from 1.0 for identical
+    /// canvases down towards 0 as the structure diverges.
+    pub ssim: f64,
+}
+
+/// the side of the gaussian window ssim is measured over, and its spread.
+const SSIM_WINDOW: usize = 11;
+const SSIM_SIGMA: f64 = 1.5;
+
+/// the stabilizing constants from the ssim paper, for a dynamic range of 1.
+const SSIM_C1: f64 = 0.01 * 0.01;
+const SSIM_C2: f64 = 0.03 * 0.03;
+
+fn assert_same_size(a: &Canvas, b: &Canvas) {
+    assert!(
+        a.width == b.width && a.height == b.height,
+        "can't compare a {}x{} canvas with a {}x{} one",
+        a.width,
+        a.height,
+        b.width,
+        b.height
+    );
+}
+
+fn channel_error(a: Color, b: Color) -> f64 {
+    (a.r - b.r)
+        .abs()
+        .max((a.g - b.g).abs())
+        .max((a.b - b.b).abs())
+}
+
+/// the "hot" color map: black through red and yellow to white as `t` goes
+/// from 0 to 1.
+fn heat(t: f64) -> Color {
+    let t = t.clamp(0.0, 1.0) * 3.0;
+    Color::new(t.min(1.0), (t - 1.0).clamp(0.0, 1.0), (t - 2.0).max(0.0))
+}
+
+impl Canvas {
+    /// a heatmap of where `self` and `other` differ, scaled so the largest
+    /// difference is white. identical canvases give a black one.
+    pub fn diff(&self, other: &Canvas) -> Canvas {
+        let max = self.max_error(other);
+        self.diff_scaled(other, if max > 0.0 { max } else { 1.0 })
+    }
+
+    /// a heatmap of where `self` and `other` differ, with a difference of
+    /// `full_scale` in any channel shown as white. a fixed scale makes heatmaps
+    /// from different runs comparable.
+    pub fn diff_scaled(&self, other: &Canvas, full_scale: f64) -> Canvas {
+        assert_same_size(self, other);
+
+        let mut out = Canvas::new(self.width, self.height);
+        for (p, (&a, &b)) in out
+            .pixels
+            .iter_mut()
+            .zip(self.pixels.iter().zip(other.pixels.iter()))
+        {
+            *p = heat(channel_error(a, b) / full_scale);
+        }
+        out
+    }
+
+    pub fn mse(&self, other: &Canvas) -> f64 {
+        assert_same_size(self, other);
+        if self.pixels.is_empty() {
+            return 0.0;
+        }
+
+        let total: f64 = self
+            .pixels
+            .iter()
+            .zip(other.pixels.iter())
+            .map(|(&a, &b)| {
+                let d = a - b;
+                d.r * d.r + d.g * d.g + d.b * d.b
+            })
+            .sum();
+        total / (3 * self.pixels.len()) as f64
+    }
+
+    pub fn psnr(&self, other: &Canvas) -> f64 {
+        let mse = self.mse(other);
+        if mse == 0.0 {
+            f64::INFINITY
+        } else {
+            -10.0 * mse.log10()
+        }
+    }
+
+    pub fn max_error(&self, other: &Canvas) -> f64 {
+        assert_same_size(self, other);
+        self.pixels
+            .iter()
+            .zip(other.pixels.iter())
+            .map(|(&a, &b)| channel_error(a, b))
+            .fold(0.0, f64::max)
+    }
+
+    /// the mean structural similarity of the two canvases' luminance, measured
+    /// over an 11x11 gaussian window around every pixel. windows are cut off at
+    /// the canvas edges.
+    pub fn ssim(&self, other: &Canvas) -> f64 {
+        assert_same_size(self, other);
+        if self.pixels.is_empty() {
+            return 1.0;
+        }
+
+        let luma = |c: &Canvas| -> Vec<f64> { c.pixels.iter().map(|p| p.to_xyz().y).collect() };
+        let (a, b) = (luma(self), luma(other));
+
+        let half = (SSIM_WINDOW / 2) as i64;
+        let kernel: Vec<f64> = (-half..=half)
+            .map(|i| (-((i * i) as f64) / (2.0 * SSIM_SIGMA * SSIM_SIGMA)).exp())
+            .collect();
+
+        let (w, h) = (self.width as i64, self.height as i64);
+        let mut total = 0.0;
+        for y in 0..h {
+            for x in 0..w {
+                let (mut sum, mut mean_a, mut mean_b) = (0.0, 0.0, 0.0);
+                let (mut aa, mut bb, mut ab) = (0.0, 0.0, 0.0);
+
+                for j in (y - half).max(0)..(y + half + 1).min(h) {
+                    for i in (x - half).max(0)..(x + half + 1).min(w) {
+                        let weight =
+                            kernel[(i - x + half) as usize] * kernel[(j - y + half) as usize];
+                        let n = (i + j * w) as usize;
+                        sum += weight;
+                        mean_a += weight * a[n];
+                        mean_b += weight * b[n];
+                        aa += weight * a[n] * a[n];
+                        bb += weight * b[n] * b[n];
+                        ab += weight * a[n] * b[n];
+                    }
+                }
+
+                let (mean_a, mean_b) = (mean_a / sum, mean_b / sum);
+                let var_a = aa / sum - mean_a * mean_a;
+                let var_b = bb / sum - mean_b * mean_b;
+                let covariance = ab / sum - mean_a * mean_b;
+
+                total += ((2.0 * mean_a * mean_b + SSIM_C1) * (2.0 * covariance + SSIM_C2))
+                    / ((mean_a * mean_a + mean_b * mean_b + SSIM_C1) * (var_a + var_b + SSIM_C2));
+            }
+        }
+        total / self.pixels.len() as f64
+    }
+
+    /// every statistic at once.
+    pub fn diff_stats(&self, other: &Canvas) -> DiffStats {
+        DiffStats {
+            mse: self.mse(other),
+            psnr: self.psnr(other),
+            max_error: self.max_error(other),
+            ssim: self.ssim(other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    /// a 16x16 checkerboard of 4x4 squares, with `level` as the light squares.
+    fn checker(level: f64) -> Canvas {
+        let mut c = Canvas::new(16, 16);
+        for y in 0..16 {
+            for x in 0..16 {
+                if (x / 4 + y / 4) % 2 == 1 {
+                    c.write_pixel(x, y, Color::new(level, level, level));
+                }
+            }
+        }
+        c
+    }
+
+    #[test]
+    fn test_identical() {
+        let c = checker(1.0);
+        let stats = c.diff_stats(&c);
+
+        assert_eq!(stats.mse, 0.0);
+        assert_eq!(stats.psnr, f64::INFINITY);
+        assert_eq!(stats.max_error, 0.0);
+        assert_approx_eq!(stats.ssim, 1.0);
+        assert!(c
+            .diff(&c)
+            .pixels
+            .iter()
+            .all(|&p| p == Color::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_mse_and_psnr() {
+        let a = Canvas::new(2, 1);
+        let mut b = Canvas::new(2, 1);
+        b.write_pixel(0, 0, Color::new(0.1, 0.0, 0.0));
+        b.write_pixel(1, 0, Color::new(0.0, 0.0, -0.2));
+
+        assert_approx_eq!(a.mse(&b), 0.05 / 6.0);
+        assert_approx_eq!(a.psnr(&b), 10.0 * (6.0_f64 / 0.05).log10());
+        assert_approx_eq!(a.max_error(&b), 0.2);
+    }
+
+    #[test]
+    fn test_ssim() {
+        let a = checker(1.0);
+        let drift = a.map(|p| p + Color::new(0.001, 0.001, 0.001));
+
+        // a tiny uniform offset barely registers, losing the pattern does.
+        assert!(a.ssim(&drift) > 0.99);
+        assert!(a.ssim(&checker(0.5)) < a.ssim(&drift));
+        assert!(a.ssim(&Canvas::new(16, 16)) < 0.1);
+    }
+
+    #[test]
+    fn test_diff_heatmap() {
+        let a = Canvas::new(3, 1);
+        let mut b = Canvas::new(3, 1);
+        b.write_pixel(1, 0, Color::new(0.0, 0.25, 0.0));
+        b.write_pixel(2, 0, Color::new(0.5, 0.0, 0.0));
+
+        let heat = a.diff(&b);
+        assert_eq!(heat[(0, 0)], Color::new(0.0, 0.0, 0.0));
+        assert_eq!(heat[(1, 0)], Color::new(1.0, 0.5, 0.0));
+        assert_eq!(heat[(2, 0)], Color::new(1.0, 1.0, 1.0));
+
+        let heat = a.diff_scaled(&b, 1.0);
+        assert_eq!(heat[(2, 0)], Color::new(1.0, 0.5, 0.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_size_mismatch() {
+        Canvas::new(2, 2).mse(&Canvas::new(1, 2));
+    }
+}
@@ -0,0 +1,362 @@
+//! convolution with arbitrary and separable kernels, plus the usual blurs,
+//! sharpening and edge detection built on top. building blocks for bloom and
+//! denoising.
+//!
+//! kernels run over the premultiplied pixels and the alpha plane alike, except
+//! that opaque canvases stay exactly opaque.
+
+use crate::{canvas::Canvas, color::Color};
+
+/// what a kernel sees when it reaches past the edge of the canvas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    /// the nearest edge pixel, repeated.
+    Clamp,
+    /// the opposite edge, as if the canvas were tiled.
+    Wrap,
+    /// the canvas reflected about its edge, so `-1` reads `0` and `-2` reads
+    /// `1`.
+    Mirror,
+    /// transparent black.
+    Zero,
+}
+
+impl Edge {
+    /// maps a possibly out of range coordinate onto `0..len`, or `None` if it
+    /// should read as transparent black.
+    fn index(self, i: i64, len: usize) -> Option<usize> {
+        let n = len as i64;
+        if (0..n).contains(&i) {
+            return Some(i as usize);
+        }
+
+        match self {
+            Edge::Clamp => Some(i.clamp(0, n - 1) as usize),
+            Edge::Wrap => Some(i.rem_euclid(n) as usize),
+            Edge::Mirror => {
+                let i = i.rem_euclid(2 * n);
+                Some(if i < n { i } else { 2 * n - 1 - i } as usize)
+            }
+            Edge::Zero => None,
+        }
+    }
+}
+
+/// a rectangle of weights with odd sides, centred on the pixel being
+/// filtered. `weights` is in reading order, with `weights[0]` landing on the
+/// top left neighbour; it isn't flipped first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Kernel {
+    pub width: usize,
+    pub height: usize,
+    pub weights: Vec<f64>,
+}
+
+impl Kernel {
+    /// panics unless both sides are odd and there's one weight for each cell.
+    pub fn new(width: usize, height: usize, weights: Vec<f64>) -> Self {
+        assert!(
+            width % 2 == 1 && height % 2 == 1,
+            "kernel sides must be odd, found {}x{}",
+            width,
+            height
+        );
+        assert_eq!(
+            weights.len(),
+            width * height,
+            "a {}x{} kernel needs {} weights",
+            width,
+            height,
+            width * height
+        );
+
+        Self {
+            width,
+            height,
+            weights,
+        }
+    }
+
+    /// the outer product of a column and a row, e.g. to inspect a separable
+    /// filter as a whole.
+    pub fn from_separable(horizontal: &[f64], vertical: &[f64]) -> Self {
+        let weights = vertical
+            .iter()
+            .flat_map(|&v| horizontal.iter().map(move |&h| h * v))
+            .collect();
+        Self::new(horizontal.len(), vertical.len(), weights)
+    }
+
+    /// a 3x3 kernel that pushes each pixel away from its neighbours' average.
+    /// an `amount` of 0 leaves the image alone.
+    pub fn sharpen(amount: f64) -> Self {
+        let a = -amount;
+        Self::new(
+            3,
+            3,
+            vec![0.0, a, 0.0, a, 1.0 + 4.0 * amount, a, 0.0, a, 0.0],
+        )
+    }
+
+    /// the horizontal sobel operator, responding to vertical edges. positive
+    /// where the image gets brighter to the right.
+    pub fn sobel_x() -> Self {
+        Self::new(3, 3, vec![-1.0, 0.0, 1.0, -2.0, 0.0, 2.0, -1.0, 0.0, 1.0])
+    }
+
+    /// the vertical sobel operator, responding to horizontal edges. positive
+    /// where the image gets brighter downwards.
+    pub fn sobel_y() -> Self {
+        Self::new(3, 3, vec![-1.0, -2.0, -1.0, 0.0, 0.0, 0.0, 1.0, 2.0, 1.0])
+    }
+}
+
+/// the furthest a gaussian kernel reaches, whatever its sigma. past about
+/// 340 pixels the tails get cut short.
+pub const MAX_GAUSSIAN_RADIUS: i64 = 1024;
+
+/// a normalized gaussian reaching out to three standard deviations, which
+/// covers all but a sliver of its weight. a `sigma` of 0 or less, nan or
+/// infinite is a no-op.
+pub fn gaussian_weights(sigma: f64) -> Vec<f64> {
+    if !sigma.is_finite() || sigma <= 0.0 {
+        return vec![1.0];
+    }
+
+    let radius = ((3.0 * sigma).ceil() as i64).min(MAX_GAUSSIAN_RADIUS);
+    let weights: Vec<f64> = (-radius..=radius)
+        .map(|i| (-((i * i) as f64) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f64 = weights.iter().sum();
+    weights.iter().map(|w| w / total).collect()
+}
+
+/// `2 * radius + 1` equal weights.
+pub fn box_weights(radius: usize) -> Vec<f64> {
+    let n = 2 * radius + 1;
+    vec![1.0 / n as f64; n]
+}
+
+impl Canvas {
+    /// runs `kernel` over every pixel.
+    pub fn convolve(&self, kernel: &Kernel, edge: Edge) -> Canvas {
+        self.filtered(kernel.width, kernel.height, &kernel.weights, edge)
+    }
+
+    /// runs `horizontal` along the rows, then `vertical` down the columns.
+    /// much cheaper than the equivalent 2d kernel once it gets large. both
+    /// must have odd lengths.
+    pub fn convolve_separable(&self, horizontal: &[f64], vertical: &[f64], edge: Edge) -> Canvas {
+        assert!(
+            horizontal.len() % 2 == 1 && vertical.len() % 2 == 1,
+            "separable kernels must have odd lengths, found {} and {}",
+            horizontal.len(),
+            vertical.len()
+        );
+
+        self.filtered(horizontal.len(), 1, horizontal, edge)
+            .filtered(1, vertical.len(), vertical, edge)
+    }
+
+    /// builds a canvas where each pixel is the weighted sum of the `width` by
+    /// `height` block of pixels centred on it, `weights` being in reading
+    /// order. reads past the edges according to `edge`.
+    fn filtered(&self, width: usize, height: usize, weights: &[f64], edge: Edge) -> Canvas {
+        let (kx, ky) = ((width / 2) as i64, (height / 2) as i64);
+        let opaque = self.is_opaque();
+        let mut out = Canvas::new(self.width, self.height);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut color = Color::new(0.0, 0.0, 0.0);
+                let mut alpha = 0.0;
+
+                for (k, &w) in weights.iter().enumerate() {
+                    let tx = x as i64 + (k % width) as i64 - kx;
+                    let ty = y as i64 + (k / width) as i64 - ky;
+                    if let (Some(tx), Some(ty)) =
+                        (edge.index(tx, self.width), edge.index(ty, self.height))
+                    {
+                        let p = self.rgba(tx, ty);
+                        color += p.color * w;
+                        alpha += p.alpha * w;
+                    }
+                }
+
+                out[(x, y)] = color;
+                out.alpha[x + y * self.width] = if opaque { 1.0 } else { alpha.clamp(0.0, 1.0) };
+            }
+        }
+        out
+    }
+
+    /// a gaussian blur with standard deviation `sigma` pixels.
+    pub fn gaussian_blur(&self, sigma: f64) -> Canvas {
+        let weights = gaussian_weights(sigma);
+        self.convolve_separable(&weights, &weights, Edge::Clamp)
+    }
+
+    /// averages each pixel with everything within `radius` pixels along both
+    /// axes.
+    pub fn box_blur(&self, radius: usize) -> Canvas {
+        let weights = box_weights(radius);
+        self.convolve_separable(&weights, &weights, Edge::Clamp)
+    }
+
+    pub fn sharpen(&self, amount: f64) -> Canvas {
+        self.convolve(&Kernel::sharpen(amount), Edge::Clamp)
+    }
+
+    /// the magnitude of the sobel gradient in each channel, bright along edges
+    /// and black in flat areas.
+    pub fn sobel(&self) -> Canvas {
+        let gx = self.convolve(&Kernel::sobel_x(), Edge::Clamp);
+        let gy = self.convolve(&Kernel::sobel_y(), Edge::Clamp);
+
+        gx.zip(&gy, |a, b| {
+            Color::new(a.r.hypot(b.r), a.g.hypot(b.g), a.b.hypot(b.b))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    fn row(values: &[f64]) -> Canvas {
+        let mut c = Canvas::new(values.len(), 1);
+        for (p, &v) in c.pixels.iter_mut().zip(values) {
+            *p = Color::new(v, v, v);
+        }
+        c
+    }
+
+    fn reds(c: &Canvas) -> Vec<f64> {
+        c.pixels.iter().map(|p| p.r).collect()
+    }
+
+    #[test]
+    fn test_identity_kernel() {
+        let c = row(&[0.1, 0.7, 0.3, 2.0]);
+        let identity = Kernel::new(3, 3, vec![0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0]);
+
+        assert_eq!(c.convolve(&identity, Edge::Zero), c);
+    }
+
+    #[test]
+    fn test_edge_handling() {
+        // reads the right hand neighbour, so the last pixel reads past the edge.
+        let c = row(&[1.0, 2.0, 3.0]);
+        let shift = [0.0, 0.0, 1.0];
+
+        let read = |edge| reds(&c.convolve_separable(&shift, &[1.0], edge));
+        assert_eq!(read(Edge::Clamp), vec![2.0, 3.0, 3.0]);
+        assert_eq!(read(Edge::Wrap), vec![2.0, 3.0, 1.0]);
+        assert_eq!(read(Edge::Mirror), vec![2.0, 3.0, 3.0]);
+        assert_eq!(read(Edge::Zero), vec![2.0, 3.0, 0.0]);
+
+        assert_eq!(Edge::Mirror.index(-2, 3), Some(1));
+        assert_eq!(Edge::Mirror.index(4, 3), Some(1));
+        assert_eq!(Edge::Wrap.index(-1, 3), Some(2));
+    }
+
+    #[test]
+    fn test_gaussian_weights() {
+        let w = gaussian_weights(1.0);
+
+        assert_eq!(w.len(), 7);
+        assert_approx_eq!(w.iter().sum::<f64>(), 1.0);
+        assert_approx_eq!(w[0], w[6]);
+        assert!(w[3] > w[2] && w[2] > w[1]);
+        assert_eq!(gaussian_weights(0.0), vec![1.0]);
+        assert_eq!(
+            gaussian_weights(1e9).len(),
+            2 * MAX_GAUSSIAN_RADIUS as usize + 1
+        );
+    }
+
+    #[test]
+    fn test_separable_matches_2d() {
+        let mut c = Canvas::new(6, 5);
+        c.write_pixel(2, 2, Color::new(1.0, 0.5, 0.25));
+        c.write_pixel(5, 0, Color::new(0.0, 1.0, 0.0));
+
+        let w = gaussian_weights(0.8);
+        for edge in [Edge::Clamp, Edge::Wrap, Edge::Mirror, Edge::Zero] {
+            assert_eq!(
+                c.convolve_separable(&w, &w, edge),
+                c.convolve(&Kernel::from_separable(&w, &w), edge)
+            );
+        }
+    }
+
+    #[test]
+    fn test_blurs_preserve_flat_areas() {
+        let mut c = Canvas::new(5, 4);
+        c.pixels.fill(Color::new(0.2, 0.4, 0.6));
+
+        assert_eq!(c.gaussian_blur(1.5), c);
+        assert_eq!(c.box_blur(2), c);
+        assert_eq!(c.sharpen(0.5), c);
+        assert!(c
+            .sobel()
+            .pixels
+            .iter()
+            .all(|&p| p == Color::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_box_blur() {
+        let c = row(&[0.0, 3.0, 0.0, 0.0]);
+        assert_eq!(reds(&c.box_blur(1)), vec![1.0, 1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_sharpen() {
+        let c = row(&[0.5, 1.0, 0.5]);
+        let sharp = c.sharpen(1.0);
+
+        // the peak rises and its neighbours fall away from it.
+        assert_approx_eq!(sharp[(1, 0)].r, 2.0);
+        assert_approx_eq!(sharp[(0, 0)].r, 0.0);
+    }
+
+    #[test]
+    fn test_sobel() {
+        let c = row(&[0.0, 0.0, 1.0, 1.0]);
+        let edges = c.sobel();
+
+        assert_eq!(reds(&edges), vec![0.0, 4.0, 4.0, 0.0]);
+        assert!(edges.is_opaque());
+    }
+
+    #[test]
+    fn test_blur_transparent() {
+        let mut c = Canvas::new_transparent(3, 1);
+        c.write_pixel(1, 0, Color::new(1.0, 1.0, 1.0));
+
+        // coverage spreads out, but the transparent pixels don't darken it.
+        let blurred = c.box_blur(1);
+        assert_approx_eq!(blurred.alpha[0], 1.0 / 3.0);
+        assert_approx_eq!(blurred.alpha[1], 1.0 / 3.0);
+        assert_eq!(blurred.rgba(0, 0).to_straight(), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_even_kernel() {
+        Kernel::new(2, 1, vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_gaussian_non_finite_sigma() {
+        for sigma in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(gaussian_weights(sigma), vec![1.0]);
+        }
+
+        let mut c = Canvas::new(3, 3);
+        c.write_pixel(1, 1, Color::new(1.0, 0.5, 0.25));
+        assert_eq!(c.gaussian_blur(f64::NAN), c);
+    }
+}
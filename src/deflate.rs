@@ -0,0 +1,598 @@
+//! just enough zlib (RFC 1950) and deflate (RFC 1951) for the image formats:
+//! compression uses LZ77 with fixed Huffman codes, decompression handles every
+//! block type.
+
+use std::fmt;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// the order code length code lengths are sent in for dynamic blocks.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+// how many earlier positions with the same hash we try before giving up.
+const MAX_CHAIN: usize = 128;
+const HASH_BITS: u32 = 15;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeflateError {
+    UnexpectedEof,
+    InvalidHeader,
+    InvalidBlockType,
+    /// a stored block's length didn't match its one's complement.
+    InvalidStoredLength,
+    /// the Huffman code lengths don't describe a valid code, or a symbol
+    /// didn't decode to anything.
+    InvalidCode,
+    /// a back-reference pointed before the start of the output.
+    InvalidDistance,
+    ChecksumMismatch,
+    /// the data decompressed to more than the caller's limit.
+    TooLong,
+}
+
+impl fmt::Display for DeflateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            DeflateError::UnexpectedEof => "unexpected end of compressed data",
+            DeflateError::InvalidHeader => "invalid zlib header",
+            DeflateError::InvalidBlockType => "invalid deflate block type",
+            DeflateError::InvalidStoredLength => "stored block length doesn't match its complement",
+            DeflateError::InvalidCode => "invalid Huffman code",
+            DeflateError::InvalidDistance => "back-reference distance is too far back",
+            DeflateError::ChecksumMismatch => "adler-32 checksum mismatch",
+            DeflateError::TooLong => "decompressed data is longer than expected",
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for DeflateError {}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the most bytes we can sum before `b` could overflow.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+
+    (b << 16) | a
+}
+
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    // deflate with a 32K window, default compression level.
+    let mut out = vec![0x78, 0x9c];
+    out.extend(deflate(data));
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+pub fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>, DeflateError> {
+    zlib_decompress_limited(data, usize::MAX)
+}
+
+/// like `zlib_decompress`, but gives up with `TooLong` rather than inflating
+/// past `limit` bytes, for when the caller knows how much to expect.
+// `is_multiple_of` would need rust 1.87.
+#[allow(clippy::manual_is_multiple_of)]
+pub fn zlib_decompress_limited(data: &[u8], limit: usize) -> Result<Vec<u8>, DeflateError> {
+    let [cmf, flg, ..] = *data else {
+        return Err(DeflateError::UnexpectedEof);
+    };
+
+    let uses_dictionary = flg & 0x20 != 0;
+    if cmf & 0x0f != 8 || (cmf as u16 * 256 + flg as u16) % 31 != 0 || uses_dictionary {
+        return Err(DeflateError::InvalidHeader);
+    }
+
+    let (out, consumed) = inflate_with_length(&data[2..], limit)?;
+
+    let trailer = data
+        .get(2 + consumed..2 + consumed + 4)
+        .ok_or(DeflateError::UnexpectedEof)?;
+    if u32::from_be_bytes(trailer.try_into().unwrap()) != adler32(&out) {
+        return Err(DeflateError::ChecksumMismatch);
+    }
+
+    Ok(out)
+}
+
+struct BitWriter {
+    out: Vec<u8>,
+    buf: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            out: vec![],
+            buf: 0,
+            count: 0,
+        }
+    }
+
+    // deflate packs values starting from the least significant bit.
+    fn bits(&mut self, value: u32, n: u32) {
+        self.buf |= (value as u64) << self.count;
+        self.count += n;
+        while self.count >= 8 {
+            self.out.push(self.buf as u8);
+            self.buf >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // ...but Huffman codes are sent most significant bit first.
+    fn code(&mut self, code: u32, n: u32) {
+        self.bits(code.reverse_bits() >> (32 - n), n);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.buf as u8);
+        }
+        self.out
+    }
+}
+
+fn write_fixed_literal(w: &mut BitWriter, symbol: u16) {
+    match symbol {
+        0..=143 => w.code(0x30 + symbol as u32, 8),
+        144..=255 => w.code(0x190 + (symbol - 144) as u32, 9),
+        256..=279 => w.code((symbol - 256) as u32, 7),
+        _ => w.code(0xc0 + (symbol - 280) as u32, 8),
+    }
+}
+
+fn write_match(w: &mut BitWriter, length: usize, distance: usize) {
+    let code = LENGTH_BASE
+        .iter()
+        .rposition(|&b| b as usize <= length)
+        .unwrap();
+    write_fixed_literal(w, 257 + code as u16);
+    w.bits(
+        (length - LENGTH_BASE[code] as usize) as u32,
+        LENGTH_EXTRA[code] as u32,
+    );
+
+    let code = DIST_BASE
+        .iter()
+        .rposition(|&b| b as usize <= distance)
+        .unwrap();
+    w.code(code as u32, 5);
+    w.bits(
+        (distance - DIST_BASE[code] as usize) as u32,
+        DIST_EXTRA[code] as u32,
+    );
+}
+
+fn hash(data: &[u8], i: usize) -> usize {
+    let v = (data[i] as u32) << 16 | (data[i + 1] as u32) << 8 | data[i + 2] as u32;
+    (v.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+}
+
+/// compresses `data` into a single fixed-Huffman deflate block.
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut w = BitWriter::new();
+    // BFINAL = 1, BTYPE = 01 (fixed Huffman).
+    w.bits(1, 1);
+    w.bits(1, 2);
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; WINDOW_SIZE];
+    let insert = |i: usize, head: &mut Vec<usize>, prev: &mut Vec<usize>| {
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(data, i);
+            prev[i % WINDOW_SIZE] = head[h];
+            head[h] = i;
+        }
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let mut best_length = 0;
+        let mut best_distance = 0;
+
+        if i + MIN_MATCH <= data.len() {
+            let max_length = MAX_MATCH.min(data.len() - i);
+            let mut candidate = head[hash(data, i)];
+            let mut chain = 0;
+
+            while candidate != usize::MAX && i - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+                let length = data[candidate..]
+                    .iter()
+                    .zip(&data[i..i + max_length])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+
+                if length > best_length {
+                    best_length = length;
+                    best_distance = i - candidate;
+                    if length == max_length {
+                        break;
+                    }
+                }
+
+                let next = prev[candidate % WINDOW_SIZE];
+                // the chain slot may have been reused by a newer position.
+                if next == usize::MAX || next >= candidate {
+                    break;
+                }
+                candidate = next;
+                chain += 1;
+            }
+        }
+
+        if best_length >= MIN_MATCH {
+            write_match(&mut w, best_length, best_distance);
+            for j in i..i + best_length {
+                insert(j, &mut head, &mut prev);
+            }
+            i += best_length;
+        } else {
+            write_fixed_literal(&mut w, data[i] as u16);
+            insert(i, &mut head, &mut prev);
+            i += 1;
+        }
+    }
+
+    write_fixed_literal(&mut w, 256);
+    w.finish()
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u32,
+    count: u32,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, n: u32) -> Result<u32, DeflateError> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or(DeflateError::UnexpectedEof)?;
+            self.pos += 1;
+            self.buf |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+
+        let value = self.buf & ((1u64 << n) - 1) as u32;
+        self.buf >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    fn align_to_byte(&mut self) {
+        self.buf = 0;
+        self.count = 0;
+    }
+}
+
+// a canonical Huffman code, stored as the number of codes of each length and
+// the symbols sorted by code.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, DeflateError> {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // reject over-subscribed codes; incomplete ones are allowed, since a
+        // single distance code is legal.
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(DeflateError::InvalidCode);
+            }
+        }
+
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, r: &mut BitReader) -> Result<u16, DeflateError> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..16 {
+            code |= r.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(DeflateError::InvalidCode)
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+
+    (
+        Huffman::new(&lengths).unwrap(),
+        Huffman::new(&[5; 30]).unwrap(),
+    )
+}
+
+fn dynamic_codes(r: &mut BitReader) -> Result<(Huffman, Huffman), DeflateError> {
+    let literal_count = r.bits(5)? as usize + 257;
+    let distance_count = r.bits(5)? as usize + 1;
+    let code_length_count = r.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &i in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[i] = r.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; literal_count + distance_count];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = code_length_code.decode(r)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths[..i].last().ok_or(DeflateError::InvalidCode)?;
+                (previous, 3 + r.bits(2)? as usize)
+            }
+            17 => (0, 3 + r.bits(3)? as usize),
+            _ => (0, 11 + r.bits(7)? as usize),
+        };
+
+        if i + repeat > lengths.len() {
+            return Err(DeflateError::InvalidCode);
+        }
+        lengths[i..i + repeat].fill(value);
+        i += repeat;
+    }
+
+    if lengths[256] == 0 {
+        // no end-of-block code.
+        return Err(DeflateError::InvalidCode);
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..])?,
+    ))
+}
+
+fn inflate_block(
+    r: &mut BitReader,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+    limit: usize,
+) -> Result<(), DeflateError> {
+    loop {
+        if out.len() > limit {
+            return Err(DeflateError::TooLong);
+        }
+
+        let symbol = literals.decode(r)? as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let code = symbol - 257;
+                if code >= LENGTH_BASE.len() {
+                    return Err(DeflateError::InvalidCode);
+                }
+                let length =
+                    LENGTH_BASE[code] as usize + r.bits(LENGTH_EXTRA[code] as u32)? as usize;
+
+                let code = distances.decode(r)? as usize;
+                if code >= DIST_BASE.len() {
+                    return Err(DeflateError::InvalidCode);
+                }
+                let distance = DIST_BASE[code] as usize + r.bits(DIST_EXTRA[code] as u32)? as usize;
+
+                if distance > out.len() {
+                    return Err(DeflateError::InvalidDistance);
+                }
+
+                // copied byte by byte, since the match may overlap itself.
+                let start = out.len() - distance;
+                for j in 0..length {
+                    out.push(out[start + j]);
+                }
+            }
+        }
+    }
+}
+
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>, DeflateError> {
+    inflate_with_length(data, usize::MAX).map(|(out, _)| out)
+}
+
+// also returns how many bytes of `data` the stream took up.
+fn inflate_with_length(data: &[u8], limit: usize) -> Result<(Vec<u8>, usize), DeflateError> {
+    let mut r = BitReader {
+        data,
+        pos: 0,
+        buf: 0,
+        count: 0,
+    };
+    let mut out = vec![];
+
+    loop {
+        let last = r.bits(1)? == 1;
+
+        match r.bits(2)? {
+            0 => {
+                r.align_to_byte();
+                let header = data
+                    .get(r.pos..r.pos + 4)
+                    .ok_or(DeflateError::UnexpectedEof)?;
+                let length = u16::from_le_bytes([header[0], header[1]]);
+                let complement = u16::from_le_bytes([header[2], header[3]]);
+                if length != !complement {
+                    return Err(DeflateError::InvalidStoredLength);
+                }
+
+                r.pos += 4;
+                let stored = data
+                    .get(r.pos..r.pos + length as usize)
+                    .ok_or(DeflateError::UnexpectedEof)?;
+                out.extend_from_slice(stored);
+                r.pos += length as usize;
+            }
+            1 => {
+                let (literals, distances) = fixed_codes();
+                inflate_block(&mut r, &mut out, &literals, &distances, limit)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut r)?;
+                inflate_block(&mut r, &mut out, &literals, &distances, limit)?;
+            }
+            _ => return Err(DeflateError::InvalidBlockType),
+        }
+
+        if out.len() > limit {
+            return Err(DeflateError::TooLong);
+        }
+        if last {
+            // any bits left in the buffer belong to the final partial byte.
+            return Ok((out, r.pos));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_data() -> Vec<u8> {
+        let mut data = b"the quick brown fox jumps over the lazy dog. ".repeat(40);
+        data.extend((0..5000u32).map(|i| (i * i % 251) as u8));
+        data.extend(vec![7; 1000]);
+        data
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+    }
+
+    #[test]
+    fn test_deflate_roundtrip() {
+        for data in [vec![], vec![42], b"abcabcabcabc".to_vec(), sample_data()] {
+            assert_eq!(inflate(&deflate(&data)).unwrap(), data);
+            assert_eq!(zlib_decompress(&zlib_compress(&data)).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_deflate_compresses() {
+        let data = sample_data();
+
+        assert!(zlib_compress(&data).len() < data.len() / 2);
+    }
+
+    #[test]
+    fn test_inflate_stored_block() {
+        // a single final stored block holding "hi".
+        let data = [0x01, 0x02, 0x00, 0xfd, 0xff, b'h', b'i'];
+
+        assert_eq!(inflate(&data).unwrap(), b"hi");
+    }
+
+    #[test]
+    fn test_inflate_dynamic_block() {
+        // skewed pseudo-random text, which CPython's `zlib.compress(data, 9)`
+        // encodes with a dynamic Huffman block.
+        let mut x = 1u32;
+        let data: Vec<u8> = (0..300)
+            .map(|_| {
+                x = x.wrapping_mul(1103515245).wrapping_add(12345) & 0x7fffffff;
+                b"aaaaaaaabbbbccda"[(x >> 16 & 15) as usize]
+            })
+            .collect();
+        let compressed = [
+            0x78, 0xda, 0x45, 0x4f, 0xc9, 0x01, 0xc0, 0x20, 0x08, 0x9b, 0x35, 0xc7, 0xfe, 0x33,
+            0x14, 0x82, 0x50, 0x1e, 0x8a, 0x39, 0x08, 0xc2, 0x20, 0x09, 0x02, 0x82, 0x84, 0x2e,
+            0x63, 0xaa, 0x70, 0x15, 0xd5, 0x1c, 0xa8, 0x61, 0x5a, 0x5a, 0x3d, 0xa7, 0x1d, 0x48,
+            0xa2, 0x5b, 0x64, 0x51, 0xe3, 0xfd, 0x4d, 0x35, 0x20, 0x3a, 0x0d, 0x12, 0x93, 0xdb,
+            0x2f, 0x6c, 0x8a, 0x02, 0xb6, 0xb6, 0x0f, 0xaf, 0x5b, 0xb3, 0x51, 0x29, 0x6d, 0x78,
+            0x47, 0xe6, 0xe6, 0x86, 0xfc, 0x55, 0x0b, 0x79, 0x82, 0xe8, 0xc7, 0x27, 0xb3, 0x7e,
+            0x00, 0x9e, 0xf3, 0xe0, 0x6b, 0x22, 0x56, 0xdc, 0xca, 0xec, 0x4e, 0x12, 0x9f, 0x9c,
+            0x27, 0xcc, 0xfb, 0x03, 0x54, 0x05, 0x72, 0x74,
+        ];
+
+        assert_eq!((compressed[2] >> 1) & 3, 2);
+        assert_eq!(zlib_decompress(&compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_zlib_errors() {
+        assert_eq!(zlib_decompress(&[0x78]), Err(DeflateError::UnexpectedEof));
+        assert_eq!(
+            zlib_decompress(&[0x78, 0x00, 0x03, 0x00]),
+            Err(DeflateError::InvalidHeader)
+        );
+
+        let mut data = zlib_compress(b"some data");
+        *data.last_mut().unwrap() ^= 1;
+        assert_eq!(zlib_decompress(&data), Err(DeflateError::ChecksumMismatch));
+
+        let zeros = zlib_compress(&[0; 100000]);
+        assert_eq!(
+            zlib_decompress_limited(&zeros, 1000),
+            Err(DeflateError::TooLong)
+        );
+        assert_eq!(
+            zlib_decompress_limited(&zeros, 100000).unwrap().len(),
+            100000
+        );
+    }
+}
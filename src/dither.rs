@@ -0,0 +1,390 @@
+//! dithering for low bit depth exports, trading banding in smooth gradients
+//! for fine noise.
+//!
+//! dithering happens after the transfer function, on the encoded values that
+//! actually get quantized. the image writers always round, so to export a
+//! dithered image, write out `dithered` with the writer's depth and encoding.
+
+use crate::{
+    canvas::Canvas,
+    color::{Color, ColorInt},
+    transfer::TransferFunction,
+};
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dither {
+    /// plain rounding to the nearest level, exactly like `Color::to_int`.
+    None,
+    /// ordered dithering with a `2^n` by `2^n` bayer matrix. cheap and stable
+    /// between frames, but leaves a visible crosshatch. `n` is capped at 8,
+    /// which already has a threshold for every 16-bit level.
+    Bayer(u32),
+    /// ordered dithering with a tiled 64x64 blue noise mask. stable between
+    /// frames like bayer, without the pattern.
+    BlueNoise,
+    /// error diffusion, pushing each pixel's rounding error onto the pixels not
+    /// yet quantized. rows alternate direction to avoid drifting streaks.
+    FloydSteinberg,
+}
+
+const BLUE_NOISE_SIZE: usize = 64;
+const MAX_BAYER_ORDER: u32 = 8;
+
+/// the index matrix for an `n` by `n` bayer pattern, where `n` is a power of
+/// two, built up by repeatedly interleaving four copies of the half-size one.
+fn bayer_matrix(n: usize) -> Vec<usize> {
+    let mut m = vec![0];
+    let mut size = 1;
+    while size < n {
+        let next = size * 2;
+        let mut grown = vec![0; next * next];
+        for y in 0..next {
+            for x in 0..next {
+                let quadrant = [0, 2, 3, 1][(x / size) + 2 * (y / size)];
+                grown[x + y * next] = 4 * m[x % size + (y % size) * size] + quadrant;
+            }
+        }
+        m = grown;
+        size = next;
+    }
+    m
+}
+
+/// ranks every cell of an `n` by `n` torus with robert ulichney's
+/// void-and-cluster method, so that the cells up to any rank are spread out as
+/// evenly as possible.
+fn void_and_cluster(n: usize) -> Vec<usize> {
+    let size = n * n;
+    let sigma: f64 = 1.5;
+
+    // the gaussian filter, indexed by the wrapped offset between two cells.
+    let kernel: Vec<f64> = (0..size)
+        .map(|i| {
+            let (dx, dy) = (i % n, i / n);
+            let (dx, dy) = (dx.min(n - dx), dy.min(n - dy));
+            (-((dx * dx + dy * dy) as f64) / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let splat = |energy: &mut [f64], p: usize, sign: f64| {
+        let (px, py) = (p % n, p / n);
+        for (q, e) in energy.iter_mut().enumerate() {
+            let (dx, dy) = ((q % n + n - px) % n, (q / n + n - py) % n);
+            *e += sign * kernel[dx + dy * n];
+        }
+    };
+
+    // the most crowded cell that's `set`, or the emptiest that isn't.
+    let tightest = |pattern: &[bool], energy: &[f64], set: bool| -> usize {
+        (0..size)
+            .filter(|&i| pattern[i] == set)
+            .max_by(|&a, &b| {
+                let (a, b) = if set {
+                    (energy[a], energy[b])
+                } else {
+                    (-energy[a], -energy[b])
+                };
+                a.total_cmp(&b)
+            })
+            .unwrap()
+    };
+
+    // start from a sprinkling of points, then move points out of the tightest
+    // clusters into the largest voids until that stops changing anything.
+    let mut pattern = vec![false; size];
+    let mut energy = vec![0.0; size];
+    let mut state: u32 = 0x9e37_79b9;
+    let mut placed = 0;
+    while placed < size / 10 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let p = state as usize % size;
+        if !pattern[p] {
+            pattern[p] = true;
+            splat(&mut energy, p, 1.0);
+            placed += 1;
+        }
+    }
+
+    for _ in 0..size {
+        let cluster = tightest(&pattern, &energy, true);
+        pattern[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+
+        let void = tightest(&pattern, &energy, false);
+        pattern[void] = true;
+        splat(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; size];
+
+    // the initial points get the lowest ranks, tightest clusters last.
+    let (mut p, mut e) = (pattern.clone(), energy.clone());
+    for rank in (0..placed).rev() {
+        let cluster = tightest(&p, &e, true);
+        p[cluster] = false;
+        splat(&mut e, cluster, -1.0);
+        ranks[cluster] = rank;
+    }
+
+    // then fill the largest voids up to half way.
+    for rank in placed..size / 2 {
+        let void = tightest(&pattern, &energy, false);
+        pattern[void] = true;
+        splat(&mut energy, void, 1.0);
+        ranks[void] = rank;
+    }
+
+    // past half way the empty cells are the minority, so track their energy
+    // instead and fill the tightest clusters of them.
+    let mut empty: Vec<bool> = pattern.iter().map(|&set| !set).collect();
+    let mut energy = vec![0.0; size];
+    for i in (0..size).filter(|&i| empty[i]) {
+        splat(&mut energy, i, 1.0);
+    }
+    for rank in size / 2..size {
+        let cluster = tightest(&empty, &energy, true);
+        empty[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+        ranks[cluster] = rank;
+    }
+
+    ranks
+}
+
+fn blue_noise() -> &'static [usize] {
+    static NOISE: OnceLock<Vec<usize>> = OnceLock::new();
+    NOISE.get_or_init(|| void_and_cluster(BLUE_NOISE_SIZE))
+}
+
+impl Canvas {
+    /// encodes every pixel with `encoding` and quantizes it to `0..=max`,
+    /// dithering with `dither`. pixels are in reading order.
+    pub fn quantize(&self, max: u32, encoding: TransferFunction, dither: Dither) -> Vec<ColorInt> {
+        let encoded = self.pixels.iter().map(|&p| encoding.encode_color(p));
+
+        match dither {
+            Dither::None => encoded.map(|c| c.to_int(max)).collect(),
+            Dither::Bayer(order) => {
+                let n = 1 << order.min(MAX_BAYER_ORDER);
+                let m = bayer_matrix(n);
+                let cells = (n * n) as f64;
+                self.ordered(encoded, max, |x, y| {
+                    (m[x % n + (y % n) * n] as f64 + 0.5) / cells
+                })
+            }
+            Dither::BlueNoise => {
+                let noise = blue_noise();
+                let n = BLUE_NOISE_SIZE;
+                let cells = (n * n) as f64;
+                self.ordered(encoded, max, |x, y| {
+                    (noise[x % n + (y % n) * n] as f64 + 0.5) / cells
+                })
+            }
+            Dither::FloydSteinberg => self.diffused(encoded.collect(), max),
+        }
+    }
+
+    fn ordered<F>(
+        &self,
+        encoded: impl Iterator<Item = Color>,
+        max: u32,
+        threshold: F,
+    ) -> Vec<ColorInt>
+    where
+        F: Fn(usize, usize) -> f64,
+    {
+        encoded
+            .enumerate()
+            .map(|(i, c)| c.to_int_dithered(max, threshold(i % self.width, i / self.width)))
+            .collect()
+    }
+
+    fn diffused(&self, mut levels: Vec<Color>, max: u32) -> Vec<ColorInt> {
+        let (w, h) = (self.width, self.height);
+        let scale = max as f64;
+        for c in levels.iter_mut() {
+            *c = *c * scale;
+        }
+
+        let mut out = vec![ColorInt { r: 0, g: 0, b: 0 }; w * h];
+        for y in 0..h {
+            let reversed = y % 2 == 1;
+            for i in 0..w {
+                let x = if reversed { w - 1 - i } else { i };
+                let wanted = levels[x + y * w];
+                let wanted = Color::new(
+                    wanted.r.clamp(0.0, scale),
+                    wanted.g.clamp(0.0, scale),
+                    wanted.b.clamp(0.0, scale),
+                );
+                let q = (wanted * (1.0 / scale)).to_int(max);
+                out[x + y * w] = q;
+
+                let error = wanted - Color::new(q.r as f64, q.g as f64, q.b as f64);
+                let ahead = if reversed { -1 } else { 1 };
+                for (dx, dy, weight) in [
+                    (ahead, 0, 7.0),
+                    (-ahead, 1, 3.0),
+                    (0, 1, 5.0),
+                    (ahead, 1, 1.0),
+                ] {
+                    let (nx, ny) = (x as i64 + dx, y + dy);
+                    if nx >= 0 && (nx as usize) < w && ny < h {
+                        levels[nx as usize + ny * w] += error * (weight / 16.0);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// a copy of the canvas with every pixel snapped to the level `quantize`
+    /// picks for it, decoded back to linear. writing it out with the same
+    /// `max` and `encoding` reproduces the dithered levels exactly, so any
+    /// writer gets dithering for free.
+    ///
+    /// it works on the stored premultiplied colors, so it's exact for opaque
+    /// canvases but not for formats that write straight color with alpha.
+    pub fn dithered(&self, max: u32, encoding: TransferFunction, dither: Dither) -> Canvas {
+        let scale = 1.0 / max as f64;
+        let pixels = self
+            .quantize(max, encoding, dither)
+            .iter()
+            .map(|q| {
+                let level = Color::new(q.r as f64, q.g as f64, q.b as f64) * scale;
+                encoding.decode_color(level)
+            })
+            .collect();
+
+        Canvas {
+            width: self.width,
+            height: self.height,
+            pixels,
+            alpha: self.alpha.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    fn flat(width: usize, height: usize, value: f64) -> Canvas {
+        let mut c = Canvas::new(width, height);
+        c.pixels.fill(Color::new(value, value, value));
+        c
+    }
+
+    fn mean_red(levels: &[ColorInt], max: u32) -> f64 {
+        levels.iter().map(|q| q.r as f64).sum::<f64>() / (levels.len() as f64 * max as f64)
+    }
+
+    #[test]
+    fn test_bayer_matrix() {
+        assert_eq!(bayer_matrix(1), vec![0]);
+        assert_eq!(bayer_matrix(2), vec![0, 2, 3, 1]);
+        assert_eq!(
+            bayer_matrix(4),
+            vec![0, 8, 2, 10, 12, 4, 14, 6, 3, 11, 1, 9, 15, 7, 13, 5]
+        );
+    }
+
+    #[test]
+    fn test_bayer_orders() {
+        // a 1x1 matrix thresholds at a half, which is just rounding.
+        let c = flat(4, 4, 0.3);
+        let plain = c.quantize(15, TransferFunction::Linear, Dither::None);
+        let bayer = c.quantize(15, TransferFunction::Linear, Dither::Bayer(0));
+        for (a, b) in plain.iter().zip(bayer.iter()) {
+            assert_eq!((a.r, a.g, a.b), (b.r, b.g, b.b));
+        }
+
+        let largest = c.quantize(1, TransferFunction::Linear, Dither::Bayer(MAX_BAYER_ORDER));
+        let capped = c.quantize(1, TransferFunction::Linear, Dither::Bayer(u32::MAX));
+        for (a, b) in largest.iter().zip(capped.iter()) {
+            assert_eq!((a.r, a.g, a.b), (b.r, b.g, b.b));
+        }
+    }
+
+    #[test]
+    fn test_blue_noise_ranks() {
+        let mut ranks = blue_noise().to_vec();
+        ranks.sort_unstable();
+        assert!(ranks.iter().enumerate().all(|(i, &r)| i == r));
+
+        // the first few percent should be well spread, with no two cells
+        // touching.
+        let n = BLUE_NOISE_SIZE;
+        let lowest: Vec<usize> = (0..n * n)
+            .filter(|&i| blue_noise()[i] < n * n / 20)
+            .collect();
+        for &a in &lowest {
+            for &b in &lowest {
+                let (dx, dy) = ((a % n).abs_diff(b % n), (a / n).abs_diff(b / n));
+                let (dx, dy) = (dx.min(n - dx), dy.min(n - dy));
+                assert!(a == b || dx > 1 || dy > 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_dither_matches_to_int() {
+        let c = flat(3, 2, 0.3);
+        for q in c.quantize(255, TransferFunction::Linear, Dither::None) {
+            assert_eq!((q.r, q.g, q.b), (77, 77, 77));
+        }
+    }
+
+    #[test]
+    fn test_dithering_keeps_the_average() {
+        let c = flat(64, 64, 0.3);
+        let plain = c.quantize(1, TransferFunction::Linear, Dither::None);
+        assert_eq!(mean_red(&plain, 1), 0.0);
+
+        for (dither, tolerance) in [
+            (Dither::Bayer(2), 1.0 / 16.0),
+            (Dither::Bayer(3), 1.0 / 64.0),
+            (Dither::BlueNoise, 1.0 / 4096.0),
+            (Dither::FloydSteinberg, 0.01),
+        ] {
+            let levels = c.quantize(1, TransferFunction::Linear, dither);
+            assert_approx_eq!(mean_red(&levels, 1), 0.3, abs <= tolerance);
+        }
+    }
+
+    #[test]
+    fn test_dithered_round_trips() {
+        let mut c = Canvas::new(16, 4);
+        for (i, p) in c.pixels.iter_mut().enumerate() {
+            *p = Color::new(i as f64 / 64.0, 0.5, 0.01);
+        }
+
+        let encoding = TransferFunction::Srgb;
+        for dither in [Dither::Bayer(2), Dither::BlueNoise, Dither::FloydSteinberg] {
+            let expected = c.quantize(15, encoding, dither);
+            let d = c.dithered(15, encoding, dither);
+            let again = d.quantize(15, encoding, Dither::None);
+
+            for (a, b) in expected.iter().zip(again.iter()) {
+                assert_eq!((a.r, a.g, a.b), (b.r, b.g, b.b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_floyd_steinberg_clips_error() {
+        // a blown out pixel shouldn't push its excess onto its neighbours.
+        let mut c = flat(4, 1, 0.0);
+        c.write_pixel(0, 0, Color::new(50.0, 50.0, 50.0));
+
+        let levels = c.quantize(255, TransferFunction::Linear, Dither::FloydSteinberg);
+        assert_eq!(levels[0].r, 255);
+        assert_eq!(levels[1].r, 0);
+    }
+}
@@ -0,0 +1,526 @@
+//! 2d drawing on a canvas, for debug overlays and plots.
+//!
+//! coordinates are signed and anything that lands off the canvas is silently
+//! skipped, unlike indexing, which panics.
+
+use crate::{canvas::Canvas, color::Color, rgba::Rgba};
+
+impl Canvas {
+    /// writes an opaque pixel if `(x, y)` is on the canvas.
+    pub fn plot(&mut self, x: i64, y: i64, color: Color) {
+        if self.contains(x, y) {
+            self.write_pixel(x as usize, y as usize, color);
+        }
+    }
+
+    /// covers `coverage` of the pixel at `(x, y)` with `color`, if it's on the
+    /// canvas. used for anti-aliasing.
+    pub fn plot_blended(&mut self, x: i64, y: i64, color: Color, coverage: f64) {
+        if self.contains(x, y) {
+            let (x, y) = (x as usize, y as usize);
+            let src = Rgba::from_straight(color, coverage.clamp(0.0, 1.0));
+            self.write_rgba(x, y, src.over(self.rgba(x, y)));
+        }
+    }
+
+    fn contains(&self, x: i64, y: i64) -> bool {
+        x >= 0 && y >= 0 && (x as u64) < self.width as u64 && (y as u64) < self.height as u64
+    }
+
+    /// a one pixel wide line from `(x0, y0)` to `(x1, y1)` inclusive, using
+    /// bresenham's algorithm.
+    pub fn draw_line(&mut self, x0: i64, y0: i64, x1: i64, y1: i64, color: Color) {
+        let Some((x0, y0, x1, y1)) = self.clip_line(x0, y0, x1, y1) else {
+            return;
+        };
+
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (mut x, mut y) = (x0, y0);
+        let mut err = dx + dy;
+
+        loop {
+            self.plot(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    // cuts the line down to the part over the canvas, give or take a pixel,
+    // with liang-barsky clipping. endpoints already on the canvas are kept
+    // exactly, so only lines running off it can come out a pixel different.
+    fn clip_line(&self, x0: i64, y0: i64, x1: i64, y1: i64) -> Option<(i64, i64, i64, i64)> {
+        let (w, h) = (self.width as i64, self.height as i64);
+        let (dx, dy) = (x1 as i128 - x0 as i128, y1 as i128 - y0 as i128);
+
+        // where the line crosses the column `x == edge` or the row `y == edge`,
+        // worked out from the edge so huge coordinates don't lose the pixel.
+        let cross = |vertical: bool, edge: i64| {
+            if vertical {
+                let t = (edge as i128 - x0 as i128) as f64 / dx as f64;
+                (edge, y0.saturating_add((t * dy as f64).round() as i64))
+            } else {
+                let t = (edge as i128 - y0 as i128) as f64 / dy as f64;
+                (x0.saturating_add((t * dx as f64).round() as i64), edge)
+            }
+        };
+
+        let (mut start, mut end) = ((0.0, (x0, y0)), (1.0, (x1, y1)));
+        for (p, q, vertical, edge) in [
+            (-dx, x0 as i128 + 1, true, -1),
+            (dx, w as i128 - x0 as i128, true, w),
+            (-dy, y0 as i128 + 1, false, -1),
+            (dy, h as i128 - y0 as i128, false, h),
+        ] {
+            if p == 0 {
+                if q < 0 {
+                    return None;
+                }
+                continue;
+            }
+
+            let t = q as f64 / p as f64;
+            if p < 0 && t > start.0 {
+                start = (t, cross(vertical, edge));
+            } else if p > 0 && t < end.0 {
+                end = (t, cross(vertical, edge));
+            }
+        }
+        if start.0 > end.0 {
+            return None;
+        }
+
+        let ((x0, y0), (x1, y1)) = (start.1, end.1);
+        Some((x0, y0, x1, y1))
+    }
+
+    /// an anti-aliased line between two points in pixel coordinates, using
+    /// xiaolin wu's algorithm. pixel centres sit on whole numbers. lines with
+    /// an infinite or nan endpoint aren't drawn.
+    pub fn draw_line_aa(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, color: Color) {
+        // also catches finite endpoints so far apart the distance overflows.
+        if !(x1 - x0).is_finite() || !(y1 - y0).is_finite() {
+            return;
+        }
+
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        let (mut x0, mut y0, mut x1, mut y1) = if steep {
+            (y0, x0, y1, x1)
+        } else {
+            (x0, y0, x1, y1)
+        };
+        if x0 > x1 {
+            std::mem::swap(&mut x0, &mut x1);
+            std::mem::swap(&mut y0, &mut y1);
+        }
+
+        let dx = x1 - x0;
+        let gradient = if dx == 0.0 { 1.0 } else { (y1 - y0) / dx };
+        let fpart = |v: f64| v - v.floor();
+
+        let plot = |canvas: &mut Canvas, x: f64, y: f64, coverage: f64| {
+            let (x, y) = if steep { (y, x) } else { (x, y) };
+            canvas.plot_blended(x as i64, y as i64, color, coverage);
+        };
+
+        // the endpoints are weighted by how much of their pixel the line covers.
+        let endpoint = |canvas: &mut Canvas, x: f64, y: f64, gap: f64| {
+            let x_end = x.round();
+            let y_end = y + gradient * (x_end - x);
+            plot(canvas, x_end, y_end.floor(), (1.0 - fpart(y_end)) * gap);
+            plot(canvas, x_end, y_end.floor() + 1.0, fpart(y_end) * gap);
+            x_end
+        };
+        let start = endpoint(self, x0, y0, 1.0 - fpart(x0 + 0.5));
+        let end = endpoint(self, x1, y1, fpart(x1 + 0.5));
+
+        // only step along the part of the line that's over the canvas.
+        let columns = if steep { self.height } else { self.width } as f64;
+        let first = (start + 1.0).max(-1.0);
+        let last = end.min(columns + 1.0);
+
+        let mut y = y0 + gradient * (start - x0) + gradient * (first - start);
+        let mut x = first;
+        while x < last {
+            plot(self, x, y.floor(), 1.0 - fpart(y));
+            plot(self, x, y.floor() + 1.0, fpart(y));
+            y += gradient;
+            x += 1.0;
+        }
+    }
+
+    /// the outline of a circle, using the midpoint algorithm.
+    pub fn draw_circle(&mut self, cx: i64, cy: i64, radius: i64, color: Color) {
+        let (w, h) = (self.width as i64, self.height as i64);
+        if radius < 0
+            || cx.saturating_add(radius) < 0
+            || cy.saturating_add(radius) < 0
+            || cx.saturating_sub(radius) >= w
+            || cy.saturating_sub(radius) >= h
+        {
+            return;
+        }
+
+        // nothing to draw if the whole canvas is inside the circle.
+        let far_x = (cx as f64).abs().max((cx as f64 - (w - 1) as f64).abs());
+        let far_y = (cy as f64).abs().max((cy as f64 - (h - 1) as f64).abs());
+        if far_x.hypot(far_y) < radius as f64 - 1.0 {
+            return;
+        }
+
+        // each step plots a point in all eight octants, `y` from the centre
+        // along the minor axis and `x` along the major. a point can only be on
+        // the canvas if its `y` is, so only the steps in these spans matter.
+        let (cx, cy, r) = (cx as i128, cy as i128, radius as i128);
+        let (w, h) = (w as i128, h as i128);
+        let mut spans = [
+            (-cy, h - cy),
+            (cy - h + 1, cy + 1),
+            (-cx, w - cx),
+            (cx - w + 1, cx + 1),
+        ];
+        spans.sort();
+
+        let mut done = 0;
+        for (first, last) in spans {
+            let mut y = first.max(done);
+            if y >= last {
+                continue;
+            }
+
+            // jump straight to the step for `y`, rather than walking there.
+            let mut x = octant_x(r, y);
+            let mut err = x * x - x + y * y + 2 * y + 1 - r * r;
+            while x >= y && y < last {
+                for (px, py) in [(x, y), (y, x), (-y, x), (-x, y)] {
+                    self.plot_wide(cx + px, cy + py, color);
+                    self.plot_wide(cx - px, cy - py, color);
+                }
+
+                y += 1;
+                if err < 0 {
+                    err += 2 * y + 1;
+                } else {
+                    x -= 1;
+                    err += 2 * (y - x) + 1;
+                }
+            }
+            done = y;
+        }
+    }
+
+    fn plot_wide(&mut self, x: i128, y: i128, color: Color) {
+        if (0..self.width as i128).contains(&x) && (0..self.height as i128).contains(&y) {
+            self.write_pixel(x as usize, y as usize, color);
+        }
+    }
+
+    pub fn fill_circle(&mut self, cx: i64, cy: i64, radius: i64, color: Color) {
+        let first = cy.saturating_sub(radius).max(0);
+        let last = cy.saturating_add(radius).min(self.height as i64 - 1);
+        let radius = radius as f64;
+
+        for y in first..=last {
+            let dy = y as f64 - cy as f64;
+            let half = (radius * radius - dy * dy).sqrt() as i64;
+            self.fill_span(cx.saturating_sub(half), cx.saturating_add(half), y, color);
+        }
+    }
+
+    /// the outline of a `width` by `height` rectangle with its top left corner
+    /// at `(x, y)`.
+    pub fn draw_rect(&mut self, x: i64, y: i64, width: i64, height: i64, color: Color) {
+        if width <= 0 || height <= 0 {
+            return;
+        }
+
+        let (right, bottom) = (x.saturating_add(width - 1), y.saturating_add(height - 1));
+        self.draw_line(x, y, right, y, color);
+        self.draw_line(x, bottom, right, bottom, color);
+        self.draw_line(x, y, x, bottom, color);
+        self.draw_line(right, y, right, bottom, color);
+    }
+
+    pub fn fill_rect(&mut self, x: i64, y: i64, width: i64, height: i64, color: Color) {
+        if width <= 0 || height <= 0 {
+            return;
+        }
+
+        let first = y.max(0);
+        let last = y.saturating_add(height - 1).min(self.height as i64 - 1);
+        for row in first..=last {
+            self.fill_span(x, x.saturating_add(width - 1), row, color);
+        }
+    }
+
+    /// fills the polygon through `points`, in pixel coordinates, with the
+    /// even-odd rule. a pixel is inside if its centre is.
+    pub fn fill_polygon(&mut self, points: &[(f64, f64)], color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let min_y = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+        let max_y = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+        let first = (min_y.floor() as i64).max(0);
+        let last = (max_y.ceil() as i64).min(self.height as i64 - 1);
+
+        let mut crossings = vec![];
+        for row in first..=last {
+            let centre = row as f64;
+
+            crossings.clear();
+            for (i, &(x0, y0)) in points.iter().enumerate() {
+                let (x1, y1) = points[(i + 1) % points.len()];
+                // half-open, so a vertex shared by two edges counts once.
+                if (y0 <= centre && centre < y1) || (y1 <= centre && centre < y0) {
+                    crossings.push(x0 + (centre - y0) * (x1 - x0) / (y1 - y0));
+                }
+            }
+            crossings.sort_by(f64::total_cmp);
+
+            for pair in crossings.chunks_exact(2) {
+                self.fill_span(pair[0].ceil() as i64, pair[1].ceil() as i64 - 1, row, color);
+            }
+        }
+    }
+
+    // a horizontal run from `x0` to `x1` inclusive, clipped to the canvas.
+    fn fill_span(&mut self, x0: i64, x1: i64, y: i64, color: Color) {
+        if y < 0 || y >= self.height as i64 {
+            return;
+        }
+
+        let x0 = x0.max(0);
+        let x1 = x1.min(self.width as i64 - 1);
+        for x in x0..=x1 {
+            self.write_pixel(x as usize, y as usize, color);
+        }
+    }
+}
+
+// where the midpoint circle algorithm's `x` is when it reaches `y`: the one
+// with `x * (x - 1) < r * r - y * y <= x * (x + 1)`.
+fn octant_x(r: i128, y: i128) -> i128 {
+    let d = r * r - y * y;
+    let mut x = ((d as f64 + 0.25).max(0.0).sqrt() - 0.5).ceil() as i128;
+    while x * (x + 1) < d {
+        x += 1;
+    }
+    while x > 0 && (x - 1) * x >= d {
+        x -= 1;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Color = Color {
+        r: 1.0,
+        g: 1.0,
+        b: 1.0,
+    };
+
+    fn lit(c: &Canvas) -> Vec<(usize, usize)> {
+        let mut points = vec![];
+        for y in 0..c.height {
+            for x in 0..c.width {
+                if c[(x, y)] != Color::new(0.0, 0.0, 0.0) {
+                    points.push((x, y));
+                }
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn test_plot_clips() {
+        let mut c = Canvas::new(2, 2);
+        c.plot(-1, 0, WHITE);
+        c.plot(0, 2, WHITE);
+        c.plot(i64::MAX, i64::MIN, WHITE);
+        c.plot(1, 1, WHITE);
+
+        assert_eq!(lit(&c), vec![(1, 1)]);
+    }
+
+    #[test]
+    fn test_draw_line() {
+        let mut c = Canvas::new(5, 3);
+        c.draw_line(0, 0, 4, 2, WHITE);
+        assert_eq!(lit(&c), vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)]);
+
+        // backwards, ties between two pixels can break the other way.
+        let mut reversed = Canvas::new(5, 3);
+        reversed.draw_line(4, 2, 0, 0, WHITE);
+        assert_eq!(lit(&reversed).len(), 5);
+        assert_eq!(lit(&reversed)[0], (0, 0));
+        assert_eq!(lit(&reversed)[4], (4, 2));
+
+        // starting off the canvas lights the same pixels.
+        let mut c = Canvas::new(3, 3);
+        c.draw_line(0, 0, 2, 2, WHITE);
+        let mut clipped = Canvas::new(3, 3);
+        clipped.draw_line(-5, -5, 2, 2, WHITE);
+        assert_eq!(lit(&clipped), vec![(0, 0), (1, 1), (2, 2)]);
+        assert_eq!(lit(&clipped), lit(&c));
+    }
+
+    #[test]
+    fn test_draw_line_aa() {
+        let mut c = Canvas::new(6, 3);
+        c.draw_line_aa(0.0, 1.0, 5.0, 1.0, WHITE);
+        for x in 1..5 {
+            assert_eq!(c[(x, 1)], WHITE);
+            assert_eq!(c[(x, 0)], Color::new(0.0, 0.0, 0.0));
+        }
+        // the line starts and ends at pixel centres, covering half of each.
+        assert_eq!(c[(0, 1)], Color::new(0.5, 0.5, 0.5));
+        assert_eq!(c[(5, 1)], Color::new(0.5, 0.5, 0.5));
+
+        // halfway between two rows, both get half coverage.
+        let mut c = Canvas::new(6, 3);
+        c.draw_line_aa(0.0, 0.5, 5.0, 0.5, Color::new(1.0, 0.0, 0.0));
+        for x in 1..5 {
+            assert_eq!(c[(x, 0)], Color::new(0.5, 0.0, 0.0));
+            assert_eq!(c[(x, 1)], Color::new(0.5, 0.0, 0.0));
+        }
+
+        let mut steep = Canvas::new(3, 6);
+        steep.draw_line_aa(1.0, -10.0, 1.0, 10.0, WHITE);
+        assert_eq!(lit(&steep).len(), 6);
+        assert!(lit(&steep).iter().all(|&(x, _)| x == 1));
+    }
+
+    #[test]
+    fn test_circles() {
+        let mut c = Canvas::new(7, 7);
+        c.draw_circle(3, 3, 3, WHITE);
+        let outline = lit(&c);
+        assert!(outline.contains(&(0, 3)) && outline.contains(&(6, 3)));
+        assert!(outline.contains(&(3, 0)) && outline.contains(&(3, 6)));
+        assert!(!outline.contains(&(3, 3)));
+        for &(x, y) in &outline {
+            assert!(outline.contains(&(6 - x, y)) && outline.contains(&(x, 6 - y)));
+        }
+
+        let mut c = Canvas::new(7, 7);
+        c.fill_circle(3, 3, 1, WHITE);
+        assert_eq!(lit(&c), vec![(3, 2), (2, 3), (3, 3), (4, 3), (3, 4)]);
+
+        let mut c = Canvas::new(3, 3);
+        c.fill_circle(-100, -100, 50, WHITE);
+        assert!(lit(&c).is_empty());
+
+        // only drawing the parts of a circle over the canvas doesn't change them.
+        let mut whole = Canvas::new(60, 60);
+        whole.draw_circle(30, 30, 21, WHITE);
+        for (x, y) in [(0, 0), (5, 25), (20, 8), (44, 44), (25, 50)] {
+            let mut c = Canvas::new(10, 10);
+            c.draw_circle(30 - x as i64, 30 - y as i64, 21, WHITE);
+            assert_eq!(c, whole.crop(x, y, 10, 10));
+        }
+    }
+
+    #[test]
+    fn test_rects() {
+        let mut c = Canvas::new(4, 4);
+        c.draw_rect(0, 0, 3, 3, WHITE);
+        assert_eq!(lit(&c).len(), 8);
+        assert_eq!(c[(1, 1)], Color::new(0.0, 0.0, 0.0));
+
+        let mut c = Canvas::new(4, 4);
+        c.fill_rect(2, 2, 10, 10, WHITE);
+        assert_eq!(lit(&c), vec![(2, 2), (3, 2), (2, 3), (3, 3)]);
+    }
+
+    #[test]
+    fn test_huge_shapes() {
+        // none of these should overflow, or loop over more than the canvas.
+        let mut c = Canvas::new(3, 3);
+        c.fill_rect(0, 0, i64::MAX, 1, WHITE);
+        assert_eq!(lit(&c), vec![(0, 0), (1, 0), (2, 0)]);
+
+        let mut c = Canvas::new(3, 3);
+        c.fill_rect(i64::MIN, i64::MIN, i64::MAX, i64::MAX, WHITE);
+        assert!(lit(&c).is_empty());
+        c.draw_rect(-1, -1, i64::MAX, i64::MAX, WHITE);
+        assert!(lit(&c).is_empty());
+
+        let mut c = Canvas::new(3, 3);
+        c.draw_line(i64::MIN, 1, i64::MAX, 1, WHITE);
+        assert_eq!(lit(&c), vec![(0, 1), (1, 1), (2, 1)]);
+
+        let mut c = Canvas::new(3, 3);
+        c.draw_line(-1 << 40, -1 << 40, 1 << 40, 1 << 40, WHITE);
+        assert_eq!(lit(&c), vec![(0, 0), (1, 1), (2, 2)]);
+
+        let mut c = Canvas::new(3, 3);
+        c.draw_line(i64::MIN, i64::MIN, i64::MIN, i64::MAX, WHITE);
+        assert!(lit(&c).is_empty());
+
+        let mut c = Canvas::new(3, 3);
+        c.fill_circle(1, 1, i64::MAX, WHITE);
+        assert_eq!(lit(&c).len(), 9);
+        c.fill_circle(i64::MIN, i64::MAX, i64::MAX, WHITE);
+
+        let mut c = Canvas::new(3, 3);
+        c.draw_circle(1, 1, i64::MAX, WHITE);
+        c.draw_circle(1, 1, i64::MIN, WHITE);
+        assert!(lit(&c).is_empty());
+
+        let mut c = Canvas::new(10, 10);
+        c.draw_line_aa(-1e9, 5.0, 1e9, 5.0, WHITE);
+        assert_eq!(lit(&c), (0..10).map(|x| (x, 5)).collect::<Vec<_>>());
+        c.draw_line_aa(f64::NEG_INFINITY, 1.0, 5.0, 1.0, WHITE);
+        c.draw_line_aa(0.0, 1.0, f64::NAN, 1.0, WHITE);
+        c.draw_line_aa(-f64::MAX, 1.0, f64::MAX, 1.0, WHITE);
+        assert_eq!(lit(&c).len(), 10);
+
+        // huge circles just grazing the top row and the right column, which
+        // are all but straight over a canvas this small.
+        let mut c = Canvas::new(10, 10);
+        c.draw_circle(5, -1_000_000_000, 1_000_000_000, WHITE);
+        assert_eq!(lit(&c), (0..10).map(|x| (x, 0)).collect::<Vec<_>>());
+        let mut c = Canvas::new(10, 10);
+        c.draw_circle(i64::MAX, 5, i64::MAX - 9, WHITE);
+        assert_eq!(lit(&c), (0..10).map(|y| (9, y)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_fill_polygon() {
+        let mut c = Canvas::new(10, 10);
+        c.fill_polygon(&[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)], WHITE);
+        assert_eq!(lit(&c).len(), 16);
+
+        // a triangle covering half of an 8x8 square.
+        let mut c = Canvas::new(10, 10);
+        c.fill_polygon(&[(0.0, 0.0), (8.0, 0.0), (0.0, 8.0)], WHITE);
+        assert_eq!(lit(&c).len(), 36);
+
+        // even-odd leaves the middle of a self-overlapping star empty.
+        let mut c = Canvas::new(20, 20);
+        let star: Vec<(f64, f64)> = (0..5)
+            .map(|i| {
+                let angle = i as f64 * 4.0 * std::f64::consts::PI / 5.0;
+                (10.0 + 9.0 * angle.sin(), 10.0 - 9.0 * angle.cos())
+            })
+            .collect();
+        c.fill_polygon(&star, WHITE);
+        assert_eq!(c[(10, 10)], Color::new(0.0, 0.0, 0.0));
+        assert_eq!(c[(10, 3)], WHITE);
+    }
+}
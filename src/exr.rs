@@ -0,0 +1,763 @@
+//! a subset of openexr: single-part scanline images with uncompressed, zips
+//! or zip compression, and half, float or uint channels.
+//!
+//! canvases are written as `B`, `G`, `R` channels (exr wants them sorted by
+//! name), plus a premultiplied `A` when they aren't opaque, and read back from
+//! whichever of `R`, `G`, `B` and `A` the file has.
+
+use crate::{
+    canvas::Canvas,
+    deflate::{zlib_compress, zlib_decompress, DeflateError},
+};
+use std::{
+    fmt,
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::Path,
+};
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+// version 2, with none of the tiled, long name, deep or multipart flags set.
+const VERSION: u32 = 2;
+const TILED_FLAG: u32 = 0x200;
+const UNSUPPORTED_FLAGS: u32 = 0x800 | 0x1000;
+
+// zlib can't expand data by much more than this, which bounds how big an
+// image a compressed file of a given length can hold.
+const MAX_DEFLATE_RATIO: usize = 1032;
+
+#[derive(Debug)]
+pub enum ExrError {
+    Io(std::io::Error),
+    Deflate(DeflateError),
+    InvalidMagic,
+    InvalidHeader(String),
+    MissingAttribute(&'static str),
+    /// a chunk offset, or the data it points at, runs past the end of the file.
+    Truncated(&'static str),
+    Unsupported(String),
+}
+
+impl fmt::Display for ExrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExrError::Io(e) => write!(f, "error reading exr: {}", e),
+            ExrError::Deflate(e) => write!(f, "error decompressing exr: {}", e),
+            ExrError::InvalidMagic => write!(f, "not an openexr file"),
+            ExrError::InvalidHeader(what) => write!(f, "invalid exr header: {}", what),
+            ExrError::MissingAttribute(name) => write!(f, "missing {} attribute", name),
+            ExrError::Truncated(what) => write!(f, "truncated {}", what),
+            ExrError::Unsupported(what) => write!(f, "unsupported exr: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for ExrError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExrError::Io(e) => Some(e),
+            ExrError::Deflate(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ExrError {
+    fn from(e: std::io::Error) -> Self {
+        ExrError::Io(e)
+    }
+}
+
+impl From<DeflateError> for ExrError {
+    fn from(e: DeflateError) -> Self {
+        ExrError::Deflate(e)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExrPixelType {
+    Uint,
+    Half,
+    Float,
+}
+
+impl ExrPixelType {
+    fn from_id(id: i32) -> Option<Self> {
+        match id {
+            0 => Some(ExrPixelType::Uint),
+            1 => Some(ExrPixelType::Half),
+            2 => Some(ExrPixelType::Float),
+            _ => None,
+        }
+    }
+
+    fn id(self) -> i32 {
+        match self {
+            ExrPixelType::Uint => 0,
+            ExrPixelType::Half => 1,
+            ExrPixelType::Float => 2,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ExrPixelType::Half => 2,
+            ExrPixelType::Uint | ExrPixelType::Float => 4,
+        }
+    }
+
+    fn encode(self, value: f64, out: &mut Vec<u8>) {
+        match self {
+            ExrPixelType::Uint => out.extend_from_slice(&(value.max(0.0) as u32).to_le_bytes()),
+            ExrPixelType::Half => out.extend_from_slice(&f32_to_f16(value as f32).to_le_bytes()),
+            ExrPixelType::Float => out.extend_from_slice(&(value as f32).to_le_bytes()),
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> f64 {
+        match self {
+            ExrPixelType::Uint => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            ExrPixelType::Half => f16_to_f32(u16::from_le_bytes(bytes.try_into().unwrap())) as f64,
+            ExrPixelType::Float => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExrCompression {
+    None,
+    /// zlib, one scanline per chunk.
+    Zips,
+    /// zlib, sixteen scanlines per chunk.
+    Zip,
+}
+
+impl ExrCompression {
+    fn from_id(id: u8) -> Result<Self, ExrError> {
+        match id {
+            0 => Ok(ExrCompression::None),
+            2 => Ok(ExrCompression::Zips),
+            3 => Ok(ExrCompression::Zip),
+            1 | 4..=9 => Err(ExrError::Unsupported(format!("compression type {}", id))),
+            _ => Err(ExrError::InvalidHeader(format!(
+                "unknown compression {}",
+                id
+            ))),
+        }
+    }
+
+    fn id(self) -> u8 {
+        match self {
+            ExrCompression::None => 0,
+            ExrCompression::Zips => 2,
+            ExrCompression::Zip => 3,
+        }
+    }
+
+    fn lines_per_chunk(self) -> usize {
+        match self {
+            ExrCompression::None | ExrCompression::Zips => 1,
+            ExrCompression::Zip => 16,
+        }
+    }
+}
+
+// round-to-nearest-even conversion to ieee 754 binary16.
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exp == 0xff {
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+
+    let exp = exp - 127 + 15;
+    if exp >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    let round = |value: u32, shift: u32| {
+        let half = 1 << (shift - 1);
+        let rest = value & ((1 << shift) - 1);
+        let truncated = value >> shift;
+        if rest > half || (rest == half && truncated & 1 == 1) {
+            truncated + 1
+        } else {
+            truncated
+        }
+    };
+
+    if exp <= 0 {
+        if exp < -10 {
+            return sign;
+        }
+        // subnormal: shift the mantissa, implicit bit included, into place.
+        return sign | round(mantissa | 0x80_0000, (14 - exp) as u32) as u16;
+    }
+
+    // a carry out of the mantissa correctly bumps the exponent, up to infinity.
+    sign | round(((exp as u32) << 23) | mantissa, 13) as u16
+}
+
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half & 0x8000) as u32) << 16;
+    let exp = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+
+    match exp {
+        0 => {
+            let value = mantissa as f32 * 2f32.powi(-24);
+            if sign != 0 {
+                -value
+            } else {
+                value
+            }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exp + 112) << 23) | (mantissa << 13)),
+    }
+}
+
+// exr's zip compression deflates the bytes after splitting them into even and
+// odd halves and delta-encoding the result, which keeps float exponents and
+// mantissas apart.
+fn zip_compress(raw: &[u8]) -> Vec<u8> {
+    let mut tmp: Vec<u8> = raw.iter().step_by(2).copied().collect();
+    tmp.extend(raw.iter().skip(1).step_by(2));
+
+    let mut previous = tmp.first().copied().unwrap_or(0);
+    for byte in tmp.iter_mut().skip(1) {
+        let current = *byte;
+        *byte = current.wrapping_sub(previous).wrapping_add(128);
+        previous = current;
+    }
+
+    zlib_compress(&tmp)
+}
+
+fn zip_decompress(compressed: &[u8]) -> Result<Vec<u8>, DeflateError> {
+    let mut tmp = zlib_decompress(compressed)?;
+    for i in 1..tmp.len() {
+        tmp[i] = tmp[i - 1].wrapping_add(tmp[i]).wrapping_sub(128);
+    }
+
+    let (even, odd) = tmp.split_at(tmp.len().div_ceil(2));
+    let mut raw = Vec::with_capacity(tmp.len());
+    for (i, &byte) in even.iter().enumerate() {
+        raw.push(byte);
+        if let Some(&byte) = odd.get(i) {
+            raw.push(byte);
+        }
+    }
+
+    Ok(raw)
+}
+
+fn write_attribute(out: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    out.extend_from_slice(name.as_bytes());
+    out.push(0);
+    out.extend_from_slice(kind.as_bytes());
+    out.push(0);
+    out.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    out.extend_from_slice(value);
+}
+
+struct Channel {
+    name: String,
+    pixel_type: ExrPixelType,
+}
+
+// reads little-endian values from the file, turning running off the end into
+// an error.
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize, what: &'static str) -> Result<&'a [u8], ExrError> {
+        let end = self.pos.checked_add(n).ok_or(ExrError::Truncated(what))?;
+        let bytes = self
+            .data
+            .get(self.pos..end)
+            .ok_or(ExrError::Truncated(what))?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn string(&mut self) -> Result<String, ExrError> {
+        let rest = self.data.get(self.pos..).unwrap_or_default();
+        let len = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(ExrError::Truncated("header"))?;
+        self.pos += len + 1;
+        Ok(String::from_utf8_lossy(&rest[..len]).to_string())
+    }
+
+    fn i32(&mut self, what: &'static str) -> Result<i32, ExrError> {
+        Ok(i32::from_le_bytes(self.take(4, what)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self, what: &'static str) -> Result<u64, ExrError> {
+        Ok(u64::from_le_bytes(self.take(8, what)?.try_into().unwrap()))
+    }
+}
+
+fn parse_channels(value: &[u8]) -> Result<Vec<Channel>, ExrError> {
+    let mut cursor = Cursor {
+        data: value,
+        pos: 0,
+    };
+    let mut channels = vec![];
+
+    loop {
+        let name = cursor.string()?;
+        if name.is_empty() {
+            return Ok(channels);
+        }
+
+        let id = cursor.i32("channel list")?;
+        let pixel_type = ExrPixelType::from_id(id)
+            .ok_or_else(|| ExrError::InvalidHeader(format!("unknown pixel type {}", id)))?;
+        // plinear and reserved bytes.
+        cursor.take(4, "channel list")?;
+        let sampling = (cursor.i32("channel list")?, cursor.i32("channel list")?);
+        if sampling != (1, 1) {
+            return Err(ExrError::Unsupported(format!(
+                "subsampled channel {}",
+                name
+            )));
+        }
+
+        channels.push(Channel { name, pixel_type });
+    }
+}
+
+impl Canvas {
+    pub fn write_to_exr(&self, path: &Path) -> std::io::Result<()> {
+        self.write_exr(File::create(path)?, ExrPixelType::Half, ExrCompression::Zip)
+    }
+
+    /// writes the canvas as a scanline openexr image to any writer.
+    pub fn write_exr<W: Write>(
+        &self,
+        writer: W,
+        pixel_type: ExrPixelType,
+        compression: ExrCompression,
+    ) -> std::io::Result<()> {
+        let mut header = vec![];
+        header.extend_from_slice(&MAGIC);
+        header.extend_from_slice(&VERSION.to_le_bytes());
+
+        let names: &[&str] = if self.is_opaque() {
+            &["B", "G", "R"]
+        } else {
+            &["A", "B", "G", "R"]
+        };
+
+        let mut channels = vec![];
+        for name in names {
+            channels.extend_from_slice(name.as_bytes());
+            channels.push(0);
+            channels.extend_from_slice(&pixel_type.id().to_le_bytes());
+            channels.extend_from_slice(&[0; 4]);
+            channels.extend_from_slice(&1i32.to_le_bytes());
+            channels.extend_from_slice(&1i32.to_le_bytes());
+        }
+        channels.push(0);
+
+        let mut window = vec![];
+        for v in [0, 0, self.width as i32 - 1, self.height as i32 - 1] {
+            window.extend_from_slice(&v.to_le_bytes());
+        }
+
+        write_attribute(&mut header, "channels", "chlist", &channels);
+        write_attribute(
+            &mut header,
+            "compression",
+            "compression",
+            &[compression.id()],
+        );
+        write_attribute(&mut header, "dataWindow", "box2i", &window);
+        write_attribute(&mut header, "displayWindow", "box2i", &window);
+        write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+        write_attribute(
+            &mut header,
+            "pixelAspectRatio",
+            "float",
+            &1f32.to_le_bytes(),
+        );
+        write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+        write_attribute(
+            &mut header,
+            "screenWindowWidth",
+            "float",
+            &1f32.to_le_bytes(),
+        );
+        header.push(0);
+
+        let lines = compression.lines_per_chunk();
+        let mut chunks = vec![];
+        for start in (0..self.height).step_by(lines) {
+            let mut raw = vec![];
+            for y in start..(start + lines).min(self.height) {
+                for &name in names {
+                    for x in 0..self.width {
+                        let rgba = self.rgba(x, y);
+                        let value = match name {
+                            "A" => rgba.alpha,
+                            "B" => rgba.color.b,
+                            "G" => rgba.color.g,
+                            _ => rgba.color.r,
+                        };
+                        pixel_type.encode(value, &mut raw);
+                    }
+                }
+            }
+
+            // compressed chunks that don't shrink are stored raw instead.
+            let data = match compression {
+                ExrCompression::None => raw,
+                _ => {
+                    let compressed = zip_compress(&raw);
+                    if compressed.len() < raw.len() {
+                        compressed
+                    } else {
+                        raw
+                    }
+                }
+            };
+            chunks.push((start as i32, data));
+        }
+
+        let mut w = BufWriter::new(writer);
+        w.write_all(&header)?;
+
+        let mut offset = (header.len() + chunks.len() * 8) as u64;
+        for (_, data) in &chunks {
+            w.write_all(&offset.to_le_bytes())?;
+            offset += 8 + data.len() as u64;
+        }
+        for (y, data) in &chunks {
+            w.write_all(&y.to_le_bytes())?;
+            w.write_all(&(data.len() as i32).to_le_bytes())?;
+            w.write_all(data)?;
+        }
+
+        w.flush()
+    }
+
+    /// reads a single-part scanline openexr image. channels other than `R`,
+    /// `G`, `B` and `A` are ignored. missing color channels read as 0, a
+    /// missing alpha as opaque.
+    pub fn from_exr<R: Read>(mut reader: R) -> Result<Canvas, ExrError> {
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+
+        let mut cursor = Cursor {
+            data: &data,
+            pos: 0,
+        };
+        if cursor.take(4, "header").ok() != Some(&MAGIC[..]) {
+            return Err(ExrError::InvalidMagic);
+        }
+
+        let version = u32::from_le_bytes(cursor.take(4, "header")?.try_into().unwrap());
+        if version & 0xff != VERSION {
+            return Err(ExrError::Unsupported(format!("version {}", version & 0xff)));
+        }
+        if version & TILED_FLAG != 0 {
+            return Err(ExrError::Unsupported("tiled images".to_string()));
+        }
+        if version & UNSUPPORTED_FLAGS != 0 {
+            return Err(ExrError::Unsupported(
+                "deep or multi-part images".to_string(),
+            ));
+        }
+
+        let mut channels = None;
+        let mut compression = None;
+        let mut window = None;
+
+        loop {
+            let name = cursor.string()?;
+            if name.is_empty() {
+                break;
+            }
+            let kind = cursor.string()?;
+            let size = cursor.i32("header")?;
+            let value = cursor.take(size.max(0) as usize, "header")?;
+
+            match (name.as_str(), kind.as_str()) {
+                ("channels", "chlist") => channels = Some(parse_channels(value)?),
+                ("compression", "compression") if size == 1 => {
+                    compression = Some(ExrCompression::from_id(value[0])?)
+                }
+                ("dataWindow", "box2i") if size == 16 => {
+                    let v: Vec<i32> = value
+                        .chunks(4)
+                        .map(|b| i32::from_le_bytes(b.try_into().unwrap()))
+                        .collect();
+                    window = Some((v[0], v[1], v[2], v[3]));
+                }
+                _ => {}
+            }
+        }
+
+        let channels = channels.ok_or(ExrError::MissingAttribute("channels"))?;
+        let compression = compression.ok_or(ExrError::MissingAttribute("compression"))?;
+        let (x_min, y_min, x_max, y_max) =
+            window.ok_or(ExrError::MissingAttribute("dataWindow"))?;
+        let width = x_max as i64 - x_min as i64 + 1;
+        let height = y_max as i64 - y_min as i64 + 1;
+        if width < 0 || height < 0 {
+            return Err(ExrError::InvalidHeader("negative data window".to_string()));
+        }
+
+        let (width, height) = (width as usize, height as usize);
+        let lines = compression.lines_per_chunk();
+        let too_large = || ExrError::InvalidHeader(format!("{}x{} data window", width, height));
+        let pixels = width.checked_mul(height).ok_or_else(too_large)?;
+        let bytes_per_line = channels
+            .iter()
+            .try_fold(0usize, |n, c| {
+                n.checked_add(c.pixel_type.size().checked_mul(width)?)
+            })
+            .ok_or_else(too_large)?;
+        let raster_size = bytes_per_line.checked_mul(height).ok_or_else(too_large)?;
+
+        // check the file could hold that much before allocating for it: every
+        // chunk has an offset, and every pixel takes at least a byte once
+        // decompressed.
+        let max_ratio = match compression {
+            ExrCompression::None => 1,
+            _ => MAX_DEFLATE_RATIO,
+        };
+        let remaining = data.len() - cursor.pos;
+        if height.div_ceil(lines).saturating_mul(8) > remaining
+            || raster_size.max(pixels) > remaining.saturating_mul(max_ratio)
+        {
+            return Err(ExrError::Truncated("image data"));
+        }
+
+        let mut canvas = Canvas::new(width, height);
+        if width == 0 {
+            return Ok(canvas);
+        }
+
+        for _ in 0..height.div_ceil(lines) {
+            let offset = cursor.u64("offset table")? as usize;
+            let mut chunk = Cursor {
+                data: &data,
+                pos: offset,
+            };
+
+            let y = (chunk.i32("chunk")? as i64 - y_min as i64) as usize;
+            if y >= height {
+                return Err(ExrError::InvalidHeader(format!("chunk at line {}", y)));
+            }
+            let size = chunk.i32("chunk")?.max(0) as usize;
+            let stored = chunk.take(size, "chunk")?;
+
+            let rows = lines.min(height - y);
+            let expected = rows * bytes_per_line;
+            let raw = if size == expected {
+                stored.to_vec()
+            } else if compression == ExrCompression::None {
+                return Err(ExrError::Truncated("chunk"));
+            } else {
+                zip_decompress(stored)?
+            };
+            if raw.len() != expected {
+                return Err(ExrError::Truncated("chunk"));
+            }
+
+            for (row, line) in raw.chunks(bytes_per_line).enumerate() {
+                let mut samples = line;
+                for channel in &channels {
+                    let size = channel.pixel_type.size();
+                    let (values, rest) = samples.split_at(size * width);
+                    samples = rest;
+
+                    for (x, bytes) in values.chunks(size).enumerate() {
+                        let value = channel.pixel_type.decode(bytes);
+                        let pixel = &mut canvas[(x, y + row)];
+                        match channel.name.as_str() {
+                            "R" => pixel.r = value,
+                            "G" => pixel.g = value,
+                            "B" => pixel.b = value,
+                            "A" => canvas.alpha[x + (y + row) * width] = value,
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(canvas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::Color, rgba::Rgba};
+
+    #[test]
+    fn test_half_conversion() {
+        assert_eq!(f32_to_f16(0.0), 0x0000);
+        assert_eq!(f32_to_f16(-0.0), 0x8000);
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(-2.0), 0xc000);
+        assert_eq!(f32_to_f16(65504.0), 0x7bff);
+        assert_eq!(f32_to_f16(65520.0), 0x7c00);
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7c00);
+        assert_eq!(f32_to_f16(2f32.powi(-24)), 0x0001);
+        assert_eq!(f32_to_f16(2f32.powi(-26)), 0x0000);
+        // 1 + 2^-11 is exactly halfway between two halves and rounds to even.
+        assert_eq!(f32_to_f16(1.0 + 2f32.powi(-11)), 0x3c00);
+        assert_eq!(f32_to_f16(1.0 + 3.0 * 2f32.powi(-11)), 0x3c02);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+
+        for half in [0x0001u16, 0x03ff, 0x0400, 0x3c00, 0x3555, 0x7bff, 0xfbff] {
+            assert_eq!(f32_to_f16(f16_to_f32(half)), half);
+        }
+        assert_eq!(f16_to_f32(0x3555), 0.333_251_95);
+    }
+
+    #[test]
+    fn test_zip_roundtrip() {
+        let raw: Vec<u8> = (0..1001u32).map(|i| (i * i % 251) as u8).collect();
+        assert_eq!(zip_decompress(&zip_compress(&raw)).unwrap(), raw);
+        assert_eq!(zip_decompress(&zip_compress(&[])).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_exr_roundtrip() {
+        let mut c = Canvas::new(7, 19);
+        for y in 0..19 {
+            for x in 0..7 {
+                c.write_pixel(x, y, Color::new(x as f64 * 10.0, y as f64 / 4.0, 0.1));
+            }
+        }
+
+        for compression in [
+            ExrCompression::None,
+            ExrCompression::Zips,
+            ExrCompression::Zip,
+        ] {
+            let mut out = vec![];
+            c.write_exr(&mut out, ExrPixelType::Float, compression)
+                .unwrap();
+            let read = Canvas::from_exr(&out[..]).unwrap();
+            assert_eq!(read.width, 7);
+            assert_eq!(read.height, 19);
+            for (a, b) in read.pixels.iter().zip(c.pixels.iter()) {
+                assert_eq!(a, b);
+            }
+
+            let mut out = vec![];
+            c.write_exr(&mut out, ExrPixelType::Half, compression)
+                .unwrap();
+            let read = Canvas::from_exr(&out[..]).unwrap();
+            for (a, b) in read.pixels.iter().zip(c.pixels.iter()) {
+                assert!((a.r - b.r).abs() <= b.r / 1024.0);
+                assert!((a.g - b.g).abs() <= b.g / 1024.0);
+                assert!((a.b - b.b).abs() <= b.b / 1024.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_exr_alpha() {
+        let mut c = Canvas::new_transparent(2, 2);
+        c.write_pixel(0, 0, Color::new(4.0, 2.0, 1.0));
+        c.write_rgba(1, 1, Rgba::from_straight(Color::new(1.0, 1.0, 1.0), 0.5));
+
+        let mut out = vec![];
+        c.write_exr(&mut out, ExrPixelType::Half, ExrCompression::Zips)
+            .unwrap();
+        assert_eq!(&out[28..30], b"A\0");
+
+        let read = Canvas::from_exr(&out[..]).unwrap();
+        assert_eq!(read, c);
+    }
+
+    #[test]
+    fn test_write_exr_header() {
+        let mut out = vec![];
+        Canvas::new(2, 1)
+            .write_exr(&mut out, ExrPixelType::Half, ExrCompression::None)
+            .unwrap();
+
+        assert_eq!(&out[..8], &[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+        assert_eq!(&out[8..24], b"channels\0chlist\0");
+        // one chunk: line 0, three channels of two half pixels.
+        assert_eq!(
+            &out[out.len() - 20..out.len() - 12],
+            &[0, 0, 0, 0, 12, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_read_exr_errors() {
+        assert!(matches!(
+            Canvas::from_exr(&b"\x89PNG"[..]),
+            Err(ExrError::InvalidMagic)
+        ));
+
+        let mut tiled = MAGIC.to_vec();
+        tiled.extend_from_slice(&(VERSION | TILED_FLAG).to_le_bytes());
+        assert!(matches!(
+            Canvas::from_exr(&tiled[..]),
+            Err(ExrError::Unsupported(_))
+        ));
+
+        let mut piz = MAGIC.to_vec();
+        piz.extend_from_slice(&VERSION.to_le_bytes());
+        write_attribute(&mut piz, "compression", "compression", &[4]);
+        assert!(matches!(
+            Canvas::from_exr(&piz[..]),
+            Err(ExrError::Unsupported(_))
+        ));
+
+        let mut out = vec![];
+        Canvas::new(3, 3)
+            .write_exr(&mut out, ExrPixelType::Float, ExrCompression::None)
+            .unwrap();
+        out.truncate(out.len() - 10);
+        assert!(matches!(
+            Canvas::from_exr(&out[..]),
+            Err(ExrError::Truncated(_))
+        ));
+
+        // a data window far bigger than the file, or than memory.
+        let header = |window: [i32; 4]| {
+            let mut out = MAGIC.to_vec();
+            out.extend_from_slice(&VERSION.to_le_bytes());
+            let mut chlist = b"R\0".to_vec();
+            for v in [2, 0, 1, 1] {
+                chlist.extend_from_slice(&i32::to_le_bytes(v));
+            }
+            chlist.push(0);
+            write_attribute(&mut out, "channels", "chlist", &chlist);
+            write_attribute(&mut out, "compression", "compression", &[0]);
+            let window: Vec<u8> = window.iter().flat_map(|v| v.to_le_bytes()).collect();
+            write_attribute(&mut out, "dataWindow", "box2i", &window);
+            out.push(0);
+            out
+        };
+        assert!(matches!(
+            Canvas::from_exr(&header([0, 0, 99_999, 99_999])[..]),
+            Err(ExrError::Truncated("image data"))
+        ));
+        assert!(matches!(
+            Canvas::from_exr(&header([i32::MIN, i32::MIN, i32::MAX, i32::MAX])[..]),
+            Err(ExrError::InvalidHeader(_))
+        ));
+    }
+}
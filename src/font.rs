@@ -0,0 +1,284 @@
+//! an embedded 5x7 bitmap font, for stamping renders with frame numbers,
+//! sample counts and timings.
+//!
+//! each lit dot is drawn with `fill_rect`, so text clips at the canvas edges like
+//! the rest of the 2d drawing.
+
+use crate::{canvas::Canvas, color::Color};
+
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+
+/// the horizontal distance between glyphs, leaving a column of spacing.
+pub const ADVANCE: usize = GLYPH_WIDTH + 1;
+
+/// the vertical distance between lines, leaving a row of spacing.
+pub const LINE_HEIGHT: usize = GLYPH_HEIGHT + 1;
+
+/// printable ascii from space to tilde. each glyph is five columns, left to
+/// right, with the top row in the lowest bit.
+const GLYPHS: [[u8; GLYPH_WIDTH]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // space
+    [0x00, 0x00, 0x5f, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7f, 0x14, 0x7f, 0x14], // #
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1c, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1c, 0x00], // )
+    [0x14, 0x08, 0x3e, 0x08, 0x14], // *
+    [0x08, 0x08, 0x3e, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], // 0
+    [0x00, 0x42, 0x7f, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4b, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7f, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3c, 0x4a, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1e], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3e], // @
+    [0x7e, 0x11, 0x11, 0x11, 0x7e], // A
+    [0x7f, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3e, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], // D
+    [0x7f, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7f, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3e, 0x41, 0x49, 0x49, 0x7a], // G
+    [0x7f, 0x08, 0x08, 0x08, 0x7f], // H
+    [0x00, 0x41, 0x7f, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3f, 0x01], // J
+    [0x7f, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7f, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7f, 0x02, 0x0c, 0x02, 0x7f], // M
+    [0x7f, 0x04, 0x08, 0x10, 0x7f], // N
+    [0x3e, 0x41, 0x41, 0x41, 0x3e], // O
+    [0x7f, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3e, 0x41, 0x51, 0x21, 0x5e], // Q
+    [0x7f, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7f, 0x01, 0x01], // T
+    [0x3f, 0x40, 0x40, 0x40, 0x3f], // U
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], // V
+    [0x3f, 0x40, 0x38, 0x40, 0x3f], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x07, 0x08, 0x70, 0x08, 0x07], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7f, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // \
+    [0x00, 0x41, 0x41, 0x7f, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7f, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7f], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7e, 0x09, 0x01, 0x02], // f
+    [0x0c, 0x52, 0x52, 0x52, 0x3e], // g
+    [0x7f, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7d, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3d, 0x00], // j
+    [0x7f, 0x10, 0x28, 0x44, 0x00], // k
+    [0x00, 0x41, 0x7f, 0x40, 0x00], // l
+    [0x7c, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7c, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7c, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7c], // q
+    [0x7c, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3f, 0x44, 0x40, 0x20], // t
+    [0x3c, 0x40, 0x40, 0x20, 0x7c], // u
+    [0x1c, 0x20, 0x40, 0x20, 0x1c], // v
+    [0x3c, 0x40, 0x30, 0x40, 0x3c], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0c, 0x50, 0x50, 0x50, 0x3c], // y
+    [0x44, 0x64, 0x54, 0x4c, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7f, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x02, 0x01, 0x02, 0x04, 0x02], // ~
+];
+
+/// drawn for anything outside printable ascii.
+const MISSING: [u8; GLYPH_WIDTH] = [0x7f, 0x41, 0x41, 0x41, 0x7f];
+
+fn glyph(c: char) -> &'static [u8; GLYPH_WIDTH] {
+    match c {
+        ' '..='~' => &GLYPHS[c as usize - ' ' as usize],
+        _ => &MISSING,
+    }
+}
+
+/// the width and height in pixels of `text` drawn at `scale`, ignoring the
+/// spacing after the last glyph and line.
+pub fn text_size(text: &str, scale: usize) -> (usize, usize) {
+    let mut lines = 0;
+    let mut longest = 0;
+    for line in text.split('\n') {
+        lines += 1;
+        longest = longest.max(line.chars().count());
+    }
+
+    if longest == 0 {
+        return (0, (lines - 1) * LINE_HEIGHT * scale);
+    }
+
+    (
+        (longest * ADVANCE - 1) * scale,
+        (lines * LINE_HEIGHT - 1) * scale,
+    )
+}
+
+impl Canvas {
+    /// draws `text` with its top left corner at `(x, y)`. `\n` starts a new
+    /// line back at `x`.
+    pub fn draw_text(&mut self, x: i64, y: i64, text: &str, color: Color) {
+        self.draw_text_scaled(x, y, text, color, 1);
+    }
+
+    /// draws `text` with every font pixel blown up to a `scale` by `scale`
+    /// square, for labels that need to survive being viewed small.
+    pub fn draw_text_scaled(&mut self, x: i64, y: i64, text: &str, color: Color, scale: usize) {
+        let scale = scale as i64;
+        let (mut cx, mut cy) = (x, y);
+
+        for c in text.chars() {
+            if c == '\n' {
+                cx = x;
+                cy += LINE_HEIGHT as i64 * scale;
+                continue;
+            }
+
+            for (col, bits) in glyph(c).iter().enumerate() {
+                for row in 0..GLYPH_HEIGHT {
+                    if bits >> row & 1 == 1 {
+                        let px = cx + col as i64 * scale;
+                        let py = cy + row as i64 * scale;
+                        self.fill_rect(px, py, scale, scale, color);
+                    }
+                }
+            }
+
+            cx += ADVANCE as i64 * scale;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Color = Color {
+        r: 1.0,
+        g: 1.0,
+        b: 1.0,
+    };
+
+    /// renders the canvas as rows of `#` and `.`.
+    fn ascii(c: &Canvas) -> Vec<String> {
+        (0..c.height)
+            .map(|y| {
+                (0..c.width)
+                    .map(|x| if c[(x, y)] == WHITE { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_draw_glyph() {
+        let mut c = Canvas::new(5, 7);
+        c.draw_text(0, 0, "A", WHITE);
+
+        assert_eq!(
+            ascii(&c),
+            vec![".###.", "#...#", "#...#", "#...#", "#####", "#...#", "#...#"]
+        );
+    }
+
+    #[test]
+    fn test_draw_text_layout() {
+        let mut c = Canvas::new(11, 15);
+        c.draw_text(0, 0, "11\n1", WHITE);
+        let rows = ascii(&c);
+
+        // the second glyph starts one advance along, the second line one line
+        // height down.
+        assert_eq!(rows[0], "..#.....#..");
+        assert_eq!(rows[6], ".###...###.");
+        assert_eq!(rows[7], "...........");
+        assert_eq!(rows[8], "..#........");
+    }
+
+    #[test]
+    fn test_draw_text_scaled() {
+        let mut c = Canvas::new(2, 2);
+        c.draw_text_scaled(-2, 0, "-", WHITE, 2);
+
+        // the dash is on the fourth row, so at scale 2 it starts at y = 6.
+        assert!(c.pixels.iter().all(|&p| p != WHITE));
+
+        let mut c = Canvas::new(10, 8);
+        c.draw_text_scaled(0, 0, "-", WHITE, 2);
+        assert_eq!(ascii(&c)[6], "##########");
+        assert_eq!(ascii(&c)[7], "##########");
+        assert_eq!(ascii(&c)[5], "..........");
+    }
+
+    #[test]
+    fn test_draw_text_clips() {
+        let mut c = Canvas::new(3, 3);
+        c.draw_text(-2, -2, "frame 12", WHITE);
+
+        // only the middle of the "f" lands on the canvas.
+        let mut f = Canvas::new(GLYPH_WIDTH, GLYPH_HEIGHT);
+        f.draw_text(0, 0, "f", WHITE);
+        for y in 0..3 {
+            for x in 0..3 {
+                assert_eq!(c[(x, y)], f[(x + 2, y + 2)]);
+            }
+        }
+        assert!(ascii(&c).concat().contains('#'));
+
+        let before = c.clone();
+        c.draw_text(100, 100, "off canvas", WHITE);
+        c.draw_text(-100, -100, "off canvas", WHITE);
+        assert_eq!(c, before);
+    }
+
+    #[test]
+    fn test_missing_glyph() {
+        let mut a = Canvas::new(5, 7);
+        a.draw_text(0, 0, "é", WHITE);
+        let mut b = Canvas::new(5, 7);
+        b.draw_text(0, 0, "\u{1}", WHITE);
+
+        assert_eq!(a, b);
+        assert_eq!(ascii(&a)[0], "#####");
+        assert_eq!(ascii(&a)[3], "#...#");
+    }
+
+    #[test]
+    fn test_text_size() {
+        assert_eq!(text_size("", 1), (0, 0));
+        assert_eq!(text_size("A", 1), (5, 7));
+        assert_eq!(text_size("spp 64", 1), (35, 7));
+        assert_eq!(text_size("ab\nlonger", 2), (70, 30));
+    }
+}
@@ -0,0 +1,585 @@
+//! animated gif output, for turntables and other previews that need to play
+//! anywhere.
+//!
+//! every frame gets its own palette of up to 256 colors, picked by median cut
+//! from the srgb-encoded frame, so frames can be streamed out as they finish
+//! rendering. pixels less than half covered come out transparent.
+
+use crate::{canvas::Canvas, color::ColorInt, transfer::TransferFunction};
+use std::{
+    collections::HashMap,
+    io::{BufWriter, Write},
+    time::Duration,
+};
+
+/// how many times a gif plays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Repeat {
+    Once,
+    Forever,
+    /// plays once, then repeats this many more times.
+    Times(u16),
+}
+
+/// the largest code lzw uses, after which the dictionary starts over.
+const MAX_CODE: u16 = 4095;
+
+type Rgb = [u8; 3];
+
+/// reduces `histogram` to at most `max` colors by repeatedly splitting the box
+/// of colors with the widest channel at its median, then averaging each box.
+fn median_cut(histogram: &HashMap<Rgb, usize>, max: usize) -> Vec<Rgb> {
+    if histogram.len() <= max {
+        let mut colors: Vec<Rgb> = histogram.keys().copied().collect();
+        colors.sort_unstable();
+        return colors;
+    }
+
+    let mut boxes: Vec<Vec<(Rgb, usize)>> = vec![histogram.iter().map(|(&c, &n)| (c, n)).collect()];
+
+    let range = |b: &[(Rgb, usize)], channel: usize| {
+        let (lo, hi) = b.iter().fold((255, 0), |(lo, hi), (c, _)| {
+            (c[channel].min(lo), c[channel].max(hi))
+        });
+        hi.saturating_sub(lo)
+    };
+
+    while boxes.len() < max {
+        // split whichever box spans the most along any one channel.
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let channel = (0..3).max_by_key(|&ch| range(b, ch)).unwrap();
+                (i, channel, range(b, channel))
+            })
+            .max_by_key(|&(_, _, r)| r);
+        let Some((i, channel, _)) = widest else {
+            break;
+        };
+
+        let mut b = boxes.swap_remove(i);
+        b.sort_unstable_by_key(|(c, _)| c[channel]);
+
+        // cut where half the pixels (not half the distinct colors) fall each
+        // side, keeping at least one color in each half.
+        let total: usize = b.iter().map(|&(_, n)| n).sum();
+        let mut seen = 0;
+        let mut cut = 1;
+        for (j, &(_, n)) in b.iter().enumerate() {
+            seen += n;
+            if seen * 2 >= total {
+                cut = (j + 1).clamp(1, b.len() - 1);
+                break;
+            }
+        }
+
+        let upper = b.split_off(cut);
+        boxes.push(b);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .map(|b| {
+            let total: usize = b.iter().map(|&(_, n)| n).sum();
+            let mut sum = [0; 3];
+            for (c, n) in b {
+                for (s, &v) in sum.iter_mut().zip(c) {
+                    *s += v as usize * n;
+                }
+            }
+            sum.map(|s| ((s + total / 2) / total) as u8)
+        })
+        .collect()
+}
+
+fn nearest(palette: &[Rgb], c: Rgb) -> u8 {
+    let distance = |p: &Rgb| -> i32 { (0..3).map(|ch| (p[ch] as i32 - c[ch] as i32).pow(2)).sum() };
+
+    (0..palette.len())
+        .min_by_key(|&i| distance(&palette[i]))
+        .unwrap() as u8
+}
+
+/// packs variable width codes into bytes, least significant bit first.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, width: u32) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += width;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// compresses palette indices with gif's flavour of lzw.
+fn lzw_compress(indices: &[u8], min_code_size: u32) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+
+    let mut out = BitWriter {
+        bytes: vec![],
+        buffer: 0,
+        bits: 0,
+    };
+    let mut dictionary: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut width = min_code_size + 1;
+    let mut next = end + 1;
+
+    out.write(clear, width);
+    let Some((&first, rest)) = indices.split_first() else {
+        out.write(end, width);
+        return out.finish();
+    };
+
+    let mut prefix = first as u16;
+    for &k in rest {
+        if let Some(&code) = dictionary.get(&(prefix, k)) {
+            prefix = code;
+            continue;
+        }
+
+        out.write(prefix, width);
+        dictionary.insert((prefix, k), next);
+        if next >= 1 << width {
+            width += 1;
+        }
+        next += 1;
+        prefix = k as u16;
+
+        if next > MAX_CODE {
+            out.write(clear, width);
+            dictionary.clear();
+            width = min_code_size + 1;
+            next = end + 1;
+        }
+    }
+
+    out.write(prefix, width);
+    out.write(end, width);
+    out.finish()
+}
+
+/// writes an animated gif one frame at a time. call `finish` after the last
+/// frame to write the trailer.
+pub struct GifWriter<W: Write> {
+    writer: BufWriter<W>,
+    width: usize,
+    height: usize,
+}
+
+impl<W: Write> GifWriter<W> {
+    /// writes the header for a `width` by `height` animation. gifs can't be
+    /// wider or taller than 65535 pixels.
+    pub fn new(writer: W, width: usize, height: usize, repeat: Repeat) -> std::io::Result<Self> {
+        assert!(
+            width <= u16::MAX as usize && height <= u16::MAX as usize,
+            "a gif can't be {}x{}",
+            width,
+            height
+        );
+
+        let mut w = BufWriter::new(writer);
+        w.write_all(b"GIF89a")?;
+        w.write_all(&(width as u16).to_le_bytes())?;
+        w.write_all(&(height as u16).to_le_bytes())?;
+        // no global color table, background index 0, square pixels.
+        w.write_all(&[0, 0, 0])?;
+
+        let loops = match repeat {
+            Repeat::Once => None,
+            Repeat::Forever => Some(0),
+            Repeat::Times(n) => Some(n),
+        };
+        if let Some(loops) = loops {
+            w.write_all(&[0x21, 0xff, 0x0b])?;
+            w.write_all(b"NETSCAPE2.0")?;
+            w.write_all(&[0x03, 0x01])?;
+            w.write_all(&loops.to_le_bytes())?;
+            w.write_all(&[0])?;
+        }
+
+        Ok(Self {
+            writer: w,
+            width,
+            height,
+        })
+    }
+
+    /// appends `frame`, which must be the size given to `new`, and shows it for
+    /// `delay`, to the nearest hundredth of a second. colors are rounded to
+    /// 8-bit srgb and then to the nearest palette entry. to dither, pass in
+    /// `dithered(max, TransferFunction::Srgb, ..)` with few enough levels for
+    /// the palette to hold them all, e.g. a `max` of 5 for 216 colors.
+    pub fn write_frame(&mut self, frame: &Canvas, delay: Duration) -> std::io::Result<()> {
+        assert!(
+            frame.width == self.width && frame.height == self.height,
+            "can't add a {}x{} frame to a {}x{} gif",
+            frame.width,
+            frame.height,
+            self.width,
+            self.height
+        );
+
+        let encode = |c: ColorInt| [c.r as u8, c.g as u8, c.b as u8];
+        let pixels: Vec<Option<Rgb>> = (0..frame.height)
+            .flat_map(|y| (0..frame.width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let rgba = frame.rgba(x, y);
+                (rgba.alpha >= 0.5).then(|| {
+                    encode(
+                        TransferFunction::Srgb
+                            .encode_color(rgba.to_straight())
+                            .to_int(255),
+                    )
+                })
+            })
+            .collect();
+
+        let transparent = pixels.iter().any(|p| p.is_none());
+        let mut histogram = HashMap::new();
+        for &c in pixels.iter().flatten() {
+            *histogram.entry(c).or_insert(0) += 1;
+        }
+
+        // the transparent index goes after the colors, so only needs a slot
+        // when it's used.
+        let colors = median_cut(&histogram, if transparent { 255 } else { 256 });
+        let transparent_index = colors.len() as u8;
+
+        let mut lookup: HashMap<Rgb, u8> = HashMap::new();
+        let indices: Vec<u8> = pixels
+            .iter()
+            .map(|p| match p {
+                Some(c) => *lookup.entry(*c).or_insert_with(|| nearest(&colors, *c)),
+                None => transparent_index,
+            })
+            .collect();
+
+        let mut palette = colors;
+        if transparent {
+            palette.push([0, 0, 0]);
+        }
+
+        // color tables hold a power of two entries, at least two.
+        let bits = palette.len().max(2).next_power_of_two().trailing_zeros();
+        palette.resize(1 << bits, [0, 0, 0]);
+
+        let w = &mut self.writer;
+        let centiseconds = ((delay.as_millis() + 5) / 10).min(u16::MAX as u128) as u16;
+        // transparent frames are cleared before the next one, so nothing shows
+        // through from underneath.
+        let disposal: u8 = if transparent { 2 } else { 1 };
+        w.write_all(&[0x21, 0xf9, 0x04, disposal << 2 | transparent as u8])?;
+        w.write_all(&centiseconds.to_le_bytes())?;
+        w.write_all(&[if transparent { transparent_index } else { 0 }, 0])?;
+
+        w.write_all(&[0x2c, 0, 0, 0, 0])?;
+        w.write_all(&(self.width as u16).to_le_bytes())?;
+        w.write_all(&(self.height as u16).to_le_bytes())?;
+        w.write_all(&[0x80 | (bits as u8 - 1)])?;
+        for c in &palette {
+            w.write_all(c)?;
+        }
+
+        let min_code_size = bits.max(2);
+        w.write_all(&[min_code_size as u8])?;
+        for block in lzw_compress(&indices, min_code_size).chunks(255) {
+            w.write_all(&[block.len() as u8])?;
+            w.write_all(block)?;
+        }
+        w.write_all(&[0])
+    }
+
+    /// writes the trailer and flushes, handing back the underlying writer.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.writer.write_all(&[0x3b])?;
+        self.writer.into_inner().map_err(|e| e.into_error())
+    }
+}
+
+/// writes `frames` as a gif that loops forever, showing each for `delay`.
+/// panics if the frames differ in size.
+pub fn write_gif<W: Write>(writer: W, frames: &[Canvas], delay: Duration) -> std::io::Result<()> {
+    let (width, height) = frames.first().map_or((0, 0), |f| (f.width, f.height));
+
+    let mut gif = GifWriter::new(writer, width, height, Repeat::Forever)?;
+    for frame in frames {
+        gif.write_frame(frame, delay)?;
+    }
+    gif.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    /// a frame read back out of a gif.
+    struct Frame {
+        delay: u16,
+        transparent: Option<u8>,
+        palette: Vec<Rgb>,
+        indices: Vec<u8>,
+    }
+
+    fn lzw_decompress(data: &[u8], min_code_size: u32) -> Vec<u8> {
+        let clear = 1u16 << min_code_size;
+        let end = clear + 1;
+        let reset = || -> Vec<Vec<u8>> { (0..clear + 2).map(|i| vec![i as u8]).collect() };
+
+        let mut table = reset();
+        let mut width = min_code_size + 1;
+        let (mut buffer, mut bits, mut pos) = (0u32, 0, 0);
+        let mut prev: Option<u16> = None;
+        let mut out = vec![];
+
+        loop {
+            while bits < width {
+                buffer |= (data[pos] as u32) << bits;
+                pos += 1;
+                bits += 8;
+            }
+            let code = (buffer & ((1 << width) - 1)) as u16;
+            buffer >>= width;
+            bits -= width;
+
+            if code == clear {
+                table = reset();
+                width = min_code_size + 1;
+                prev = None;
+                continue;
+            }
+            if code == end {
+                return out;
+            }
+
+            let entry = match prev {
+                _ if (code as usize) < table.len() => table[code as usize].clone(),
+                Some(p) => {
+                    let mut e = table[p as usize].clone();
+                    e.push(e[0]);
+                    e
+                }
+                None => panic!("first code {} isn't in the table", code),
+            };
+            out.extend_from_slice(&entry);
+
+            if let Some(p) = prev {
+                if table.len() <= MAX_CODE as usize {
+                    let mut e = table[p as usize].clone();
+                    e.push(entry[0]);
+                    table.push(e);
+                }
+            }
+            if table.len() == 1 << width && width < 12 {
+                width += 1;
+            }
+            prev = Some(code);
+        }
+    }
+
+    /// a minimal reader for the gifs this module writes.
+    fn read_gif(data: &[u8]) -> (usize, usize, Option<u16>, Vec<Frame>) {
+        assert_eq!(&data[..6], b"GIF89a");
+        let u16_at = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+        let (width, height) = (u16_at(6) as usize, u16_at(8) as usize);
+
+        let mut pos = 13;
+        let mut loops = None;
+        let mut frames = vec![];
+        let (mut delay, mut transparent) = (0, None);
+
+        loop {
+            match data[pos] {
+                0x21 if data[pos + 1] == 0xff => {
+                    assert_eq!(&data[pos + 3..pos + 14], b"NETSCAPE2.0");
+                    loops = Some(u16_at(pos + 16));
+                    pos += 19;
+                }
+                0x21 if data[pos + 1] == 0xf9 => {
+                    delay = u16_at(pos + 4);
+                    transparent = (data[pos + 3] & 1 == 1).then_some(data[pos + 6]);
+                    pos += 8;
+                }
+                0x2c => {
+                    let packed = data[pos + 9];
+                    let entries = 2usize << (packed & 7);
+                    pos += 10;
+                    let palette = data[pos..pos + 3 * entries]
+                        .chunks(3)
+                        .map(|c| [c[0], c[1], c[2]])
+                        .collect();
+                    pos += 3 * entries;
+
+                    let min_code_size = data[pos] as u32;
+                    pos += 1;
+                    let mut lzw = vec![];
+                    while data[pos] != 0 {
+                        let n = data[pos] as usize;
+                        lzw.extend_from_slice(&data[pos + 1..pos + 1 + n]);
+                        pos += 1 + n;
+                    }
+                    pos += 1;
+
+                    frames.push(Frame {
+                        delay,
+                        transparent,
+                        palette,
+                        indices: lzw_decompress(&lzw, min_code_size),
+                    });
+                }
+                0x3b => return (width, height, loops, frames),
+                b => panic!("unexpected block {:#x}", b),
+            }
+        }
+    }
+
+    fn colors(frame: &Frame) -> Vec<Rgb> {
+        frame
+            .indices
+            .iter()
+            .map(|&i| frame.palette[i as usize])
+            .collect()
+    }
+
+    #[test]
+    fn test_lzw_round_trip() {
+        let runs: Vec<u8> = (0..5000).map(|i| ((i / 7) % 4) as u8).collect();
+        let noisy: Vec<u8> = (0..20000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect();
+
+        assert_eq!(lzw_decompress(&lzw_compress(&runs, 2), 2), runs);
+        assert_eq!(lzw_decompress(&lzw_compress(&noisy, 8), 8), noisy);
+        assert_eq!(lzw_decompress(&lzw_compress(&[], 2), 2), vec![]);
+        assert!(lzw_compress(&runs, 2).len() < runs.len() / 4);
+    }
+
+    #[test]
+    fn test_median_cut() {
+        let mut histogram = HashMap::new();
+        histogram.insert([0, 0, 0], 10);
+        histogram.insert([255, 255, 255], 10);
+        assert_eq!(median_cut(&histogram, 4), vec![[0, 0, 0], [255, 255, 255]]);
+
+        // two tight clusters collapse to one color each.
+        let mut histogram = HashMap::new();
+        for i in 0..10 {
+            histogram.insert([200 + i, 10, 10], 1);
+            histogram.insert([10, 10, 200 + i], 1);
+        }
+        let mut palette = median_cut(&histogram, 2);
+        palette.sort_unstable();
+        assert_eq!(palette, vec![[10, 10, 205], [205, 10, 10]]);
+    }
+
+    #[test]
+    fn test_write_gif() {
+        let mut a = Canvas::new(4, 2);
+        a.write_pixel(1, 0, Color::new(1.0, 0.0, 0.0));
+        let mut b = Canvas::new(4, 2);
+        b.write_pixel(3, 1, Color::new(1.0, 1.0, 1.0));
+
+        let mut out = vec![];
+        write_gif(&mut out, &[a, b], Duration::from_millis(40)).unwrap();
+        let (width, height, loops, frames) = read_gif(&out);
+
+        assert_eq!((width, height, loops), (4, 2, Some(0)));
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].delay, 4);
+        assert_eq!(frames[0].transparent, None);
+
+        let black = [0, 0, 0];
+        assert_eq!(
+            colors(&frames[0]),
+            vec![black, [255, 0, 0], black, black, black, black, black, black]
+        );
+        assert_eq!(colors(&frames[1])[7], [255, 255, 255]);
+    }
+
+    #[test]
+    fn test_gif_encodes_srgb() {
+        let mut c = Canvas::new(1, 1);
+        c.write_pixel(0, 0, Color::new(0.5, 0.5, 0.5));
+
+        let mut gif = GifWriter::new(vec![], 1, 1, Repeat::Once).unwrap();
+        gif.write_frame(&c, Duration::from_millis(100)).unwrap();
+        let (_, _, loops, frames) = read_gif(&gif.finish().unwrap());
+
+        assert_eq!(loops, None);
+        assert_eq!(frames[0].delay, 10);
+        assert_eq!(colors(&frames[0]), vec![[188, 188, 188]]);
+    }
+
+    #[test]
+    fn test_gif_transparency() {
+        let mut c = Canvas::new_transparent(2, 1);
+        c.write_pixel(0, 0, Color::new(0.0, 1.0, 0.0));
+
+        let mut out = vec![];
+        write_gif(&mut out, &[c], Duration::ZERO).unwrap();
+        let (_, _, _, frames) = read_gif(&out);
+
+        let transparent = frames[0].transparent.unwrap();
+        assert_eq!(frames[0].indices[1], transparent);
+        assert_eq!(
+            frames[0].palette[frames[0].indices[0] as usize],
+            [0, 255, 0]
+        );
+    }
+
+    #[test]
+    fn test_gif_many_colors() {
+        let mut c = Canvas::new(64, 64);
+        for y in 0..64 {
+            for x in 0..64 {
+                c.write_pixel(x, y, Color::new(x as f64 / 63.0, y as f64 / 63.0, 0.5));
+            }
+        }
+
+        let mut out = vec![];
+        write_gif(&mut out, &[c], Duration::ZERO).unwrap();
+        let (_, _, _, frames) = read_gif(&out);
+
+        assert_eq!(frames[0].palette.len(), 256);
+        assert_eq!(frames[0].indices.len(), 64 * 64);
+
+        // every pixel should land on a palette color close to its own.
+        let encoded = |v: f64| (TransferFunction::Srgb.encode(v) * 255.0).round() as i32;
+        for (i, got) in colors(&frames[0]).iter().enumerate() {
+            let (x, y) = (i % 64, i / 64);
+            assert!((got[0] as i32 - encoded(x as f64 / 63.0)).abs() <= 24);
+            assert!((got[1] as i32 - encoded(y as f64 / 63.0)).abs() <= 24);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_gif_frame_size_mismatch() {
+        write_gif(
+            vec![],
+            &[Canvas::new(2, 2), Canvas::new(3, 2)],
+            Duration::ZERO,
+        )
+        .unwrap();
+    }
+}
@@ -0,0 +1,286 @@
+//! golden image regression testing: compare a render against a checked-in
+//! reference ppm, allowing for numeric drift but not visible change.
+//!
+//! set `RENACHAN_BLESS=1` to write the current renders out as the new
+//! references instead of checking them.
+
+use crate::{
+    canvas::Canvas,
+    color::Color,
+    compare::DiffStats,
+    ppm::{PpmError, PpmFormat},
+};
+use std::{
+    fmt,
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+/// how different a render may be from its reference before the test fails.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    /// the smallest acceptable structural similarity.
+    pub min_ssim: f64,
+    /// the largest acceptable ciede2000 difference at any one pixel. around
+    /// 1.0 is just noticeable side by side.
+    pub max_delta_e: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            min_ssim: 0.99,
+            max_delta_e: 2.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GoldenError {
+    Io(std::io::Error),
+    Ppm(PpmError),
+    /// there's no reference yet. rerun with `RENACHAN_BLESS=1` to make one.
+    MissingReference(PathBuf),
+    SizeMismatch {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    /// the render differs too much. the heatmap and the render itself were
+    /// written next to the reference.
+    Mismatch {
+        stats: DiffStats,
+        delta_e: f64,
+        diff: PathBuf,
+    },
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GoldenError::Io(e) => write!(f, "error reading or writing golden image: {}", e),
+            GoldenError::Ppm(e) => write!(f, "invalid reference image: {}", e),
+            GoldenError::MissingReference(path) => write!(
+                f,
+                "no reference image at {}, rerun with RENACHAN_BLESS=1 to create it",
+                path.display()
+            ),
+            GoldenError::SizeMismatch { expected, found } => write!(
+                f,
+                "render is {}x{} but the reference is {}x{}",
+                found.0, found.1, expected.0, expected.1
+            ),
+            GoldenError::Mismatch {
+                stats,
+                delta_e,
+                diff,
+            } => write!(
+                f,
+                "render differs from the reference (ssim {:.4}, max delta e {:.2}, psnr {:.1}db), see {}",
+                stats.ssim,
+                delta_e,
+                stats.psnr,
+                diff.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GoldenError::Io(e) => Some(e),
+            GoldenError::Ppm(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for GoldenError {
+    fn from(e: std::io::Error) -> Self {
+        GoldenError::Io(e)
+    }
+}
+
+impl From<PpmError> for GoldenError {
+    fn from(e: PpmError) -> Self {
+        GoldenError::Ppm(e)
+    }
+}
+
+/// `reference` with `suffix` spliced in before its extension, so
+/// `clock.ppm` becomes `clock.diff.ppm`.
+fn sibling(reference: &Path, suffix: &str) -> PathBuf {
+    let stem = reference.file_stem().unwrap_or_default().to_string_lossy();
+    reference.with_file_name(format!("{}.{}.ppm", stem, suffix))
+}
+
+fn bless_requested() -> bool {
+    std::env::var_os("RENACHAN_BLESS").is_some_and(|v| !v.is_empty() && v != "0")
+}
+
+/// checks `render` against the ppm at `reference`, returning the statistics
+/// if it's within `tolerance`.
+///
+/// on failure the diff heatmap and the render are written next to the
+/// reference as `<name>.diff.ppm` and `<name>.actual.ppm`.
+pub fn check(
+    reference: &Path,
+    render: &Canvas,
+    tolerance: Tolerance,
+) -> Result<DiffStats, GoldenError> {
+    if bless_requested() {
+        render.write_ppm(File::create(reference)?, PpmFormat::Raw)?;
+    }
+
+    compare(reference, render, tolerance)
+}
+
+fn compare(
+    reference: &Path,
+    render: &Canvas,
+    tolerance: Tolerance,
+) -> Result<DiffStats, GoldenError> {
+    let expected = match File::open(reference) {
+        Ok(file) => Canvas::from_ppm(file)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(GoldenError::MissingReference(reference.to_path_buf()))
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    if (expected.width, expected.height) != (render.width, render.height) {
+        return Err(GoldenError::SizeMismatch {
+            expected: (expected.width, expected.height),
+            found: (render.width, render.height),
+        });
+    }
+
+    // nan would quantize to black and could slip past a dark reference, so
+    // it always counts as a mismatch.
+    let has_nan = render
+        .pixels
+        .iter()
+        .any(|c| c.r.is_nan() || c.g.is_nan() || c.b.is_nan());
+
+    // the reference went through 8 bits, so compare against a render that has
+    // too, otherwise quantization alone eats into the tolerance.
+    let render = render.map(|c| {
+        let q = c.to_int(255);
+        Color::new(q.r as f64, q.g as f64, q.b as f64) * (1.0 / 255.0)
+    });
+
+    let stats = render.diff_stats(&expected);
+    let delta_e = if has_nan {
+        f64::INFINITY
+    } else {
+        render
+            .pixels
+            .iter()
+            .zip(expected.pixels.iter())
+            .map(|(&a, &b)| a.delta_e2000(b))
+            .fold(0.0, f64::max)
+    };
+
+    if stats.ssim >= tolerance.min_ssim && delta_e <= tolerance.max_delta_e {
+        return Ok(stats);
+    }
+
+    let diff = sibling(reference, "diff");
+    render
+        .diff(&expected)
+        .write_ppm(File::create(&diff)?, PpmFormat::Raw)?;
+    render.write_ppm(File::create(sibling(reference, "actual"))?, PpmFormat::Raw)?;
+
+    Err(GoldenError::Mismatch {
+        stats,
+        delta_e,
+        diff,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenes;
+
+    fn reference(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(name)
+    }
+
+    // removes the directory even if an assertion fails partway through.
+    struct TempDir(PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn assert_golden(name: &str, render: &Canvas) {
+        if let Err(e) = check(&reference(name), render, Tolerance::default()) {
+            panic!("{}: {}", name, e);
+        }
+    }
+
+    #[test]
+    fn test_golden_clock() {
+        assert_golden("clock.ppm", &scenes::clock(120));
+    }
+
+    #[test]
+    fn test_golden_lit_sphere() {
+        assert_golden("lit_sphere.ppm", &scenes::lit_sphere(100));
+    }
+
+    #[test]
+    fn test_golden_reflective() {
+        assert_golden("reflective.ppm", &scenes::reflective(100));
+    }
+
+    #[test]
+    fn test_golden_catches_changes() {
+        let dir =
+            TempDir(std::env::temp_dir().join(format!("renachan-golden-{}", std::process::id())));
+        std::fs::create_dir_all(&dir.0).unwrap();
+        let path = dir.0.join("scene.ppm");
+
+        let render = scenes::clock(64);
+        render
+            .write_ppm(File::create(&path).unwrap(), PpmFormat::Raw)
+            .unwrap();
+
+        // slightly dimmer passes, a new mark doesn't.
+        let drift = render.map(|c| c * 0.99);
+        assert!(compare(&path, &drift, Tolerance::default()).is_ok());
+        assert!(!sibling(&path, "diff").exists());
+
+        let mut changed = render.clone();
+        changed.fill_circle(20, 20, 3, Color::new(1.0, 0.0, 0.0));
+        match compare(&path, &changed, Tolerance::default()) {
+            Err(GoldenError::Mismatch { diff, .. }) => {
+                assert!(diff.exists());
+                assert!(sibling(&path, "actual").exists());
+            }
+            other => panic!("expected a mismatch, got {:?}", other),
+        }
+
+        // nan in a black area would otherwise quantize to a perfect match.
+        let mut broken = render.clone();
+        broken.write_pixel(0, 0, Color::new(f64::NAN, 0.0, 0.0));
+        assert!(matches!(
+            compare(&path, &broken, Tolerance::default()),
+            Err(GoldenError::Mismatch { .. })
+        ));
+
+        assert!(matches!(
+            compare(&path, &Canvas::new(3, 3), Tolerance::default()),
+            Err(GoldenError::SizeMismatch { .. })
+        ));
+        assert!(matches!(
+            compare(&dir.0.join("missing.ppm"), &render, Tolerance::default()),
+            Err(GoldenError::MissingReference(_))
+        ));
+    }
+}
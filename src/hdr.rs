@@ -0,0 +1,428 @@
+//! radiance rgbe (`.hdr`) images.
+//!
+//! each pixel is stored as three 8-bit mantissas sharing one exponent byte,
+//! so unlike ppm and png, values above 1.0 survive.
+
+use crate::{canvas::Canvas, color::Color};
+use std::{
+    fmt,
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::Path,
+};
+
+// scanlines of this width can be run-length encoded, outside it they're flat.
+const RLE_WIDTHS: std::ops::RangeInclusive<usize> = 8..=0x7fff;
+
+#[derive(Debug)]
+pub enum HdrError {
+    Io(std::io::Error),
+    InvalidHeader(String),
+    UnexpectedEof,
+    /// a run-length encoded scanline didn't match the image width.
+    InvalidScanline(usize),
+    Unsupported(String),
+}
+
+impl fmt::Display for HdrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HdrError::Io(e) => write!(f, "error reading hdr: {}", e),
+            HdrError::InvalidHeader(what) => write!(f, "invalid hdr header: {}", what),
+            HdrError::UnexpectedEof => write!(f, "unexpected end of file in pixel data"),
+            HdrError::InvalidScanline(y) => write!(f, "invalid run-length data in scanline {}", y),
+            HdrError::Unsupported(what) => write!(f, "unsupported hdr: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for HdrError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HdrError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for HdrError {
+    fn from(e: std::io::Error) -> Self {
+        HdrError::Io(e)
+    }
+}
+
+fn to_rgbe(color: Color) -> [u8; 4] {
+    let (r, g, b) = (color.r.max(0.0), color.g.max(0.0), color.b.max(0.0));
+    let max = r.max(g).max(b);
+    if max < 1e-32 {
+        return [0; 4];
+    }
+
+    // max = m * 2^exp with m in [0.5, 1), so the largest mantissa lands in
+    // [128, 256).
+    let mut exp = max.log2().floor() as i32 + 1;
+    if max / 2f64.powi(exp) >= 1.0 {
+        exp += 1;
+    }
+    let exp = exp.clamp(-128, 127);
+    let scale = 256.0 / 2f64.powi(exp);
+
+    let mantissa = |c: f64| (c * scale).min(255.0) as u8;
+    [mantissa(r), mantissa(g), mantissa(b), (exp + 128) as u8]
+}
+
+fn from_rgbe([r, g, b, e]: [u8; 4]) -> Color {
+    if e == 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+
+    let scale = 2f64.powi(e as i32 - 136);
+    Color::new(r as f64 * scale, g as f64 * scale, b as f64 * scale)
+}
+
+// the usual radiance scheme: runs of 4 or more equal bytes become a count
+// above 128 plus the byte, everything else goes out as literal blocks.
+fn encode_rle(out: &mut Vec<u8>, data: &[u8]) {
+    let run_at = |i: usize, limit: usize| {
+        data[i..]
+            .iter()
+            .take(limit)
+            .take_while(|&&b| b == data[i])
+            .count()
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let run = run_at(i, 127);
+        if run >= 4 {
+            out.push(128 + run as u8);
+            out.push(data[i]);
+            i += run;
+            continue;
+        }
+
+        let start = i;
+        while i < data.len() && i - start < 128 && run_at(i, 4) < 4 {
+            i += 1;
+        }
+        out.push((i - start) as u8);
+        out.extend_from_slice(&data[start..i]);
+    }
+}
+
+impl Canvas {
+    pub fn write_to_hdr(&self, path: &Path) -> std::io::Result<()> {
+        self.write_hdr(File::create(path)?)
+    }
+
+    /// writes the canvas as a run-length encoded radiance `.hdr` to any
+    /// writer. negative components are clamped to 0. scanlines wider than
+    /// 32767 pixels can't be run-length encoded and are written flat, which
+    /// `from_hdr` won't read back.
+    pub fn write_hdr<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        let mut w = BufWriter::new(writer);
+        write!(
+            w,
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            self.height, self.width
+        )?;
+
+        let mut line = Vec::with_capacity(self.width * 4);
+        for row in self.pixels.chunks(self.width.max(1)) {
+            let rgbe: Vec<[u8; 4]> = row.iter().map(|&c| to_rgbe(c)).collect();
+
+            line.clear();
+            if RLE_WIDTHS.contains(&self.width) {
+                line.extend_from_slice(&[2, 2, (self.width >> 8) as u8, self.width as u8]);
+                for component in 0..4 {
+                    let data: Vec<u8> = rgbe.iter().map(|p| p[component]).collect();
+                    encode_rle(&mut line, &data);
+                }
+            } else {
+                line.extend(rgbe.iter().flatten());
+            }
+            w.write_all(&line)?;
+        }
+
+        w.flush()
+    }
+
+    /// reads a radiance `.hdr` with the standard `-Y h +X w` orientation,
+    /// undoing any `EXPOSURE` the file was saved with. scanlines wider than
+    /// 32767 pixels aren't supported.
+    pub fn from_hdr<R: Read>(mut reader: R) -> Result<Canvas, HdrError> {
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+
+        let mut lines = data.split(|&b| b == b'\n');
+        let mut pos = 0;
+        let mut next_line = || {
+            let line = lines.next()?;
+            pos += line.len() + 1;
+            Some(String::from_utf8_lossy(line).trim().to_string())
+        };
+
+        let magic = next_line().unwrap_or_default();
+        if !magic.starts_with("#?") {
+            return Err(HdrError::InvalidHeader("missing #? signature".to_string()));
+        }
+
+        let mut exposure = 1.0;
+        loop {
+            let line = next_line().ok_or(HdrError::UnexpectedEof)?;
+            if line.is_empty() {
+                break;
+            }
+
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return Err(HdrError::Unsupported(format!("format {}", format)));
+                }
+            } else if let Some(value) = line.strip_prefix("EXPOSURE=") {
+                match value.trim().parse::<f64>() {
+                    Ok(e) if e.is_finite() && e > 0.0 => exposure *= e,
+                    _ => {
+                        return Err(HdrError::InvalidHeader(format!(
+                            "invalid exposure {}",
+                            value
+                        )))
+                    }
+                }
+            }
+        }
+
+        let resolution = next_line().ok_or(HdrError::UnexpectedEof)?;
+        let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", h, "+X", w] => (h.parse::<usize>(), w.parse::<usize>()),
+            _ => return Err(HdrError::Unsupported(format!("orientation {}", resolution))),
+        };
+        let (height, width) = match (height, width) {
+            (Ok(h), Ok(w)) => (h, w),
+            _ => {
+                return Err(HdrError::InvalidHeader(format!(
+                    "invalid resolution {}",
+                    resolution
+                )))
+            }
+        };
+
+        if width.checked_mul(height).is_none() {
+            return Err(HdrError::InvalidHeader(format!(
+                "invalid resolution {}",
+                resolution
+            )));
+        }
+
+        // check the file could hold that many scanlines before allocating for
+        // them. a run-length encoded one takes at least two bytes per component
+        // for every 127 pixels, a flat one at least one pixel.
+        let raster = &data[pos.min(data.len())..];
+        let min_scanline = if RLE_WIDTHS.contains(&width) {
+            4 + 8 * width.div_ceil(127)
+        } else {
+            4
+        };
+        if width > 0 && height.saturating_mul(min_scanline) > raster.len() {
+            return Err(HdrError::UnexpectedEof);
+        }
+
+        // old-style repeats can stretch a few bytes across any width, so the
+        // file size alone doesn't bound a flat scanline.
+        if width > *RLE_WIDTHS.end() {
+            return Err(HdrError::Unsupported(format!("width {}", width)));
+        }
+
+        let mut canvas = Canvas::new(width, height);
+        if width == 0 {
+            return Ok(canvas);
+        }
+
+        let mut bytes = raster.iter().copied();
+        let mut next = || bytes.next().ok_or(HdrError::UnexpectedEof);
+        let mut scanline = vec![[0u8; 4]; width];
+
+        for y in 0..height {
+            let start = [next()?, next()?, next()?, next()?];
+
+            if RLE_WIDTHS.contains(&width) && start[0] == 2 && start[1] == 2 && start[2] < 128 {
+                if ((start[2] as usize) << 8 | start[3] as usize) != width {
+                    return Err(HdrError::InvalidScanline(y));
+                }
+
+                for component in 0..4 {
+                    let mut x = 0;
+                    while x < width {
+                        let count = next()? as usize;
+                        let (count, run) = if count > 128 {
+                            (count - 128, Some(next()?))
+                        } else {
+                            (count, None)
+                        };
+                        if count == 0 || x + count > width {
+                            return Err(HdrError::InvalidScanline(y));
+                        }
+
+                        for pixel in &mut scanline[x..x + count] {
+                            pixel[component] = match run {
+                                Some(b) => b,
+                                None => next()?,
+                            };
+                        }
+                        x += count;
+                    }
+                }
+            } else {
+                // flat pixels, possibly with old-style (1, 1, 1, n) repeats.
+                let mut x = 0;
+                let mut shift = 0;
+                let mut pixel = start;
+                loop {
+                    if pixel[..3] == [1, 1, 1] && x > 0 {
+                        let count = (pixel[3] as usize)
+                            .checked_shl(shift)
+                            .ok_or(HdrError::InvalidScanline(y))?;
+                        if count > width - x {
+                            return Err(HdrError::InvalidScanline(y));
+                        }
+                        let previous = scanline[x - 1];
+                        scanline[x..x + count].fill(previous);
+                        x += count;
+                        shift += 8;
+                    } else {
+                        scanline[x] = pixel;
+                        x += 1;
+                        shift = 0;
+                    }
+
+                    if x == width {
+                        break;
+                    }
+                    pixel = [next()?, next()?, next()?, next()?];
+                }
+            }
+
+            for (x, &pixel) in scanline.iter().enumerate() {
+                canvas[(x, y)] = from_rgbe(pixel) * (1.0 / exposure);
+            }
+        }
+
+        Ok(canvas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    #[test]
+    fn test_rgbe() {
+        assert_eq!(to_rgbe(Color::new(0.0, 0.0, 0.0)), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(Color::new(1.0, 0.5, 0.0)), [128, 64, 0, 129]);
+        assert_eq!(to_rgbe(Color::new(-1.0, 0.5, 0.0)), [0, 128, 0, 128]);
+
+        for color in [
+            Color::new(1.0, 0.5, 0.25),
+            Color::new(100.0, 3.0, 0.001),
+            Color::new(0.002, 0.001, 0.003),
+        ] {
+            let decoded = from_rgbe(to_rgbe(color));
+            let max = color.r.max(color.g).max(color.b);
+            assert_approx_eq!(decoded.r, color.r, abs <= max / 128.0);
+            assert_approx_eq!(decoded.g, color.g, abs <= max / 128.0);
+            assert_approx_eq!(decoded.b, color.b, abs <= max / 128.0);
+        }
+    }
+
+    #[test]
+    fn test_encode_rle() {
+        let mut out = vec![];
+        encode_rle(&mut out, &[1, 2, 3, 3, 3, 3, 3, 4]);
+        assert_eq!(out, vec![2, 1, 2, 133, 3, 1, 4]);
+
+        let mut out = vec![];
+        encode_rle(&mut out, &[7; 300]);
+        assert_eq!(out, vec![255, 7, 255, 7, 174, 7]);
+    }
+
+    #[test]
+    fn test_hdr_roundtrip() {
+        for width in [5, 40] {
+            let mut c = Canvas::new(width, 3);
+            for y in 0..3 {
+                for x in 0..width {
+                    let v = if x < width / 2 {
+                        8.0
+                    } else {
+                        x as f64 * 0.25 + y as f64
+                    };
+                    c.write_pixel(x, y, Color::new(v, v * 0.5, 0.125));
+                }
+            }
+
+            let mut out = vec![];
+            c.write_hdr(&mut out).unwrap();
+            let read = Canvas::from_hdr(&out[..]).unwrap();
+
+            assert_eq!(read.width, width);
+            assert_eq!(read.height, 3);
+            for (a, b) in read.pixels.iter().zip(c.pixels.iter()) {
+                assert_approx_eq!(a.r, b.r, rel <= 0.01);
+                assert_approx_eq!(a.g, b.g, rel <= 0.02);
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_hdr_exposure_and_old_rle() {
+        let mut file = b"#?RGBE\nEXPOSURE=2.0\n\n-Y 1 +X 4\n".to_vec();
+        file.extend_from_slice(&[128, 64, 0, 130, 1, 1, 1, 3]);
+
+        let c = Canvas::from_hdr(&file[..]).unwrap();
+        for x in 0..4 {
+            assert_eq!(c[(x, 0)], Color::new(1.0, 0.5, 0.0));
+        }
+    }
+
+    #[test]
+    fn test_read_hdr_errors() {
+        assert!(matches!(
+            Canvas::from_hdr(&b"P3\n1 1\n255\n"[..]),
+            Err(HdrError::InvalidHeader(_))
+        ));
+        assert!(matches!(
+            Canvas::from_hdr(&b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n"[..]),
+            Err(HdrError::Unsupported(_))
+        ));
+        assert!(matches!(
+            Canvas::from_hdr(&b"#?RADIANCE\n\n+X 1 -Y 1\n"[..]),
+            Err(HdrError::Unsupported(_))
+        ));
+        assert!(matches!(
+            Canvas::from_hdr(&b"#?RADIANCE\n\n-Y 2 +X 1\n\x80\x80\x80\x81"[..]),
+            Err(HdrError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            Canvas::from_hdr(&b"#?RADIANCE\n\n-Y 100000 +X 100000\n\x80\x80\x80\x81"[..]),
+            Err(HdrError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            Canvas::from_hdr(
+                &b"#?RADIANCE\n\n-Y 1 +X 4000000000\n\x80\x80\x80\x81\x01\x01\x01\xff"[..]
+            ),
+            Err(HdrError::Unsupported(_))
+        ));
+        let huge = format!("#?RADIANCE\n\n-Y {0} +X {0}\n", usize::MAX);
+        assert!(matches!(
+            Canvas::from_hdr(huge.as_bytes()),
+            Err(HdrError::InvalidHeader(_))
+        ));
+        for exposure in ["0", "-1.5", "inf", "NaN"] {
+            let file = format!("#?RADIANCE\nEXPOSURE={}\n\n-Y 1 +X 1\n", exposure);
+            assert!(matches!(
+                Canvas::from_hdr(file.as_bytes()),
+                Err(HdrError::InvalidHeader(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,33 @@
+pub mod approx;
+pub mod blend;
+pub mod bloom;
+pub mod canvas;
+pub mod color;
+pub mod compare;
+pub mod convolve;
+pub mod deflate;
+pub mod dither;
+pub mod draw;
+pub mod exr;
+pub mod font;
+pub mod gif;
+#[cfg(test)]
+mod golden;
+pub mod hdr;
+pub mod matrix;
+pub mod png;
+pub mod point;
+pub mod ppm;
+pub mod resample;
+pub mod rgba;
+pub mod scalar;
+pub mod scenes;
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+pub mod simd;
+pub mod spectrum;
+pub mod tonemap;
+pub mod transfer;
+pub mod transformation;
+pub mod tuple;
+pub mod vector;
+pub mod y4m;
@@ -1,16 +1,30 @@
-use crate::tuple::Tuple;
-use float_eq::float_eq;
+use crate::approx::{ApproxEq, ApproxScalar, EPSILON};
+use crate::point::Point3;
+use crate::scalar::Scalar;
+use crate::tuple::GenericTuple;
+use crate::vector::Vector3;
+use num_traits::Float;
 use std::ops::{Add, Index, IndexMut, Mul, Sub};
 
 #[derive(Clone, Debug)]
-pub struct Matrix {
+pub struct GenericMatrix<T> {
     pub width: usize,
     pub height: usize,
-    pub data: Vec<f64>,
+    pub data: Vec<T>,
 }
 
-impl Matrix {
-    pub fn new(width: usize, height: usize, data: Vec<f64>) -> Self {
+pub type Matrix = GenericMatrix<f64>;
+pub type Matrix32 = GenericMatrix<f32>;
+
+// keeps results stable across the chained products and inverses we do,
+// rounding to 5 decimal places.
+fn round5<T: Float>(x: T) -> T {
+    let scale = T::from(100000.0).unwrap();
+    (x * scale).round() / scale
+}
+
+impl<T: Scalar> GenericMatrix<T> {
+    pub fn new(width: usize, height: usize, data: Vec<T>) -> Self {
         Self {
             width,
             height,
@@ -18,11 +32,20 @@ impl Matrix {
         }
     }
 
+    // the fixed-size view the 4x4 kernels work on, if this is a 4x4 matrix.
+    fn as_mat4(&self) -> Option<&[T; 16]> {
+        if self.width != 4 || self.height != 4 {
+            return None;
+        }
+
+        self.data.as_slice().try_into().ok()
+    }
+
     pub fn size(width: usize, height: usize) -> Self {
         Self {
             width,
             height,
-            data: vec![0.0; width * height],
+            data: vec![T::zero(); width * height],
         }
     }
 
@@ -31,9 +54,9 @@ impl Matrix {
         for x in 0..self.width {
             for y in 0..self.height {
                 if x == y {
-                    data.push(1.0);
+                    data.push(T::one());
                 } else {
-                    data.push(0.0);
+                    data.push(T::zero());
                 }
             }
         }
@@ -50,9 +73,9 @@ impl Matrix {
         for x in 0..size {
             for y in 0..size {
                 if x == y {
-                    data.push(1.0);
+                    data.push(T::one());
                 } else {
-                    data.push(0.0);
+                    data.push(T::zero());
                 }
             }
         }
@@ -79,7 +102,7 @@ impl Matrix {
         }
     }
 
-    pub fn determinant(&self) -> f64 {
+    pub fn determinant(&self) -> T {
         if self.width != self.height {
             panic!("cannot calculate determinant for non-square matrices");
         }
@@ -87,9 +110,9 @@ impl Matrix {
         if self.width == 2 {
             self[(0, 0)] * self[(1, 1)] - self[(0, 1)] * self[(1, 0)]
         } else {
-            let mut determinant = 0.0;
+            let mut determinant = T::zero();
             for x in 0..self.width {
-                determinant += self[(0, x)] * self.cofactor(0, x)
+                determinant = determinant + self[(0, x)] * self.cofactor(0, x)
             }
 
             determinant
@@ -114,12 +137,12 @@ impl Matrix {
         }
     }
 
-    pub fn minor(&self, row: usize, col: usize) -> f64 {
+    pub fn minor(&self, row: usize, col: usize) -> T {
         self.submatrix(row, col).determinant()
     }
 
-    pub fn cofactor(&self, row: usize, col: usize) -> f64 {
-        if (row + col) % 2 != 0 {
+    pub fn cofactor(&self, row: usize, col: usize) -> T {
+        if (row + col) % 2 == 1 {
             return -self.minor(row, col);
         }
 
@@ -127,7 +150,7 @@ impl Matrix {
     }
 
     pub fn is_invertible(&self) -> bool {
-        self.determinant() != 0.0
+        self.determinant() != T::zero()
     }
 
     pub fn inverse(&self) -> Self {
@@ -135,45 +158,62 @@ impl Matrix {
             panic!("cannot invert matrices with determinant of 0")
         }
 
-        let mut inverse = Matrix::size(self.width, self.height);
+        let mut inverse = Self::size(self.width, self.height);
         for row in 0..self.width {
             for col in 0..self.width {
                 let cofactor = self.cofactor(row, col);
-                inverse[(col, row)] = (cofactor / self.determinant() * 100000.0).round() / 100000.0;
+                inverse[(col, row)] = round5(cofactor / self.determinant());
             }
         }
         inverse
     }
 }
 
-impl PartialEq for Matrix {
+impl<T: ApproxScalar> ApproxEq for GenericMatrix<T> {
+    type Scalar = T;
+
+    fn all_components<F>(&self, other: &Self, f: F) -> bool
+    where
+        F: Fn(&T, &T) -> bool,
+    {
+        self.width == other.width
+            && self.height == other.height
+            && self
+                .data
+                .iter()
+                .zip(other.data.iter())
+                .all(|(x, y)| f(x, y))
+    }
+
+    /// within `EPSILON`, or 0.1% of the larger entry. products with a rounded
+    /// inverse drift further than `EPSILON` on large entries.
+    fn approx_eq(&self, other: &Self) -> bool {
+        let (abs, rel) = (T::from(EPSILON).unwrap(), T::from(0.001).unwrap());
+        self.all_components(other, |a, b| a.eq_abs(b, &abs) || a.eq_rmax(b, &rel))
+    }
+}
+
+impl<T: ApproxScalar> PartialEq for GenericMatrix<T> {
     fn eq(&self, other: &Self) -> bool {
-        let result = (self.width == other.width) && (self.height == other.height);
-        if !result {
-            return result;
-        }
-        self.data
-            .iter()
-            .zip(other.data.iter())
-            .fold(true, |acc, (x, y)| acc && float_eq!(x, y, rmin <= 0.001))
+        self.approx_eq(other)
     }
 }
 
-impl Eq for Matrix {}
+impl<T: ApproxScalar> Eq for GenericMatrix<T> {}
 
-impl Add for Matrix {
-    type Output = Matrix;
+impl<T: Scalar> Add for GenericMatrix<T> {
+    type Output = Self;
 
     fn add(self, other: Self) -> Self {
         if self.width != other.width || self.height != other.height {
             panic!("cannot add two matrices of different dimensions");
         }
 
-        let result: Vec<f64> = self
+        let result: Vec<T> = self
             .data
             .iter()
             .zip(other.data.iter())
-            .map(|(x, y)| x + y)
+            .map(|(&x, &y)| x + y)
             .collect();
 
         Self {
@@ -184,19 +224,19 @@ impl Add for Matrix {
     }
 }
 
-impl Sub for Matrix {
-    type Output = Matrix;
+impl<T: Scalar> Sub for GenericMatrix<T> {
+    type Output = Self;
 
     fn sub(self, other: Self) -> Self {
         if self.width != other.width || self.height != other.height {
             panic!("cannot subtract two matrices of different dimensions");
         }
 
-        let result: Vec<f64> = self
+        let result: Vec<T> = self
             .data
             .iter()
             .zip(other.data.iter())
-            .map(|(x, y)| x - y)
+            .map(|(&x, &y)| x - y)
             .collect();
 
         Self {
@@ -207,35 +247,43 @@ impl Sub for Matrix {
     }
 }
 
-impl Mul<f64> for Matrix {
-    type Output = Matrix;
+impl<T: Scalar> Mul<T> for GenericMatrix<T> {
+    type Output = Self;
 
-    fn mul(self, other: f64) -> Self {
+    fn mul(self, other: T) -> Self {
         Self {
             width: self.width,
             height: self.height,
-            data: self.data.iter().map(|x| x * other).collect(),
+            data: self.data.iter().map(|&x| x * other).collect(),
         }
     }
 }
 
-impl Mul<Matrix> for Matrix {
-    type Output = Matrix;
+impl<T: Scalar> Mul<GenericMatrix<T>> for GenericMatrix<T> {
+    type Output = Self;
 
     fn mul(self, other: Self) -> Self {
         if self.height != other.width {
             panic!("number of columns in the first matrix should be equal to number of rows in the second matrix!");
         }
 
+        if let (Some(a), Some(b)) = (self.as_mat4(), other.as_mat4()) {
+            return Self {
+                width: 4,
+                height: 4,
+                data: T::mat4_mul_mat4(a, b).map(round5).to_vec(),
+            };
+        }
+
         let mut result = vec![];
 
         for i in 0..self.width {
             for j in 0..other.height {
-                let mut sum = 0.0;
+                let mut sum = T::zero();
                 for k in 0..self.height {
-                    sum += self[(i, k)] * other[(k, j)]
+                    sum = sum + self[(i, k)] * other[(k, j)]
                 }
-                result.push((sum * 100000.0).round() / 100000.0);
+                result.push(round5(sum));
             }
         }
 
@@ -247,31 +295,52 @@ impl Mul<Matrix> for Matrix {
     }
 }
 
-impl Mul<Tuple> for Matrix {
-    type Output = Tuple;
+impl<T: Scalar> GenericMatrix<T> {
+    // the one path for tuples, points and vectors, so they all round alike.
+    fn mul_vec4(&self, v: [T; 4]) -> Option<[T; 4]> {
+        self.as_mat4().map(|m| T::mat4_mul_vec4(m, v).map(round5))
+    }
+}
+
+impl<T: Scalar> Mul<GenericTuple<T>> for GenericMatrix<T> {
+    type Output = GenericTuple<T>;
 
-    fn mul(self, other: Tuple) -> Tuple {
-        if self.height != 4 {
-            panic!("cannot multiply this matrix with a tuple!");
+    fn mul(self, other: GenericTuple<T>) -> GenericTuple<T> {
+        match self.mul_vec4(other.into()) {
+            Some(v) => v.into(),
+            None => panic!("cannot multiply this matrix with a tuple!"),
         }
+    }
+}
+
+impl Mul<Point3> for Matrix {
+    type Output = Point3;
 
-        let tuple_matrix = Matrix::new(4, 1, vec![other.x, other.y, other.z, other.w]);
+    /// treats the point as having an implicit `w` of 1, so translations apply.
+    fn mul(self, other: Point3) -> Point3 {
+        match self.mul_vec4([other.x, other.y, other.z, 1.0]) {
+            Some([x, y, z, _]) => Point3::new(x, y, z),
+            None => panic!("cannot multiply this matrix with a point!"),
+        }
+    }
+}
 
-        let result = self * tuple_matrix;
+impl Mul<Vector3> for Matrix {
+    type Output = Vector3;
 
-        Tuple::new(
-            result[(0, 0)],
-            result[(1, 0)],
-            result[(2, 0)],
-            result[(3, 0)],
-        )
+    /// treats the vector as having an implicit `w` of 0, so translations are ignored.
+    fn mul(self, other: Vector3) -> Vector3 {
+        match self.mul_vec4([other.x, other.y, other.z, 0.0]) {
+            Some([x, y, z, _]) => Vector3::new(x, y, z),
+            None => panic!("cannot multiply this matrix with a vector!"),
+        }
     }
 }
 
-impl Index<(usize, usize)> for Matrix {
-    type Output = f64;
+impl<T> Index<(usize, usize)> for GenericMatrix<T> {
+    type Output = T;
 
-    fn index(&self, (row, col): (usize, usize)) -> &f64 {
+    fn index(&self, (row, col): (usize, usize)) -> &T {
         match self.data.get(col + row * self.height) {
             Some(t) => t,
             None => panic!(
@@ -282,8 +351,8 @@ impl Index<(usize, usize)> for Matrix {
     }
 }
 
-impl IndexMut<(usize, usize)> for Matrix {
-    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut f64 {
+impl<T> IndexMut<(usize, usize)> for GenericMatrix<T> {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut T {
         match self.data.get_mut(col + row * self.height) {
             Some(t) => t,
             None => panic!(
@@ -297,6 +366,7 @@ impl IndexMut<(usize, usize)> for Matrix {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tuple::Tuple;
 
     #[test]
     fn test_new_matrix() {
@@ -416,6 +486,66 @@ mod tests {
         assert_eq!(matrix * tuple, result);
     }
 
+    #[test]
+    fn test_mul_matrix_point() {
+        let matrix = Matrix::new(
+            4,
+            4,
+            vec![
+                1.0, 2.0, 3.0, 4.0, 2.0, 4.0, 4.0, 2.0, 8.0, 6.0, 4.0, 1.0, 0.0, 0.0, 0.0, 1.0,
+            ],
+        );
+
+        assert_eq!(
+            matrix * Point3::new(1.0, 2.0, 3.0),
+            Point3::new(18.0, 24.0, 33.0)
+        );
+    }
+
+    #[test]
+    fn test_mul_matrix_vector() {
+        let matrix = Matrix::new(
+            4,
+            4,
+            vec![
+                1.0, 2.0, 3.0, 4.0, 2.0, 4.0, 4.0, 2.0, 8.0, 6.0, 4.0, 1.0, 0.0, 0.0, 0.0, 1.0,
+            ],
+        );
+
+        assert_eq!(
+            matrix * Vector3::new(1.0, 2.0, 3.0),
+            Vector3::new(14.0, 22.0, 32.0)
+        );
+    }
+
+    #[test]
+    fn test_mul_matrix_point_matches_tuple() {
+        let matrix = Matrix::new(
+            4,
+            4,
+            vec![
+                0.1, 0.2, 0.3, 0.4, 0.7, 0.3, 0.9, 0.1, 0.3, 0.6, 0.2, 0.8, 0.0, 0.0, 0.0, 1.0,
+            ],
+        );
+        let (x, y, z) = (1.0 / 3.0, 2.0 / 7.0, 0.123456789);
+
+        let p = matrix.clone() * Point3::new(x, y, z);
+        let t = matrix.clone() * Tuple::point(x, y, z);
+        assert_eq!((p.x, p.y, p.z), (t.x, t.y, t.z));
+
+        let v = matrix.clone() * Vector3::new(x, y, z);
+        let t = matrix * Tuple::vector(x, y, z);
+        assert_eq!((v.x, v.y, v.z), (t.x, t.y, t.z));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot multiply this matrix with a point!")]
+    fn test_mul_matrix_point_fail() {
+        let matrix = Matrix::size(3, 3);
+
+        let _ = matrix * Point3::origin();
+    }
+
     #[test]
     fn test_cmp_matrix() {
         let m1 = Matrix::new(
@@ -703,4 +833,22 @@ mod tests {
 
         assert_eq!(a, result);
     }
+
+    #[test]
+    fn test_matrix_f32() {
+        let matrix = Matrix32::new(
+            4,
+            4,
+            vec![
+                1.0, 2.0, 3.0, 4.0, 2.0, 4.0, 4.0, 2.0, 8.0, 6.0, 4.0, 1.0, 0.0, 0.0, 0.0, 1.0,
+            ],
+        );
+        let tuple = GenericTuple::new(1.0_f32, 2.0, 3.0, 1.0);
+
+        assert_eq!(
+            matrix.clone() * tuple,
+            GenericTuple::new(18.0, 24.0, 33.0, 1.0)
+        );
+        assert_eq!(matrix.determinant(), -20.0);
+    }
 }
@@ -0,0 +1,766 @@
+use crate::{
+    canvas::Canvas,
+    color::Color,
+    deflate::{zlib_compress, zlib_decompress_limited, DeflateError},
+    rgba::Rgba,
+    transfer::TransferFunction,
+};
+use std::{
+    fmt,
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::Path,
+};
+
+const SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+#[derive(Debug)]
+pub enum PngError {
+    Io(std::io::Error),
+    Deflate(DeflateError),
+    InvalidSignature,
+    /// the named chunk's stored CRC didn't match its contents.
+    CrcMismatch(String),
+    MissingChunk(&'static str),
+    /// a chunk was too short, or the image data didn't fill the image.
+    Truncated(&'static str),
+    /// valid PNG, but using a feature we don't read (e.g. interlacing).
+    Unsupported(String),
+    InvalidHeader(String),
+}
+
+impl fmt::Display for PngError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PngError::Io(e) => write!(f, "error reading png: {}", e),
+            PngError::Deflate(e) => write!(f, "error decompressing png: {}", e),
+            PngError::InvalidSignature => write!(f, "not a png file"),
+            PngError::CrcMismatch(chunk) => write!(f, "crc mismatch in {} chunk", chunk),
+            PngError::MissingChunk(chunk) => write!(f, "missing {} chunk", chunk),
+            PngError::Truncated(what) => write!(f, "truncated {}", what),
+            PngError::Unsupported(what) => write!(f, "unsupported png: {}", what),
+            PngError::InvalidHeader(what) => write!(f, "invalid png header: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for PngError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PngError::Io(e) => Some(e),
+            PngError::Deflate(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PngError {
+    fn from(e: std::io::Error) -> Self {
+        PngError::Io(e)
+    }
+}
+
+impl From<DeflateError> for PngError {
+    fn from(e: DeflateError) -> Self {
+        PngError::Deflate(e)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PngFormat {
+    Rgb8,
+    Rgb16,
+    Rgba8,
+    Rgba16,
+}
+
+impl PngFormat {
+    fn channels(self) -> usize {
+        match self {
+            PngFormat::Rgb8 | PngFormat::Rgb16 => 3,
+            PngFormat::Rgba8 | PngFormat::Rgba16 => 4,
+        }
+    }
+
+    fn bit_depth(self) -> u8 {
+        match self {
+            PngFormat::Rgb8 | PngFormat::Rgba8 => 8,
+            PngFormat::Rgb16 | PngFormat::Rgba16 => 16,
+        }
+    }
+
+    fn color_type(self) -> u8 {
+        match self {
+            PngFormat::Rgb8 | PngFormat::Rgb16 => 2,
+            PngFormat::Rgba8 | PngFormat::Rgba16 => 6,
+        }
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb88320 & mask);
+        }
+    }
+
+    !crc
+}
+
+fn write_chunk<W: Write>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+
+    let mut crc_input = kind.to_vec();
+    crc_input.extend_from_slice(data);
+    w.write_all(&crc32(&crc_input).to_be_bytes())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+// applies (or with `encode == false`, reverses) filter `kind` to `row` in place,
+// where `prev` is the previous row's unfiltered bytes and `bpp` the number of
+// bytes per complete pixel.
+fn filter_row(kind: u8, row: &mut [u8], prev: &[u8], bpp: usize, encode: bool) {
+    if encode {
+        // filtering works backwards, so each byte still sees raw neighbours.
+        for i in (0..row.len()).rev() {
+            let predicted = predict(kind, row, prev, bpp, i);
+            row[i] = row[i].wrapping_sub(predicted);
+        }
+    } else {
+        for i in 0..row.len() {
+            let predicted = predict(kind, row, prev, bpp, i);
+            row[i] = row[i].wrapping_add(predicted);
+        }
+    }
+}
+
+fn predict(kind: u8, row: &[u8], prev: &[u8], bpp: usize, i: usize) -> u8 {
+    let a = if i >= bpp { row[i - bpp] } else { 0 };
+    let b = prev[i];
+    let c = if i >= bpp { prev[i - bpp] } else { 0 };
+
+    match kind {
+        1 => a,
+        2 => b,
+        3 => ((a as u16 + b as u16) / 2) as u8,
+        4 => paeth(a, b, c),
+        _ => 0,
+    }
+}
+
+impl Canvas {
+    pub fn write_to_png(&self, path: &Path) -> std::io::Result<()> {
+        self.write_png(File::create(path)?, PngFormat::Rgb8)
+    }
+
+    /// writes the canvas as a PNG to any writer. rgba formats store the alpha
+    /// plane with straight color, as PNG requires; rgb formats drop it, leaving
+    /// the canvas composited over black.
+    pub fn write_png<W: Write>(&self, writer: W, format: PngFormat) -> std::io::Result<()> {
+        self.write_png_encoded(writer, format, TransferFunction::Linear)
+    }
+
+    /// like `write_png`, but passes every pixel through `encoding` before
+    /// quantizing, and tags the file with a matching `sRGB` or `gAMA` chunk
+    /// so viewers know how to display it. samples are rounded; write
+    /// `dithered` with the format's maximum and the same encoding to dither
+    /// them.
+    pub fn write_png_encoded<W: Write>(
+        &self,
+        writer: W,
+        format: PngFormat,
+        encoding: TransferFunction,
+    ) -> std::io::Result<()> {
+        let mut w = BufWriter::new(writer);
+        w.write_all(&SIGNATURE)?;
+
+        let mut header = vec![];
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // bit depth, color type, compression, filter method, no interlacing.
+        header.extend_from_slice(&[format.bit_depth(), format.color_type(), 0, 0, 0]);
+        write_chunk(&mut w, b"IHDR", &header)?;
+
+        // gAMA stores 100000 / gamma. rec.709 has no exact equivalent, so it's
+        // left untagged, as is linear output for compatibility.
+        match encoding {
+            TransferFunction::Srgb => {
+                // perceptual rendering intent, plus the gAMA the spec recommends
+                // alongside it for older decoders.
+                write_chunk(&mut w, b"sRGB", &[0])?;
+                write_chunk(&mut w, b"gAMA", &45455u32.to_be_bytes())?;
+            }
+            TransferFunction::Gamma(gamma) => {
+                let value = (100000.0 / gamma).round() as u32;
+                write_chunk(&mut w, b"gAMA", &value.to_be_bytes())?;
+            }
+            TransferFunction::Linear | TransferFunction::Rec709 => {}
+        }
+
+        let bytes_per_sample = format.bit_depth() as usize / 8;
+        let bpp = format.channels() * bytes_per_sample;
+        let stride = self.width * bpp;
+        let max = if bytes_per_sample == 1 { 255 } else { 65535 };
+
+        let mut raw = Vec::with_capacity((stride + 1) * self.height);
+        let mut prev = vec![0u8; stride];
+        let mut row = Vec::with_capacity(stride);
+
+        for y in 0..self.height {
+            row.clear();
+            for x in 0..self.width {
+                let rgba = self.rgba(x, y);
+                let color = if format.channels() == 4 {
+                    rgba.to_straight()
+                } else {
+                    rgba.color
+                };
+
+                let c = encoding.encode_color(color).to_int(max);
+                let mut samples = vec![c.r, c.g, c.b];
+                if format.channels() == 4 {
+                    samples.push((rgba.alpha.clamp(0.0, 1.0) * max as f64).round() as u32);
+                }
+
+                for sample in samples {
+                    if bytes_per_sample == 1 {
+                        row.push(sample as u8);
+                    } else {
+                        row.extend_from_slice(&(sample as u16).to_be_bytes());
+                    }
+                }
+            }
+
+            // pick whichever filter leaves the smallest residuals, the usual
+            // heuristic from the PNG spec.
+            let (kind, filtered) = (0..5)
+                .map(|kind| {
+                    let mut filtered = row.clone();
+                    filter_row(kind, &mut filtered, &prev, bpp, true);
+                    (kind, filtered)
+                })
+                .min_by_key(|(_, filtered)| {
+                    filtered
+                        .iter()
+                        .map(|&b| (b as i8).unsigned_abs() as u64)
+                        .sum::<u64>()
+                })
+                .unwrap();
+
+            raw.push(kind);
+            raw.extend_from_slice(&filtered);
+            prev.clone_from(&row);
+        }
+
+        write_chunk(&mut w, b"IDAT", &zlib_compress(&raw))?;
+        write_chunk(&mut w, b"IEND", &[])?;
+
+        w.flush()
+    }
+
+    /// reads a non-interlaced PNG of any color type and bit depth into a
+    /// premultiplied canvas.
+    pub fn from_png<R: Read>(mut reader: R) -> Result<Canvas, PngError> {
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+
+        if data.get(..8) != Some(&SIGNATURE[..]) {
+            return Err(PngError::InvalidSignature);
+        }
+
+        let mut pos = 8;
+        let mut header = None;
+        let mut palette = vec![];
+        let mut transparency = vec![];
+        let mut compressed = vec![];
+
+        loop {
+            let length = data
+                .get(pos..pos + 4)
+                .ok_or(PngError::MissingChunk("IEND"))?;
+            let length = u32::from_be_bytes(length.try_into().unwrap()) as usize;
+            let chunk = data
+                .get(pos + 4..pos + 8 + length)
+                .ok_or(PngError::Truncated("chunk"))?;
+            let crc = data
+                .get(pos + 8 + length..pos + 12 + length)
+                .ok_or(PngError::Truncated("chunk"))?;
+
+            let (kind, body) = chunk.split_at(4);
+            let name = String::from_utf8_lossy(kind).to_string();
+            if crc32(chunk).to_be_bytes() != crc {
+                return Err(PngError::CrcMismatch(name));
+            }
+
+            match kind {
+                b"IHDR" => header = Some(Header::parse(body)?),
+                b"PLTE" => palette = body.to_vec(),
+                b"tRNS" => transparency = body.to_vec(),
+                b"IDAT" => compressed.extend_from_slice(body),
+                b"IEND" => break,
+                // ancillary chunks have a lowercase first letter and are safe
+                // to skip; anything else we can't render correctly.
+                _ if kind[0].is_ascii_lowercase() => {}
+                _ => return Err(PngError::Unsupported(format!("critical chunk {}", name))),
+            }
+
+            pos += 12 + length;
+        }
+
+        let header = header.ok_or(PngError::MissingChunk("IHDR"))?;
+        if compressed.is_empty() {
+            return Err(PngError::MissingChunk("IDAT"));
+        }
+        if header.color_type == 3 && palette.is_empty() {
+            return Err(PngError::MissingChunk("PLTE"));
+        }
+
+        let mut raw = zlib_decompress_limited(&compressed, header.raw_size()?)?;
+        header.decode(&mut raw, &palette, &transparency)
+    }
+}
+
+struct Header {
+    width: usize,
+    height: usize,
+    bit_depth: u8,
+    color_type: u8,
+}
+
+impl Header {
+    fn parse(body: &[u8]) -> Result<Self, PngError> {
+        if body.len() != 13 {
+            return Err(PngError::Truncated("IHDR chunk"));
+        }
+
+        let width = u32::from_be_bytes(body[0..4].try_into().unwrap()) as usize;
+        let height = u32::from_be_bytes(body[4..8].try_into().unwrap()) as usize;
+        let (bit_depth, color_type) = (body[8], body[9]);
+
+        let max_size = 1..=0x7fff_ffff;
+        if !max_size.contains(&width) || !max_size.contains(&height) {
+            return Err(PngError::InvalidHeader(format!(
+                "invalid image size {}x{}",
+                width, height
+            )));
+        }
+
+        let valid_depths: &[u8] = match color_type {
+            0 => &[1, 2, 4, 8, 16],
+            3 => &[1, 2, 4, 8],
+            2 | 4 | 6 => &[8, 16],
+            _ => {
+                return Err(PngError::InvalidHeader(format!(
+                    "unknown color type {}",
+                    color_type
+                )))
+            }
+        };
+        if !valid_depths.contains(&bit_depth) {
+            return Err(PngError::InvalidHeader(format!(
+                "bit depth {} isn't allowed for color type {}",
+                bit_depth, color_type
+            )));
+        }
+        if body[10] != 0 || body[11] != 0 {
+            return Err(PngError::InvalidHeader(
+                "unknown compression or filter method".to_string(),
+            ));
+        }
+        if body[12] != 0 {
+            return Err(PngError::Unsupported("interlaced images".to_string()));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            bit_depth,
+            color_type,
+        })
+    }
+
+    fn channels(&self) -> usize {
+        match self.color_type {
+            0 | 3 => 1,
+            4 => 2,
+            2 => 3,
+            _ => 4,
+        }
+    }
+
+    /// how many bytes the decompressed image data should be: every scanline
+    /// is a filter byte and then its samples.
+    fn raw_size(&self) -> Result<usize, PngError> {
+        let bits_per_pixel = self.channels() * self.bit_depth as usize;
+        self.width
+            .checked_mul(bits_per_pixel)
+            .and_then(|bits| (bits.div_ceil(8) + 1).checked_mul(self.height))
+            .ok_or_else(|| {
+                PngError::InvalidHeader(format!(
+                    "image size {}x{} is too large",
+                    self.width, self.height
+                ))
+            })
+    }
+
+    /// `transparency` is the body of the tRNS chunk, if any: an alpha for each
+    /// palette entry, or the one gray or rgb value that's fully transparent.
+    fn decode(
+        &self,
+        raw: &mut [u8],
+        palette: &[u8],
+        transparency: &[u8],
+    ) -> Result<Canvas, PngError> {
+        if raw.len() < self.raw_size()? {
+            return Err(PngError::Truncated("image data"));
+        }
+
+        let bits_per_pixel = self.channels() * self.bit_depth as usize;
+        let stride = (self.width * bits_per_pixel).div_ceil(8);
+        let bpp = bits_per_pixel.div_ceil(8);
+
+        // gray and rgb images store their transparent color as 16-bit samples,
+        // whatever the bit depth. images with an alpha channel can't have one.
+        let transparent_key: Option<Vec<u32>> = match self.color_type {
+            0 | 2 if !transparency.is_empty() => {
+                let n = self.channels();
+                if transparency.len() < n * 2 {
+                    return Err(PngError::Truncated("tRNS chunk"));
+                }
+                Some(
+                    transparency
+                        .chunks(2)
+                        .take(n)
+                        .map(|b| u16::from_be_bytes([b[0], b[1]]) as u32)
+                        .collect(),
+                )
+            }
+            _ => None,
+        };
+
+        let mut canvas = Canvas::new(self.width, self.height);
+        let mut prev = vec![0u8; stride];
+        let max = ((1u32 << self.bit_depth) - 1) as f64;
+
+        for y in 0..self.height {
+            let line = &mut raw[y * (stride + 1)..(y + 1) * (stride + 1)];
+            let (kind, row) = line.split_first_mut().unwrap();
+            if *kind > 4 {
+                return Err(PngError::InvalidHeader(format!(
+                    "unknown filter type {}",
+                    kind
+                )));
+            }
+            filter_row(*kind, row, &prev, bpp, false);
+
+            let sample = |i: usize| -> u32 {
+                match self.bit_depth {
+                    16 => u16::from_be_bytes([row[i * 2], row[i * 2 + 1]]) as u32,
+                    8 => row[i] as u32,
+                    depth => {
+                        // sub-byte samples are packed from the high bits down.
+                        let bit = i * depth as usize;
+                        let shift = 8 - depth as usize - bit % 8;
+                        (row[bit / 8] >> shift) as u32 & ((1 << depth) - 1)
+                    }
+                }
+            };
+
+            for x in 0..self.width {
+                let i = x * self.channels();
+                let color = match self.color_type {
+                    0 | 4 => {
+                        let v = sample(i) as f64 / max;
+                        Color::new(v, v, v)
+                    }
+                    3 => {
+                        let entry = sample(i) as usize * 3;
+                        let rgb = palette
+                            .get(entry..entry + 3)
+                            .ok_or(PngError::Truncated("PLTE chunk"))?;
+                        Color::new(
+                            rgb[0] as f64 / 255.0,
+                            rgb[1] as f64 / 255.0,
+                            rgb[2] as f64 / 255.0,
+                        )
+                    }
+                    _ => Color::new(
+                        sample(i) as f64 / max,
+                        sample(i + 1) as f64 / max,
+                        sample(i + 2) as f64 / max,
+                    ),
+                };
+                let alpha = match self.color_type {
+                    4 => sample(i + 1) as f64 / max,
+                    6 => sample(i + 3) as f64 / max,
+                    3 => transparency
+                        .get(sample(i) as usize)
+                        .map_or(1.0, |&a| a as f64 / 255.0),
+                    _ => match &transparent_key {
+                        Some(key) if key.iter().enumerate().all(|(c, &k)| sample(i + c) == k) => {
+                            0.0
+                        }
+                        _ => 1.0,
+                    },
+                };
+
+                canvas.write_rgba(x, y, Rgba::from_straight(color, alpha));
+            }
+
+            prev.copy_from_slice(row);
+        }
+
+        Ok(canvas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: usize, height: usize) -> Canvas {
+        let mut c = Canvas::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let color = Color::new(
+                    x as f64 / (width - 1) as f64,
+                    y as f64 / (height - 1) as f64,
+                    ((x * 7 + y * 13) % 256) as f64 / 255.0,
+                );
+                c.write_pixel(x, y, color);
+            }
+        }
+        c
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"IEND"), 0xae426082);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn test_png_roundtrip() {
+        let c = gradient(37, 23);
+
+        for format in [
+            PngFormat::Rgb8,
+            PngFormat::Rgb16,
+            PngFormat::Rgba8,
+            PngFormat::Rgba16,
+        ] {
+            let mut out = vec![];
+            c.write_png(&mut out, format).unwrap();
+            let read = Canvas::from_png(&out[..]).unwrap();
+
+            let max = if format.bit_depth() == 8 { 255 } else { 65535 };
+            assert_eq!(read.width, c.width);
+            assert_eq!(read.height, c.height);
+            for (a, b) in read.pixels.iter().zip(c.pixels.iter()) {
+                let (a, b) = (a.to_int(max), b.to_int(max));
+                assert_eq!((a.r, a.g, a.b), (b.r, b.g, b.b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_png_structure() {
+        let mut out = vec![];
+        Canvas::new(2, 3)
+            .write_png(&mut out, PngFormat::Rgb8)
+            .unwrap();
+
+        assert_eq!(&out[..8], &SIGNATURE);
+        assert_eq!(&out[12..16], b"IHDR");
+        assert_eq!(&out[16..29], &[0, 0, 0, 2, 0, 0, 0, 3, 8, 2, 0, 0, 0]);
+        assert_eq!(
+            &out[out.len() - 12..],
+            &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+    }
+
+    #[test]
+    fn test_png_alpha() {
+        let mut c = Canvas::new_transparent(3, 1);
+        c.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        c.write_rgba(1, 0, Rgba::from_straight(Color::new(0.0, 1.0, 0.0), 0.4));
+
+        let mut out = vec![];
+        c.write_png(&mut out, PngFormat::Rgba8).unwrap();
+        let read = Canvas::from_png(&out[..]).unwrap();
+        assert_eq!(read.alpha, vec![1.0, 0.4, 0.0]);
+        assert_eq!(read[(1, 0)], Color::new(0.0, 0.4, 0.0));
+
+        let mut out = vec![];
+        c.write_png(&mut out, PngFormat::Rgb8).unwrap();
+        let read = Canvas::from_png(&out[..]).unwrap();
+        assert!(read.is_opaque());
+        assert_eq!(read[(1, 0)], Color::new(0.0, 0.4, 0.0));
+    }
+
+    #[test]
+    fn test_write_png_encoded() {
+        let mut c = Canvas::new(1, 1);
+        c.write_pixel(0, 0, Color::new(0.18, 0.5, 1.0));
+
+        let mut out = vec![];
+        c.write_png_encoded(&mut out, PngFormat::Rgb8, TransferFunction::Srgb)
+            .unwrap();
+        assert_eq!(&out[37..41], b"sRGB");
+        assert_eq!(&out[46..54], &[0, 0, 0, 4, b'g', b'A', b'M', b'A']);
+
+        let read = Canvas::from_png(&out[..]).unwrap()[(0, 0)].to_int(255);
+        assert_eq!((read.r, read.g, read.b), (118, 188, 255));
+
+        let mut out = vec![];
+        c.write_png_encoded(&mut out, PngFormat::Rgb8, TransferFunction::Gamma(2.2))
+            .unwrap();
+        assert_eq!(&out[37..45], &[b'g', b'A', b'M', b'A', 0, 0, 0xb1, 0x8f]);
+    }
+
+    #[test]
+    fn test_read_png_grayscale_and_palette() {
+        // a 2x1 1-bit grayscale image: one black, one white pixel, stored with
+        // filter type 0.
+        let mut gray = SIGNATURE.to_vec();
+        write_chunk(&mut gray, b"IHDR", &[0, 0, 0, 2, 0, 0, 0, 1, 1, 0, 0, 0, 0]).unwrap();
+        write_chunk(&mut gray, b"IDAT", &zlib_compress(&[0, 0b0100_0000])).unwrap();
+        write_chunk(&mut gray, b"IEND", &[]).unwrap();
+
+        let c = Canvas::from_png(&gray[..]).unwrap();
+        assert_eq!(c[(0, 0)], Color::new(0.0, 0.0, 0.0));
+        assert_eq!(c[(1, 0)], Color::new(1.0, 1.0, 1.0));
+
+        let mut indexed = SIGNATURE.to_vec();
+        write_chunk(
+            &mut indexed,
+            b"IHDR",
+            &[0, 0, 0, 2, 0, 0, 0, 1, 8, 3, 0, 0, 0],
+        )
+        .unwrap();
+        write_chunk(&mut indexed, b"PLTE", &[255, 0, 0, 0, 0, 255]).unwrap();
+        write_chunk(&mut indexed, b"tEXt", b"Comment\0skipped").unwrap();
+        write_chunk(&mut indexed, b"IDAT", &zlib_compress(&[0, 1, 0])).unwrap();
+        write_chunk(&mut indexed, b"IEND", &[]).unwrap();
+
+        let c = Canvas::from_png(&indexed[..]).unwrap();
+        assert_eq!(c[(0, 0)], Color::new(0.0, 0.0, 1.0));
+        assert_eq!(c[(1, 0)], Color::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_read_png_transparency() {
+        let png = |ihdr: &[u8], chunks: &[(&[u8; 4], &[u8])], raw: &[u8]| {
+            let mut data = SIGNATURE.to_vec();
+            write_chunk(&mut data, b"IHDR", ihdr).unwrap();
+            for (kind, body) in chunks {
+                write_chunk(&mut data, kind, body).unwrap();
+            }
+            write_chunk(&mut data, b"IDAT", &zlib_compress(raw)).unwrap();
+            write_chunk(&mut data, b"IEND", &[]).unwrap();
+            Canvas::from_png(&data[..]).unwrap()
+        };
+
+        // palette entries past the end of tRNS stay opaque.
+        let c = png(
+            &[0, 0, 0, 2, 0, 0, 0, 1, 8, 3, 0, 0, 0],
+            &[(b"PLTE", &[255, 0, 0, 0, 0, 255]), (b"tRNS", &[51])],
+            &[0, 0, 1],
+        );
+        assert_eq!(
+            c.rgba(0, 0),
+            Rgba::from_straight(Color::new(1.0, 0.0, 0.0), 0.2)
+        );
+        assert_eq!(c.rgba(1, 0), Rgba::opaque(Color::new(0.0, 0.0, 1.0)));
+
+        let c = png(
+            &[0, 0, 0, 2, 0, 0, 0, 1, 8, 0, 0, 0, 0],
+            &[(b"tRNS", &[0, 7])],
+            &[0, 7, 8],
+        );
+        assert_eq!(c.alpha, vec![0.0, 1.0]);
+
+        let c = png(
+            &[0, 0, 0, 2, 0, 0, 0, 1, 8, 2, 0, 0, 0],
+            &[(b"tRNS", &[0, 1, 0, 2, 0, 3])],
+            &[0, 1, 2, 3, 1, 2, 4],
+        );
+        assert_eq!(c.alpha, vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_read_png_errors() {
+        assert!(matches!(
+            Canvas::from_png(&b"GIF89a"[..]),
+            Err(PngError::InvalidSignature)
+        ));
+
+        let mut out = vec![];
+        Canvas::new(2, 2)
+            .write_png(&mut out, PngFormat::Rgb8)
+            .unwrap();
+        out[20] ^= 1;
+        assert!(matches!(
+            Canvas::from_png(&out[..]),
+            Err(PngError::CrcMismatch(chunk)) if chunk == "IHDR"
+        ));
+
+        let mut interlaced = SIGNATURE.to_vec();
+        write_chunk(
+            &mut interlaced,
+            b"IHDR",
+            &[0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 0, 1],
+        )
+        .unwrap();
+        assert!(matches!(
+            Canvas::from_png(&interlaced[..]),
+            Err(PngError::Unsupported(_))
+        ));
+
+        let png = |ihdr: &[u8], raw: &[u8]| {
+            let mut data = SIGNATURE.to_vec();
+            write_chunk(&mut data, b"IHDR", ihdr).unwrap();
+            write_chunk(&mut data, b"IDAT", &zlib_compress(raw)).unwrap();
+            write_chunk(&mut data, b"IEND", &[]).unwrap();
+            Canvas::from_png(&data[..])
+        };
+        for size in [[0, 0, 0, 0], [0x80, 0, 0, 0]] {
+            let ihdr = [&size[..], &[0, 0, 0, 1, 8, 0, 0, 0, 0]].concat();
+            assert!(matches!(png(&ihdr, &[0]), Err(PngError::InvalidHeader(_))));
+        }
+        assert!(matches!(
+            png(
+                &[0x7f, 0xff, 0xff, 0xff, 0x7f, 0xff, 0xff, 0xff, 16, 6, 0, 0, 0],
+                &[0]
+            ),
+            Err(PngError::InvalidHeader(_))
+        ));
+
+        // a 1x1 gray image is two bytes, however well the rest compresses.
+        assert!(matches!(
+            png(&[0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0], &[0; 100000]),
+            Err(PngError::Deflate(DeflateError::TooLong))
+        ));
+    }
+}
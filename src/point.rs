@@ -0,0 +1,165 @@
+use crate::approx::ApproxEq;
+use crate::tuple::Tuple;
+use crate::vector::Vector3;
+use float_eq::derive_float_eq;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+/// a position in 3D space. unlike a `Tuple` with `w == 1.0`, the type system
+/// only allows the operations that make sense for points: you can move a point
+/// by a vector, or subtract two points to get the vector between them.
+#[derive_float_eq(
+    ulps_tol = "Point3Ulps",
+    ulps_tol_derive = "Clone, Copy, Debug, PartialEq",
+    debug_ulps_diff = "Point3DebugUlpsDiff",
+    debug_ulps_diff_derive = "Clone, Copy, Debug, PartialEq",
+    all_tol = "f64"
+)]
+#[derive(Clone, Copy, Debug)]
+pub struct Point3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Point3 {
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn origin() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+}
+
+impl ApproxEq for Point3 {
+    type Scalar = f64;
+
+    fn all_components<F>(&self, other: &Self, f: F) -> bool
+    where
+        F: Fn(&f64, &f64) -> bool,
+    {
+        f(&self.x, &other.x) && f(&self.y, &other.y) && f(&self.z, &other.z)
+    }
+}
+
+impl PartialEq for Point3 {
+    fn eq(&self, other: &Self) -> bool {
+        self.approx_eq(other)
+    }
+}
+
+impl Eq for Point3 {}
+
+impl Add<Vector3> for Point3 {
+    type Output = Self;
+
+    fn add(self, other: Vector3) -> Self {
+        Self {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+impl AddAssign<Vector3> for Point3 {
+    fn add_assign(&mut self, other: Vector3) {
+        *self = *self + other;
+    }
+}
+
+impl Sub<Vector3> for Point3 {
+    type Output = Self;
+
+    fn sub(self, other: Vector3) -> Self {
+        Self {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+    }
+}
+
+impl SubAssign<Vector3> for Point3 {
+    fn sub_assign(&mut self, other: Vector3) {
+        *self = *self - other;
+    }
+}
+
+impl Sub<Point3> for Point3 {
+    type Output = Vector3;
+
+    fn sub(self, other: Self) -> Vector3 {
+        Vector3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl From<Point3> for Tuple {
+    fn from(p: Point3) -> Self {
+        Tuple::point(p.x, p.y, p.z)
+    }
+}
+
+impl TryFrom<Tuple> for Point3 {
+    type Error = Tuple;
+
+    /// fails with the original tuple if it isn't a point (`w != 1.0`).
+    fn try_from(t: Tuple) -> Result<Self, Self::Error> {
+        if t.is_point() {
+            Ok(Self::new(t.x, t.y, t.z))
+        } else {
+            Err(t)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_point() {
+        let p = Point3::new(4.0, -4.0, 3.0);
+
+        assert_eq!(p.x, 4.0);
+        assert_eq!(p.y, -4.0);
+        assert_eq!(p.z, 3.0);
+    }
+
+    #[test]
+    fn test_sub_points() {
+        let a = Point3::new(3.0, 2.0, 1.0);
+        let b = Point3::new(5.0, 6.0, 7.0);
+
+        assert_eq!(a - b, Vector3::new(-2.0, -4.0, -6.0));
+    }
+
+    #[test]
+    fn test_add_vector_to_point() {
+        let p = Point3::new(3.0, -2.0, 5.0);
+        let v = Vector3::new(-2.0, 3.0, 1.0);
+
+        assert_eq!(p + v, Point3::new(1.0, 1.0, 6.0));
+    }
+
+    #[test]
+    fn test_sub_vector_from_point() {
+        let p = Point3::new(3.0, 2.0, 1.0);
+        let v = Vector3::new(5.0, 6.0, 7.0);
+
+        assert_eq!(p - v, Point3::new(-2.0, -4.0, -6.0));
+    }
+
+    #[test]
+    fn test_point_tuple_conversion() {
+        let p = Point3::new(4.0, -4.0, 3.0);
+        let t: Tuple = p.into();
+
+        assert_eq!(t, Tuple::point(4.0, -4.0, 3.0));
+        assert_eq!(Point3::try_from(t), Ok(p));
+        assert_eq!(
+            Point3::try_from(Tuple::vector(4.0, -4.0, 3.0)),
+            Err(Tuple::vector(4.0, -4.0, 3.0))
+        );
+    }
+}
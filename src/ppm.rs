@@ -0,0 +1,553 @@
+use crate::{canvas::Canvas, color::Color, transfer::TransferFunction};
+use std::{
+    fmt,
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::Path,
+    str::FromStr,
+};
+
+// the netpbm spec doesn't allow lines longer than this in plain files.
+const MAX_LINE_LENGTH: usize = 70;
+
+#[derive(Debug)]
+pub enum PpmError {
+    Io(std::io::Error),
+    /// the file didn't start with one of P2, P3, P5, P6, PF or Pf.
+    UnknownFormat(String),
+    /// the data ended while we were still reading the named field.
+    UnexpectedEof(&'static str),
+    InvalidNumber {
+        field: &'static str,
+        found: String,
+    },
+    InvalidMaxval(u32),
+    /// the width and height multiply out to more samples than can be addressed.
+    TooLarge {
+        width: usize,
+        height: usize,
+    },
+    SampleOutOfRange {
+        sample: u32,
+        maxval: u32,
+    },
+}
+
+impl fmt::Display for PpmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PpmError::Io(e) => write!(f, "error reading image: {}", e),
+            PpmError::UnknownFormat(magic) => {
+                write!(f, "unknown image format with magic number {:?}", magic)
+            }
+            PpmError::UnexpectedEof(field) => {
+                write!(f, "unexpected end of data while reading {}", field)
+            }
+            PpmError::InvalidNumber { field, found } => {
+                write!(f, "invalid {}: expected a number, found {:?}", field, found)
+            }
+            PpmError::InvalidMaxval(maxval) => {
+                write!(f, "maxval must be between 1 and 65535, found {}", maxval)
+            }
+            PpmError::TooLarge { width, height } => {
+                write!(f, "image size {}x{} is too large", width, height)
+            }
+            PpmError::SampleOutOfRange { sample, maxval } => {
+                write!(f, "sample {} is larger than maxval {}", sample, maxval)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PpmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PpmError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PpmError {
+    fn from(e: std::io::Error) -> Self {
+        PpmError::Io(e)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PpmFormat {
+    /// P3: whitespace-separated ASCII samples.
+    Plain,
+    /// P6: one byte per sample.
+    Raw,
+}
+
+// walks the header (and plain rasters) token by token, skipping whitespace and
+// `#` comments wherever they show up.
+struct Tokens<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn skip_whitespace(&mut self) {
+        while let Some(&b) = self.data.get(self.pos) {
+            if b == b'#' {
+                while self
+                    .data
+                    .get(self.pos)
+                    .is_some_and(|&b| b != b'\n' && b != b'\r')
+                {
+                    self.pos += 1;
+                }
+            } else if b.is_ascii_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next(&mut self, field: &'static str) -> Result<&'a str, PpmError> {
+        self.skip_whitespace();
+
+        let start = self.pos;
+        while self
+            .data
+            .get(self.pos)
+            .is_some_and(|&b| !b.is_ascii_whitespace() && b != b'#')
+        {
+            self.pos += 1;
+        }
+
+        if start == self.pos {
+            return Err(PpmError::UnexpectedEof(field));
+        }
+
+        Ok(std::str::from_utf8(&self.data[start..self.pos]).unwrap_or("<non-utf8>"))
+    }
+
+    fn number<T: FromStr>(&mut self, field: &'static str) -> Result<T, PpmError> {
+        let token = self.next(field)?;
+        token.parse().map_err(|_| PpmError::InvalidNumber {
+            field,
+            found: token.to_string(),
+        })
+    }
+
+    // binary formats separate the header from the raster with exactly one
+    // whitespace byte.
+    fn raster(self) -> Result<&'a [u8], PpmError> {
+        match self.data.get(self.pos) {
+            Some(b) if b.is_ascii_whitespace() => Ok(&self.data[self.pos + 1..]),
+            _ => Err(PpmError::UnexpectedEof("raster")),
+        }
+    }
+}
+
+impl Canvas {
+    /// reads a netpbm image: plain or raw PPM (P3/P6) and PGM (P2/P5) with any
+    /// maxval, or a colour or greyscale PFM (PF/Pf) float image.
+    pub fn from_ppm<R: Read>(mut reader: R) -> Result<Canvas, PpmError> {
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+
+        let mut tokens = Tokens {
+            data: &data,
+            pos: 0,
+        };
+
+        let magic = tokens.next("magic number")?;
+        let width: usize = tokens.number("width")?;
+        let height: usize = tokens.number("height")?;
+
+        let channels = match magic {
+            "P2" | "P5" | "Pf" => 1,
+            "P3" | "P6" | "PF" => 3,
+            _ => return Err(PpmError::UnknownFormat(magic.to_string())),
+        };
+
+        // the raster is read and length checked before the canvas is allocated,
+        // so a bogus header can't ask for more memory than the file backs up.
+        let count = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(channels))
+            .ok_or(PpmError::TooLarge { width, height })?;
+
+        if magic == "PF" || magic == "Pf" {
+            let scale: f32 = tokens.number("scale")?;
+            let samples = read_pfm_samples(tokens.raster()?, count, scale < 0.0)?;
+
+            // PFM stores rows bottom to top.
+            let mut canvas = Canvas::new(width, height);
+            for (i, pixel) in samples.chunks(channels).enumerate() {
+                let (x, y) = (i % width, height - 1 - i / width);
+                canvas[(x, y)] = sample_color(pixel);
+            }
+
+            return Ok(canvas);
+        }
+
+        let maxval: u32 = tokens.number("maxval")?;
+        if maxval == 0 || maxval > 65535 {
+            return Err(PpmError::InvalidMaxval(maxval));
+        }
+
+        let samples = match magic {
+            "P2" | "P3" => read_plain_samples(&mut tokens, count)?,
+            _ => read_raw_samples(tokens.raster()?, count, maxval)?,
+        };
+
+        if let Some(&sample) = samples.iter().find(|&&s| s > maxval) {
+            return Err(PpmError::SampleOutOfRange { sample, maxval });
+        }
+
+        let samples: Vec<f64> = samples.iter().map(|&s| s as f64 / maxval as f64).collect();
+        let mut canvas = Canvas::new(width, height);
+        for (pixel, samples) in canvas.pixels.iter_mut().zip(samples.chunks(channels)) {
+            *pixel = sample_color(samples);
+        }
+
+        Ok(canvas)
+    }
+
+    pub fn write_to_ppm(&self, path: &Path) -> std::io::Result<()> {
+        self.write_ppm(File::create(path)?, PpmFormat::Plain)
+    }
+
+    /// writes the canvas to any writer, e.g. a file, stdout or a socket.
+    /// output is buffered, so there's no need to wrap `writer` yourself.
+    pub fn write_ppm<W: Write>(&self, writer: W, format: PpmFormat) -> std::io::Result<()> {
+        self.write_ppm_encoded(writer, format, TransferFunction::Linear)
+    }
+
+    /// like `write_ppm`, but passes every pixel through `encoding` before
+    /// quantizing. the netpbm spec itself expects rec.709 or srgb. samples are
+    /// rounded; write `dithered(255, encoding, ..)` to dither them.
+    pub fn write_ppm_encoded<W: Write>(
+        &self,
+        writer: W,
+        format: PpmFormat,
+        encoding: TransferFunction,
+    ) -> std::io::Result<()> {
+        let mut w = BufWriter::new(writer);
+
+        match format {
+            PpmFormat::Plain => self.write_p3(&mut w, encoding)?,
+            PpmFormat::Raw => self.write_p6(&mut w, encoding)?,
+        }
+
+        w.flush()
+    }
+
+    fn write_p3<W: Write>(&self, w: &mut W, encoding: TransferFunction) -> std::io::Result<()> {
+        write!(w, "P3\n{} {}\n255\n", self.width, self.height)?;
+
+        let mut line = String::with_capacity(MAX_LINE_LENGTH);
+
+        for row in self.pixels.chunks(self.width.max(1)) {
+            for pixel in row {
+                let pixel_int = encoding.encode_color(*pixel).to_int(255);
+
+                for sample in [pixel_int.r, pixel_int.g, pixel_int.b] {
+                    let sample = sample.to_string();
+
+                    if !line.is_empty() && line.len() + 1 + sample.len() > MAX_LINE_LENGTH {
+                        writeln!(w, "{}", line)?;
+                        line.clear();
+                    }
+
+                    if !line.is_empty() {
+                        line.push(' ');
+                    }
+                    line.push_str(&sample);
+                }
+            }
+
+            // every row starts on a new line.
+            writeln!(w, "{}", line)?;
+            line.clear();
+        }
+
+        Ok(())
+    }
+
+    fn write_p6<W: Write>(&self, w: &mut W, encoding: TransferFunction) -> std::io::Result<()> {
+        write!(w, "P6\n{} {}\n255\n", self.width, self.height)?;
+
+        for pixel in self.pixels.iter() {
+            let pixel_int = encoding.encode_color(*pixel).to_int(255);
+            w.write_all(&[pixel_int.r as u8, pixel_int.g as u8, pixel_int.b as u8])?;
+        }
+
+        Ok(())
+    }
+}
+
+// greyscale images have a single sample per pixel.
+fn sample_color<T: Copy + Into<f64>>(samples: &[T]) -> Color {
+    match *samples {
+        [v] => Color::new(v.into(), v.into(), v.into()),
+        [r, g, b] => Color::new(r.into(), g.into(), b.into()),
+        _ => unreachable!("pixels have either 1 or 3 samples"),
+    }
+}
+
+fn read_plain_samples(tokens: &mut Tokens, count: usize) -> Result<Vec<u32>, PpmError> {
+    (0..count).map(|_| tokens.number("sample")).collect()
+}
+
+fn read_raw_samples(raster: &[u8], count: usize, maxval: u32) -> Result<Vec<u32>, PpmError> {
+    let bytes_per_sample = if maxval < 256 { 1 } else { 2 };
+
+    if raster.len() < count.saturating_mul(bytes_per_sample) {
+        return Err(PpmError::UnexpectedEof("raster"));
+    }
+
+    Ok(raster
+        .chunks_exact(bytes_per_sample)
+        .take(count)
+        .map(|b| match *b {
+            [v] => v as u32,
+            [hi, lo] => u16::from_be_bytes([hi, lo]) as u32,
+            _ => unreachable!(),
+        })
+        .collect())
+}
+
+fn read_pfm_samples(
+    raster: &[u8],
+    count: usize,
+    little_endian: bool,
+) -> Result<Vec<f32>, PpmError> {
+    if raster.len() < count.saturating_mul(4) {
+        return Err(PpmError::UnexpectedEof("raster"));
+    }
+
+    Ok(raster
+        .chunks_exact(4)
+        .take(count)
+        .map(|b| {
+            let bytes = [b[0], b[1], b[2], b[3]];
+            if little_endian {
+                f32::from_le_bytes(bytes)
+            } else {
+                f32::from_be_bytes(bytes)
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use std::{fs, io::BufReader};
+
+    #[test]
+    fn test_write_empty_ppm() {
+        let c = Canvas::new(5, 3);
+        c.write_to_ppm(Path::new("test_write_empty_ppm.ppm"))
+            .unwrap();
+
+        let file = File::open("test_write_empty_ppm.ppm").unwrap();
+        let mut buf_reader = BufReader::new(file);
+        let mut content = String::new();
+        buf_reader.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "P3\n5 3\n255\n0 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n0 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n0 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n");
+
+        fs::remove_file("test_write_empty_ppm.ppm").unwrap();
+    }
+
+    #[test]
+    fn test_write_ppm() {
+        let mut c = Canvas::new(5, 3);
+        let c1 = Color::new(1.5, 0.0, 0.0);
+        let c2 = Color::new(0.0, 0.5, 0.0);
+        let c3 = Color::new(-0.5, 0.0, 1.0);
+
+        c.write_pixel(0, 0, c1);
+        c.write_pixel(1, 0, Color::new(0.0, 0.5, 0.5));
+        c.write_pixel(2, 1, c2);
+        c.write_pixel(4, 2, c3);
+
+        c.write_to_ppm(Path::new("test_write_ppm.ppm")).unwrap();
+
+        let file = File::open("test_write_ppm.ppm").unwrap();
+        let mut buf_reader = BufReader::new(file);
+        let mut content = String::new();
+        buf_reader.read_to_string(&mut content).unwrap();
+
+        assert_eq!(content, "P3\n5 3\n255\n255 0 0 0 128 128 0 0 0 0 0 0 0 0 0\n0 0 0 0 0 0 0 128 0 0 0 0 0 0 0\n0 0 0 0 0 0 0 0 0 0 0 0 0 0 255\n");
+
+        fs::remove_file("test_write_ppm.ppm").unwrap();
+    }
+
+    #[test]
+    fn test_write_ppm_wraps_long_lines() {
+        let mut c = Canvas::new(10, 2);
+        c.pixels = vec![Color::new(1.0, 1.0, 1.0); 20];
+
+        let mut out = vec![];
+        c.write_ppm(&mut out, PpmFormat::Plain).unwrap();
+        let content = String::from_utf8(out).unwrap();
+
+        assert_eq!(
+            content,
+            "P3\n10 2\n255\n\
+             255 255 255 255 255 255 255 255 255 255 255 255 255 255 255 255 255\n\
+             255 255 255 255 255 255 255 255 255 255 255 255 255\n\
+             255 255 255 255 255 255 255 255 255 255 255 255 255 255 255 255 255\n\
+             255 255 255 255 255 255 255 255 255 255 255 255 255\n"
+        );
+        assert!(content.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+    }
+
+    #[test]
+    fn test_write_ppm_raw() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(1.0, 0.0, 0.5));
+        c.write_pixel(1, 0, Color::new(0.0, 1.0, 0.0));
+
+        let mut out = vec![];
+        c.write_ppm(&mut out, PpmFormat::Raw).unwrap();
+
+        let mut expected = b"P6\n2 1\n255\n".to_vec();
+        expected.extend_from_slice(&[255, 0, 128, 0, 255, 0]);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_write_ppm_encoded() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(0.18, 0.5, 1.0));
+
+        let mut out = vec![];
+        c.write_ppm_encoded(&mut out, PpmFormat::Plain, TransferFunction::Srgb)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "P3\n2 1\n255\n118 188 255 0 0 0\n"
+        );
+    }
+
+    fn read(data: &[u8]) -> Result<Canvas, PpmError> {
+        Canvas::from_ppm(data)
+    }
+
+    #[test]
+    fn test_read_ppm_roundtrip() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        c.write_pixel(1, 0, Color::new(0.0, 1.0, 0.0));
+        c.write_pixel(2, 1, Color::new(0.0, 0.0, 1.0));
+
+        for format in [PpmFormat::Plain, PpmFormat::Raw] {
+            let mut out = vec![];
+            c.write_ppm(&mut out, format).unwrap();
+
+            assert_eq!(read(&out).unwrap(), c);
+        }
+    }
+
+    #[test]
+    fn test_read_ppm_comments_and_whitespace() {
+        let data = b"P3 # a comment\r\n# another one\n 2\t1\n\n4 # maxval\n 4 0 0\n\n0 2\n 4\n";
+        let c = read(data).unwrap();
+
+        assert_eq!(c.width, 2);
+        assert_eq!(c.height, 1);
+        assert_eq!(c[(0, 0)], Color::new(1.0, 0.0, 0.0));
+        assert_eq!(c[(1, 0)], Color::new(0.0, 0.5, 1.0));
+    }
+
+    #[test]
+    fn test_read_ppm_raw_16_bit() {
+        let mut data = b"P6\n1 1\n65535\n".to_vec();
+        data.extend_from_slice(&[0xff, 0xff, 0x80, 0x00, 0x00, 0x00]);
+        let c = read(&data).unwrap();
+
+        assert_eq!(c[(0, 0)], Color::new(1.0, 32768.0 / 65535.0, 0.0));
+    }
+
+    #[test]
+    fn test_read_pgm() {
+        let plain = read(b"P2\n2 1\n10\n0 5\n").unwrap();
+        let raw = read(b"P5\n2 1\n10\n\x00\x05").unwrap();
+
+        for c in [plain, raw] {
+            assert_eq!(c[(0, 0)], Color::new(0.0, 0.0, 0.0));
+            assert_eq!(c[(1, 0)], Color::new(0.5, 0.5, 0.5));
+        }
+    }
+
+    #[test]
+    fn test_read_pfm() {
+        // 1x2, little-endian, bottom row first.
+        let mut data = b"PF\n1 2\n-1.0\n".to_vec();
+        for v in [0.0_f32, 0.0, 4.0, 0.5, 0.25, 0.125] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        let c = read(&data).unwrap();
+
+        assert_eq!(c[(0, 0)], Color::new(0.5, 0.25, 0.125));
+        assert_eq!(c[(0, 1)], Color::new(0.0, 0.0, 4.0));
+
+        let mut data = b"Pf\n1 1\n1.0\n".to_vec();
+        data.extend_from_slice(&2.0_f32.to_be_bytes());
+
+        assert_eq!(read(&data).unwrap()[(0, 0)], Color::new(2.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn test_read_ppm_errors() {
+        assert!(matches!(read(b"P7\n1 1\n255\n"), Err(PpmError::UnknownFormat(m)) if m == "P7"));
+        assert!(matches!(
+            read(b"P3\n1 x\n255\n"),
+            Err(PpmError::InvalidNumber {
+                field: "height",
+                ..
+            })
+        ));
+        assert!(matches!(
+            read(b"P3\n1 1\n0\n"),
+            Err(PpmError::InvalidMaxval(0))
+        ));
+        assert!(matches!(
+            read(b"P3\n1 1\n255\n0 0\n"),
+            Err(PpmError::UnexpectedEof("sample"))
+        ));
+        assert!(matches!(
+            read(b"P3\n1 1\n255\n0 0 256\n"),
+            Err(PpmError::SampleOutOfRange {
+                sample: 256,
+                maxval: 255
+            })
+        ));
+        assert!(matches!(
+            read(b"P6\n2 1\n255\n\x00\x00\x00"),
+            Err(PpmError::UnexpectedEof("raster"))
+        ));
+        assert!(matches!(
+            read(format!("P6\n{0} {0}\n255\n\x00", usize::MAX).as_bytes()),
+            Err(PpmError::TooLarge { .. })
+        ));
+        assert!(matches!(
+            read(b"P6\n100000 100000\n255\n\x00\x00\x00"),
+            Err(PpmError::UnexpectedEof("raster"))
+        ));
+        assert!(matches!(
+            read(b"PF\n100000 100000\n-1.0\n\x00\x00\x00\x00"),
+            Err(PpmError::UnexpectedEof("raster"))
+        ));
+        assert!(matches!(
+            read(b"P3\n100000 100000\n255\n0 0 0\n"),
+            Err(PpmError::UnexpectedEof("sample"))
+        ));
+        assert_eq!(
+            read(b"P3\n1 x\n255\n").unwrap_err().to_string(),
+            "invalid height: expected a number, found \"x\""
+        );
+    }
+}
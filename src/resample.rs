@@ -0,0 +1,379 @@
+//! cropping, resizing, flipping, rotating and tiling canvases, for thumbnails
+//! and contact sheets.
+//!
+//! filtering works on the premultiplied pixels, so transparent neighbours don't
+//! bleed their (black) color into the edges of whatever they surround.
+
+use crate::{canvas::Canvas, color::Color};
+use std::f64::consts::PI;
+
+/// how `resize` reconstructs the image between pixel centres.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    /// the closest source pixel. blocky, but never invents new colors.
+    Nearest,
+    /// a tent over the two nearest pixels on each axis.
+    Bilinear,
+    /// catmull–rom over the four nearest. sharper, with slight ringing.
+    Bicubic,
+    /// a windowed sinc over the six nearest. the sharpest, with more ringing.
+    Lanczos3,
+}
+
+impl Filter {
+    fn radius(self) -> f64 {
+        match self {
+            Filter::Nearest => 0.5,
+            Filter::Bilinear => 1.0,
+            Filter::Bicubic => 2.0,
+            Filter::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(self, x: f64) -> f64 {
+        let x = x.abs();
+        match self {
+            Filter::Nearest => (x < 0.5) as u8 as f64,
+            Filter::Bilinear => (1.0 - x).max(0.0),
+            Filter::Bicubic => {
+                if x < 1.0 {
+                    1.5 * x * x * x - 2.5 * x * x + 1.0
+                } else if x < 2.0 {
+                    -0.5 * x * x * x + 2.5 * x * x - 4.0 * x + 2.0
+                } else {
+                    0.0
+                }
+            }
+            Filter::Lanczos3 => {
+                if x == 0.0 {
+                    1.0
+                } else if x < 3.0 {
+                    let px = PI * x;
+                    3.0 * px.sin() * (px / 3.0).sin() / (px * px)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// the source pixels and normalized weights that make up each destination
+/// pixel along one axis. taps past the edge are clamped onto it.
+fn taps(src: usize, dst: usize, filter: Filter) -> Vec<Vec<(usize, f64)>> {
+    let scale = dst as f64 / src as f64;
+    let last = src as i64 - 1;
+
+    (0..dst)
+        .map(|i| {
+            let centre = (i as f64 + 0.5) / scale - 0.5;
+
+            if filter == Filter::Nearest {
+                let j = ((i as f64 + 0.5) / scale).floor() as i64;
+                return vec![(j.clamp(0, last) as usize, 1.0)];
+            }
+
+            // when shrinking, widen the filter so every source pixel counts.
+            let stretch = (1.0 / scale).max(1.0);
+            let support = filter.radius() * stretch;
+            let lo = (centre - support).ceil() as i64;
+            let hi = (centre + support).floor() as i64;
+
+            let mut taps: Vec<(usize, f64)> = (lo..=hi)
+                .map(|j| {
+                    let w = filter.weight((j as f64 - centre) / stretch);
+                    (j.clamp(0, last) as usize, w)
+                })
+                .filter(|&(_, w)| w != 0.0)
+                .collect();
+
+            let total: f64 = taps.iter().map(|&(_, w)| w).sum();
+            for (_, w) in taps.iter_mut() {
+                *w /= total;
+            }
+            taps
+        })
+        .collect()
+}
+
+impl Canvas {
+    /// the `width` by `height` region with its top left corner at `(x, y)`.
+    /// panics if it doesn't fit on the canvas.
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Canvas {
+        assert!(
+            x + width <= self.width && y + height <= self.height,
+            "can't crop {}x{} at ({}, {}) from a {}x{} canvas",
+            width,
+            height,
+            x,
+            y,
+            self.width,
+            self.height
+        );
+
+        let mut out = Canvas::new(width, height);
+        for j in 0..height {
+            for i in 0..width {
+                out.write_rgba(i, j, self.rgba(x + i, y + j));
+            }
+        }
+        out
+    }
+
+    /// scales the canvas to `width` by `height`, filtering each axis in turn.
+    /// the alpha plane is kept within `[0, 1]`, and opaque canvases stay
+    /// exactly opaque, but colors are left alone so hdr values survive.
+    pub fn resize(&self, width: usize, height: usize, filter: Filter) -> Canvas {
+        if width == 0 || height == 0 || self.width == 0 || self.height == 0 {
+            return Canvas::new_transparent(width, height);
+        }
+
+        let columns = taps(self.width, width, filter);
+        let mut wide = Canvas::new(width, self.height);
+        for y in 0..self.height {
+            for (x, taps) in columns.iter().enumerate() {
+                let (color, alpha) = self.weighted(taps.iter().map(|&(i, w)| (i, y, w)));
+                wide[(x, y)] = color;
+                wide.alpha[x + y * width] = alpha;
+            }
+        }
+
+        let opaque = self.is_opaque();
+        let rows = taps(self.height, height, filter);
+        let mut out = Canvas::new(width, height);
+        for (y, taps) in rows.iter().enumerate() {
+            for x in 0..width {
+                let (color, alpha) = wide.weighted(taps.iter().map(|&(j, w)| (x, j, w)));
+                out[(x, y)] = color;
+                out.alpha[x + y * width] = if opaque { 1.0 } else { alpha.clamp(0.0, 1.0) };
+            }
+        }
+        out
+    }
+
+    fn weighted(&self, taps: impl Iterator<Item = (usize, usize, f64)>) -> (Color, f64) {
+        taps.fold(
+            (Color::new(0.0, 0.0, 0.0), 0.0),
+            |(color, alpha), (x, y, w)| {
+                let p = self.rgba(x, y);
+                (color + p.color * w, alpha + p.alpha * w)
+            },
+        )
+    }
+
+    /// mirrors the canvas left to right.
+    pub fn flip_h(&self) -> Canvas {
+        self.remap(self.width, self.height, |x, y| (self.width - 1 - x, y))
+    }
+
+    /// mirrors the canvas top to bottom.
+    pub fn flip_v(&self) -> Canvas {
+        self.remap(self.width, self.height, |x, y| (x, self.height - 1 - y))
+    }
+
+    /// a quarter turn clockwise.
+    pub fn rotate_90(&self) -> Canvas {
+        self.remap(self.height, self.width, |x, y| (y, self.height - 1 - x))
+    }
+
+    /// a half turn.
+    pub fn rotate_180(&self) -> Canvas {
+        self.remap(self.width, self.height, |x, y| {
+            (self.width - 1 - x, self.height - 1 - y)
+        })
+    }
+
+    /// a quarter turn anticlockwise.
+    pub fn rotate_270(&self) -> Canvas {
+        self.remap(self.height, self.width, |x, y| (self.width - 1 - y, x))
+    }
+
+    /// builds a `width` by `height` canvas where each pixel is copied from the
+    /// source pixel `f` returns for it.
+    fn remap<F>(&self, width: usize, height: usize, f: F) -> Canvas
+    where
+        F: Fn(usize, usize) -> (usize, usize),
+    {
+        let mut out = Canvas::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = f(x, y);
+                out.write_rgba(x, y, self.rgba(sx, sy));
+            }
+        }
+        out
+    }
+
+    /// copies `src` onto this canvas with its top left corner at `(x, y)`,
+    /// replacing what was there. anything that falls off the edge is skipped.
+    /// use `over` instead to composite.
+    pub fn blit(&mut self, src: &Canvas, x: i64, y: i64) {
+        for j in 0..src.height {
+            for i in 0..src.width {
+                let (dx, dy) = (x + i as i64, y + j as i64);
+                if dx >= 0 && dy >= 0 && (dx as usize) < self.width && (dy as usize) < self.height {
+                    self.write_rgba(dx as usize, dy as usize, src.rgba(i, j));
+                }
+            }
+        }
+    }
+
+    /// lays `images` out left to right, top to bottom, `columns` to a row, in
+    /// cells as big as the largest one. cells are separated by `gap` pixels of
+    /// transparency.
+    pub fn tile(images: &[Canvas], columns: usize, gap: usize) -> Canvas {
+        let columns = columns.clamp(1, images.len().max(1));
+        let rows = images.len().div_ceil(columns);
+        let cell_w = images.iter().map(|c| c.width).max().unwrap_or(0);
+        let cell_h = images.iter().map(|c| c.height).max().unwrap_or(0);
+
+        let mut out = Canvas::new_transparent(
+            (columns * (cell_w + gap)).saturating_sub(gap),
+            (rows * (cell_h + gap)).saturating_sub(gap),
+        );
+        for (n, image) in images.iter().enumerate() {
+            let x = (n % columns) * (cell_w + gap);
+            let y = (n / columns) * (cell_h + gap);
+            out.blit(image, x as i64, y as i64);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    /// a canvas whose red channel numbers the pixels in reading order.
+    fn numbered(width: usize, height: usize) -> Canvas {
+        let mut c = Canvas::new(width, height);
+        for (n, p) in c.pixels.iter_mut().enumerate() {
+            *p = Color::new(n as f64, 0.0, 0.0);
+        }
+        c
+    }
+
+    fn reds(c: &Canvas) -> Vec<f64> {
+        c.pixels.iter().map(|p| p.r).collect()
+    }
+
+    #[test]
+    fn test_crop() {
+        let c = numbered(4, 3).crop(1, 1, 2, 2);
+
+        assert_eq!((c.width, c.height), (2, 2));
+        assert_eq!(reds(&c), vec![5.0, 6.0, 9.0, 10.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_crop_out_of_bounds() {
+        numbered(4, 3).crop(3, 0, 2, 1);
+    }
+
+    #[test]
+    fn test_flip() {
+        let c = numbered(3, 2);
+
+        assert_eq!(reds(&c.flip_h()), vec![2.0, 1.0, 0.0, 5.0, 4.0, 3.0]);
+        assert_eq!(reds(&c.flip_v()), vec![3.0, 4.0, 5.0, 0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_rotate() {
+        let c = numbered(3, 2);
+        let r = c.rotate_90();
+
+        // 0 1 2      3 0
+        // 3 4 5  ->  4 1
+        //            5 2
+        assert_eq!((r.width, r.height), (2, 3));
+        assert_eq!(reds(&r), vec![3.0, 0.0, 4.0, 1.0, 5.0, 2.0]);
+        assert_eq!(reds(&c.rotate_270()), vec![2.0, 5.0, 1.0, 4.0, 0.0, 3.0]);
+        assert_eq!(c.rotate_180(), c.flip_h().flip_v());
+        assert_eq!(r.rotate_90().rotate_90().rotate_90(), c);
+        assert_eq!(c.rotate_90().rotate_270(), c);
+    }
+
+    #[test]
+    fn test_resize_same_size() {
+        let c = numbered(4, 3);
+        for filter in [
+            Filter::Nearest,
+            Filter::Bilinear,
+            Filter::Bicubic,
+            Filter::Lanczos3,
+        ] {
+            assert_eq!(c.resize(4, 3, filter), c);
+        }
+    }
+
+    #[test]
+    fn test_resize_constant() {
+        let mut c = Canvas::new(5, 4);
+        c.pixels.fill(Color::new(0.25, 0.5, 0.75));
+
+        for filter in [Filter::Bilinear, Filter::Bicubic, Filter::Lanczos3] {
+            for (w, h) in [(2, 3), (11, 9)] {
+                let r = c.resize(w, h, filter);
+                assert!(r.pixels.iter().all(|&p| p == Color::new(0.25, 0.5, 0.75)));
+                assert!(r.is_opaque());
+            }
+        }
+    }
+
+    #[test]
+    fn test_resize_nearest() {
+        let c = numbered(2, 1);
+
+        assert_eq!(
+            reds(&c.resize(4, 1, Filter::Nearest)),
+            vec![0.0, 0.0, 1.0, 1.0]
+        );
+        assert_eq!(
+            reds(&numbered(4, 1).resize(2, 1, Filter::Nearest)),
+            vec![1.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn test_resize_bilinear() {
+        let r = numbered(2, 1).resize(4, 1, Filter::Bilinear);
+
+        for (p, expected) in r.pixels.iter().zip([0.0, 0.25, 0.75, 1.0]) {
+            assert_approx_eq!(p.r, expected);
+        }
+    }
+
+    #[test]
+    fn test_resize_keeps_alpha_in_range() {
+        let mut c = Canvas::new_transparent(8, 1);
+        c.write_pixel(4, 0, Color::new(1.0, 1.0, 1.0));
+
+        let r = c.resize(20, 1, Filter::Lanczos3);
+        assert!(r.alpha.iter().all(|&a| (0.0..=1.0).contains(&a)));
+    }
+
+    #[test]
+    fn test_blit() {
+        let mut c = Canvas::new(3, 3);
+        c.blit(&numbered(2, 2), 2, -1);
+
+        assert_eq!(reds(&c), vec![0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_tile() {
+        let images = [numbered(2, 2), numbered(1, 1), numbered(2, 1)];
+        let sheet = Canvas::tile(&images, 2, 1);
+
+        assert_eq!((sheet.width, sheet.height), (5, 5));
+        assert_eq!(sheet.crop(0, 0, 2, 2), images[0]);
+        assert_eq!(sheet[(3, 0)], Color::new(0.0, 0.0, 0.0));
+        assert_eq!(sheet.alpha[3], 1.0);
+        assert_eq!(sheet.alpha[4], 0.0);
+        assert_eq!(sheet.crop(0, 3, 2, 1), images[2]);
+        assert_eq!(sheet.alpha[2], 0.0);
+    }
+}
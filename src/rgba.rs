@@ -0,0 +1,205 @@
+//! premultiplied rgba pixels and porter–duff compositing.
+
+use crate::{approx::ApproxEq, canvas::Canvas, color::Color};
+
+/// a color premultiplied by its coverage, so `color` never exceeds what
+/// `alpha` allows and compositing is a plain weighted sum.
+#[derive(Clone, Copy, Debug)]
+pub struct Rgba {
+    pub color: Color,
+    pub alpha: f64,
+}
+
+/// the porter–duff operators, each describing how much of the source `a` and
+/// destination `b` survive where they overlap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PorterDuff {
+    /// `a` on top of `b`.
+    Over,
+    /// `a` where `b` is.
+    In,
+    /// `a` where `b` isn't.
+    Out,
+    /// `a` where `b` is, with `b` showing everywhere else.
+    Atop,
+    /// `a` and `b` where they don't overlap.
+    Xor,
+}
+
+impl Rgba {
+    pub fn premultiplied(color: Color, alpha: f64) -> Self {
+        Self { color, alpha }
+    }
+
+    /// premultiplies a straight (unassociated) color by `alpha`.
+    pub fn from_straight(color: Color, alpha: f64) -> Self {
+        Self {
+            color: color * alpha,
+            alpha,
+        }
+    }
+
+    pub fn opaque(color: Color) -> Self {
+        Self { color, alpha: 1.0 }
+    }
+
+    pub fn transparent() -> Self {
+        Self {
+            color: Color::new(0.0, 0.0, 0.0),
+            alpha: 0.0,
+        }
+    }
+
+    /// divides the alpha back out. fully transparent pixels come back black.
+    pub fn to_straight(self) -> Color {
+        if self.alpha <= 0.0 {
+            Color::new(0.0, 0.0, 0.0)
+        } else {
+            self.color * (1.0 / self.alpha)
+        }
+    }
+
+    /// composites `self` as the source onto `dst`.
+    pub fn composite(self, op: PorterDuff, dst: Rgba) -> Rgba {
+        let (a, b) = (self.alpha, dst.alpha);
+        let (fa, fb) = match op {
+            PorterDuff::Over => (1.0, 1.0 - a),
+            PorterDuff::In => (b, 0.0),
+            PorterDuff::Out => (1.0 - b, 0.0),
+            PorterDuff::Atop => (b, 1.0 - a),
+            PorterDuff::Xor => (1.0 - b, 1.0 - a),
+        };
+
+        Rgba {
+            color: self.color * fa + dst.color * fb,
+            alpha: a * fa + b * fb,
+        }
+    }
+
+    pub fn over(self, dst: Rgba) -> Rgba {
+        self.composite(PorterDuff::Over, dst)
+    }
+}
+
+impl ApproxEq for Rgba {
+    type Scalar = f64;
+
+    fn all_components<F>(&self, other: &Self, f: F) -> bool
+    where
+        F: Fn(&f64, &f64) -> bool,
+    {
+        self.color.all_components(&other.color, &f) && f(&self.alpha, &other.alpha)
+    }
+}
+
+impl PartialEq for Rgba {
+    fn eq(&self, other: &Self) -> bool {
+        self.approx_eq(other)
+    }
+}
+
+impl Canvas {
+    /// composites this canvas as the source onto `dst`, which must be the same
+    /// size.
+    pub fn composite(&self, op: PorterDuff, dst: &Canvas) -> Canvas {
+        assert!(
+            self.width == dst.width && self.height == dst.height,
+            "can't composite a {}x{} canvas onto a {}x{} one",
+            self.width,
+            self.height,
+            dst.width,
+            dst.height
+        );
+
+        let mut out = Canvas::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                out.write_rgba(x, y, self.rgba(x, y).composite(op, dst.rgba(x, y)));
+            }
+        }
+
+        out
+    }
+
+    /// `self` on top of `dst`, e.g. a render with transparent misses over a
+    /// background plate.
+    pub fn over(&self, dst: &Canvas) -> Canvas {
+        self.composite(PorterDuff::Over, dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    fn red(alpha: f64) -> Rgba {
+        Rgba::from_straight(Color::new(1.0, 0.0, 0.0), alpha)
+    }
+
+    fn blue(alpha: f64) -> Rgba {
+        Rgba::from_straight(Color::new(0.0, 0.0, 1.0), alpha)
+    }
+
+    #[test]
+    fn test_premultiplied() {
+        let c = Rgba::from_straight(Color::new(1.0, 0.5, 0.25), 0.5);
+        assert_eq!(c.color, Color::new(0.5, 0.25, 0.125));
+        assert_eq!(c.to_straight(), Color::new(1.0, 0.5, 0.25));
+        assert_eq!(Rgba::transparent().to_straight(), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_porter_duff() {
+        let (a, b) = (red(0.5), blue(0.5));
+
+        assert_approx_eq!(
+            a.composite(PorterDuff::Over, b),
+            Rgba::premultiplied(Color::new(0.5, 0.0, 0.25), 0.75)
+        );
+        assert_approx_eq!(
+            a.composite(PorterDuff::In, b),
+            Rgba::premultiplied(Color::new(0.25, 0.0, 0.0), 0.25)
+        );
+        assert_approx_eq!(
+            a.composite(PorterDuff::Out, b),
+            Rgba::premultiplied(Color::new(0.25, 0.0, 0.0), 0.25)
+        );
+        assert_approx_eq!(
+            a.composite(PorterDuff::Atop, b),
+            Rgba::premultiplied(Color::new(0.25, 0.0, 0.25), 0.5)
+        );
+        assert_approx_eq!(
+            a.composite(PorterDuff::Xor, b),
+            Rgba::premultiplied(Color::new(0.25, 0.0, 0.25), 0.5)
+        );
+
+        // opaque sources cover everything, transparent ones nothing.
+        assert_eq!(red(1.0).over(b), red(1.0));
+        assert_eq!(Rgba::transparent().over(b), b);
+        assert_eq!(
+            a.composite(PorterDuff::In, Rgba::transparent()),
+            Rgba::transparent()
+        );
+    }
+
+    #[test]
+    fn test_composite_canvas() {
+        let mut render = Canvas::new_transparent(2, 1);
+        render.write_pixel(0, 0, Color::new(1.0, 1.0, 1.0));
+
+        let mut plate = Canvas::new(2, 1);
+        plate.write_pixel(1, 0, Color::new(0.0, 0.5, 0.0));
+
+        let out = render.over(&plate);
+        assert_eq!(out[(0, 0)], Color::new(1.0, 1.0, 1.0));
+        assert_eq!(out[(1, 0)], Color::new(0.0, 0.5, 0.0));
+        assert!(out.is_opaque());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_composite_canvas_size_mismatch() {
+        Canvas::new(2, 1).over(&Canvas::new(1, 2));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::approx::ApproxScalar;
+use num_traits::Float;
+
+/// the float types `GenericTuple` and `GenericMatrix` can be built on.
+///
+/// the kernel methods default to plain scalar code; with the `simd` feature
+/// enabled, `f64` swaps them for SSE2 versions that give bit-identical results.
+pub trait Scalar: ApproxScalar {
+    fn add4(a: [Self; 4], b: [Self; 4]) -> [Self; 4] {
+        add4(a, b)
+    }
+
+    fn sub4(a: [Self; 4], b: [Self; 4]) -> [Self; 4] {
+        sub4(a, b)
+    }
+
+    fn dot4(a: [Self; 4], b: [Self; 4]) -> Self {
+        dot4(a, b)
+    }
+
+    fn cross3(a: [Self; 4], b: [Self; 4]) -> [Self; 4] {
+        cross3(a, b)
+    }
+
+    fn normalize4(a: [Self; 4]) -> [Self; 4] {
+        normalize4(a)
+    }
+
+    /// `m` is a row-major 4x4 matrix.
+    fn mat4_mul_vec4(m: &[Self; 16], v: [Self; 4]) -> [Self; 4] {
+        mat4_mul_vec4(m, v)
+    }
+
+    /// `a` and `b` are row-major 4x4 matrices.
+    fn mat4_mul_mat4(a: &[Self; 16], b: &[Self; 16]) -> [Self; 16] {
+        mat4_mul_mat4(a, b)
+    }
+}
+
+impl Scalar for f32 {}
+
+#[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+impl Scalar for f64 {}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+impl Scalar for f64 {
+    fn add4(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+        crate::simd::add4(a, b)
+    }
+
+    fn sub4(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+        crate::simd::sub4(a, b)
+    }
+
+    fn dot4(a: [f64; 4], b: [f64; 4]) -> f64 {
+        crate::simd::dot4(a, b)
+    }
+
+    fn cross3(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+        crate::simd::cross3(a, b)
+    }
+
+    fn normalize4(a: [f64; 4]) -> [f64; 4] {
+        crate::simd::normalize4(a)
+    }
+
+    fn mat4_mul_vec4(m: &[f64; 16], v: [f64; 4]) -> [f64; 4] {
+        crate::simd::mat4_mul_vec4(m, v)
+    }
+
+    fn mat4_mul_mat4(a: &[f64; 16], b: &[f64; 16]) -> [f64; 16] {
+        crate::simd::mat4_mul_mat4(a, b)
+    }
+}
+
+pub fn add4<T: Float>(a: [T; 4], b: [T; 4]) -> [T; 4] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]]
+}
+
+pub fn sub4<T: Float>(a: [T; 4], b: [T; 4]) -> [T; 4] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2], a[3] - b[3]]
+}
+
+pub fn dot4<T: Float>(a: [T; 4], b: [T; 4]) -> T {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2] + a[3] * b[3]
+}
+
+// the w component of the result is always 0, since the cross product only
+// makes sense for vectors.
+pub fn cross3<T: Float>(a: [T; 4], b: [T; 4]) -> [T; 4] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+        T::zero(),
+    ]
+}
+
+pub fn normalize4<T: Float>(a: [T; 4]) -> [T; 4] {
+    let magnitude = dot4(a, a).sqrt();
+    [
+        a[0] / magnitude,
+        a[1] / magnitude,
+        a[2] / magnitude,
+        a[3] / magnitude,
+    ]
+}
+
+pub fn mat4_mul_vec4<T: Float>(m: &[T; 16], v: [T; 4]) -> [T; 4] {
+    let mut result = [T::zero(); 4];
+    for (row, out) in result.iter_mut().enumerate() {
+        for (col, &x) in v.iter().enumerate() {
+            *out = *out + m[row * 4 + col] * x;
+        }
+    }
+
+    result
+}
+
+pub fn mat4_mul_mat4<T: Float>(a: &[T; 16], b: &[T; 16]) -> [T; 16] {
+    let mut result = [T::zero(); 16];
+    for row in 0..4 {
+        for col in 0..4 {
+            for k in 0..4 {
+                result[row * 4 + col] = result[row * 4 + col] + a[row * 4 + k] * b[k * 4 + col];
+            }
+        }
+    }
+
+    result
+}
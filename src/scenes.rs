@@ -0,0 +1,159 @@
+//! canonical scenes, shared by the binary and the golden image tests.
+
+use crate::{
+    canvas::Canvas, color::Color, point::Point3, transformation::rotation_y, vector::Vector3,
+};
+use std::f64::consts::PI;
+
+/// a clock face on a `size` by `size` canvas: a dot for every hour, a bigger
+/// one at twelve, a centre pin and a ring around the lot. mark sizes are
+/// given for a 900 pixel canvas and scale with it.
+pub fn clock(size: usize) -> Canvas {
+    let mut c = Canvas::new(size, size);
+    let white = Color::new(1.0, 1.0, 1.0);
+    let radius = (3.0 / 8.0) * c.width as f64;
+    let mark = |r: f64| ((r * size as f64 / 900.0).round() as i64).max(1);
+
+    let center = Point3::new(c.width as f64 / 2.0, 0.0, c.height as f64 / 2.0);
+    let twelve = Point3::new(0.0, 0.0, 1.0);
+
+    for i in 1..12 {
+        let r = rotation_y(i as f64 * PI / 6.0);
+        let mut point = r * twelve;
+        point.x = point.x * radius + center.x;
+        point.z = point.z * radius + center.z;
+
+        c.fill_circle(point.x as i64, point.z as i64, mark(8.0), white);
+    }
+
+    c.fill_circle(
+        (twelve.x * radius + center.x) as i64,
+        (twelve.z * radius + center.z) as i64,
+        mark(12.0),
+        white,
+    );
+
+    c.fill_circle(center.x as i64, center.z as i64, mark(4.0), white);
+    c.draw_circle(
+        center.x as i64,
+        center.z as i64,
+        (radius * 1.1) as i64,
+        white,
+    );
+
+    c
+}
+
+/// a magenta unit sphere on black, seen head on and lit from the upper left
+/// with phong shading.
+pub fn lit_sphere(size: usize) -> Canvas {
+    let mut c = Canvas::new(size, size);
+    let eye = Point3::new(0.0, 0.0, -5.0);
+    let light = Point3::new(-10.0, 10.0, -10.0);
+    let color = Color::new(1.0, 0.2, 1.0);
+
+    // each pixel looks at its spot on a wall behind the sphere.
+    let (wall_z, wall_size) = (10.0, 7.0);
+    for y in 0..size {
+        for x in 0..size {
+            let target = Point3::new(
+                wall_size * ((x as f64 + 0.5) / size as f64 - 0.5),
+                wall_size * (0.5 - (y as f64 + 0.5) / size as f64),
+                wall_z,
+            );
+            let direction = (target - eye).normalize();
+
+            if let Some(t) = hit_sphere(eye, direction, 1.0) {
+                let point = eye + direction * t;
+                let normal = (point - Point3::origin()).normalize();
+                c.write_pixel(x, y, phong(color, point, normal, -direction, light));
+            }
+        }
+    }
+
+    c
+}
+
+/// a mirrored unit sphere resting on a checkered floor under a blue sky,
+/// casting a shadow, seen from a little above.
+pub fn reflective(size: usize) -> Canvas {
+    let mut c = Canvas::new(size, size);
+    let eye = Point3::new(0.0, 1.0, -5.0);
+
+    let forward = (Point3::origin() - eye).normalize();
+    let right = Vector3::new(0.0, 1.0, 0.0).cross(&forward).normalize();
+    let up = forward.cross(&right);
+    let half_view = (PI / 6.0).tan();
+
+    for y in 0..size {
+        for x in 0..size {
+            let u = half_view * (2.0 * (x as f64 + 0.5) / size as f64 - 1.0);
+            let v = half_view * (1.0 - 2.0 * (y as f64 + 0.5) / size as f64);
+            let direction = (forward + right * u + up * v).normalize();
+
+            let color = match hit_sphere(eye, direction, 1.0) {
+                Some(t) => {
+                    let point = eye + direction * t;
+                    let normal = (point - Point3::origin()).normalize();
+                    let bounce = direction - normal * (2.0 * direction.dot(&normal));
+                    backdrop(point, bounce) * 0.9
+                }
+                None => backdrop(eye, direction),
+            };
+            c.write_pixel(x, y, color);
+        }
+    }
+
+    c
+}
+
+// the nearest point in front of `origin` where the ray hits a sphere of
+// `radius` at the origin, as a distance along the normalized `direction`.
+fn hit_sphere(origin: Point3, direction: Vector3, radius: f64) -> Option<f64> {
+    let to_origin = origin - Point3::origin();
+    let b = to_origin.dot(&direction);
+    let discriminant = b * b - (to_origin.dot(&to_origin) - radius * radius);
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let root = discriminant.sqrt();
+    [-b - root, -b + root].into_iter().find(|&t| t > 1e-6)
+}
+
+// ambient, diffuse and specular light from a white point light.
+fn phong(color: Color, point: Point3, normal: Vector3, eye: Vector3, light: Point3) -> Color {
+    let to_light = (light - point).normalize();
+    let ambient = color * 0.1;
+    let facing = to_light.dot(&normal);
+    if facing < 0.0 {
+        return ambient;
+    }
+
+    let reflected = normal * (2.0 * facing) - to_light;
+    let specular = 0.9 * reflected.dot(&eye).max(0.0).powf(200.0);
+    ambient + color * (0.9 * facing) + Color::new(specular, specular, specular)
+}
+
+// what a ray misses the sphere to see: the floor a unit below the sphere's
+// centre, or the sky.
+fn backdrop(origin: Point3, direction: Vector3) -> Color {
+    let light = Point3::new(-10.0, 10.0, -10.0);
+    if direction.y >= 0.0 {
+        return Color::new(0.6, 0.8, 1.0) * (1.0 - direction.y)
+            + Color::new(0.1, 0.3, 0.8) * direction.y;
+    }
+
+    let point = origin + direction * ((-1.0 - origin.y) / direction.y);
+    let color = if (point.x.floor() + point.z.floor()).rem_euclid(2.0) == 0.0 {
+        Color::new(0.9, 0.9, 0.9)
+    } else {
+        Color::new(0.2, 0.2, 0.2)
+    };
+
+    let to_light = (light - point).normalize();
+    if hit_sphere(point, to_light, 1.0).is_some() {
+        return color * 0.1;
+    }
+    phong(color, point, Vector3::new(0.0, 1.0, 0.0), -direction, light)
+}
@@ -0,0 +1,199 @@
+//! SSE2 versions of the `Scalar` kernels for `f64`.
+//!
+//! every kernel performs the same operations in the same order as its scalar
+//! counterpart in `crate::scalar` (no fused multiply-adds, no reassociated
+//! sums), so switching the feature on never changes a rendered image.
+
+use std::arch::x86_64::{
+    __m128d, _mm_add_pd, _mm_div_pd, _mm_loadu_pd, _mm_mul_pd, _mm_set1_pd, _mm_set_pd,
+    _mm_setzero_pd, _mm_storeu_pd, _mm_sub_pd,
+};
+
+#[inline(always)]
+fn load(a: &[f64], i: usize) -> __m128d {
+    let pair = &a[i..i + 2];
+    // SAFETY: `pair` is two contiguous, initialized f64s.
+    unsafe { _mm_loadu_pd(pair.as_ptr()) }
+}
+
+#[inline(always)]
+fn store(out: &mut [f64], i: usize, v: __m128d) {
+    let pair = &mut out[i..i + 2];
+    // SAFETY: `pair` is two contiguous f64s we have exclusive access to.
+    unsafe { _mm_storeu_pd(pair.as_mut_ptr(), v) }
+}
+
+#[inline(always)]
+fn lanes(v: __m128d) -> [f64; 2] {
+    let mut out = [0.0; 2];
+    store(&mut out, 0, v);
+    out
+}
+
+pub fn add4(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+    // SAFETY: sse2 is always enabled on x86_64.
+    unsafe {
+        let mut out = [0.0; 4];
+        store(&mut out, 0, _mm_add_pd(load(&a, 0), load(&b, 0)));
+        store(&mut out, 2, _mm_add_pd(load(&a, 2), load(&b, 2)));
+        out
+    }
+}
+
+pub fn sub4(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+    // SAFETY: sse2 is always enabled on x86_64.
+    unsafe {
+        let mut out = [0.0; 4];
+        store(&mut out, 0, _mm_sub_pd(load(&a, 0), load(&b, 0)));
+        store(&mut out, 2, _mm_sub_pd(load(&a, 2), load(&b, 2)));
+        out
+    }
+}
+
+pub fn dot4(a: [f64; 4], b: [f64; 4]) -> f64 {
+    // SAFETY: sse2 is always enabled on x86_64.
+    unsafe {
+        let [p0, p1] = lanes(_mm_mul_pd(load(&a, 0), load(&b, 0)));
+        let [p2, p3] = lanes(_mm_mul_pd(load(&a, 2), load(&b, 2)));
+
+        // summed left to right, like the scalar version.
+        p0 + p1 + p2 + p3
+    }
+}
+
+pub fn cross3(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+    // SAFETY: sse2 is always enabled on x86_64.
+    unsafe {
+        // lanes (x, y) of the result, then (z, _).
+        let a_yz = _mm_set_pd(a[2], a[1]);
+        let b_zx = _mm_set_pd(b[0], b[2]);
+        let a_zx = _mm_set_pd(a[0], a[2]);
+        let b_yz = _mm_set_pd(b[2], b[1]);
+        let xy = _mm_sub_pd(_mm_mul_pd(a_yz, b_zx), _mm_mul_pd(a_zx, b_yz));
+
+        let a_x = _mm_set_pd(0.0, a[0]);
+        let b_y = _mm_set_pd(0.0, b[1]);
+        let a_y = _mm_set_pd(0.0, a[1]);
+        let b_x = _mm_set_pd(0.0, b[0]);
+        let [z, _] = lanes(_mm_sub_pd(_mm_mul_pd(a_x, b_y), _mm_mul_pd(a_y, b_x)));
+
+        let [x, y] = lanes(xy);
+        [x, y, z, 0.0]
+    }
+}
+
+pub fn normalize4(a: [f64; 4]) -> [f64; 4] {
+    // SAFETY: sse2 is always enabled on x86_64.
+    unsafe {
+        let magnitude = _mm_set1_pd(dot4(a, a).sqrt());
+        let mut out = [0.0; 4];
+        store(&mut out, 0, _mm_div_pd(load(&a, 0), magnitude));
+        store(&mut out, 2, _mm_div_pd(load(&a, 2), magnitude));
+        out
+    }
+}
+
+pub fn mat4_mul_vec4(m: &[f64; 16], v: [f64; 4]) -> [f64; 4] {
+    // SAFETY: sse2 is always enabled on x86_64.
+    unsafe {
+        // rows (0, 1) and (2, 3) are accumulated column by column, which keeps
+        // each lane's sum in the same order as the scalar dot product.
+        let mut lo = _mm_setzero_pd();
+        let mut hi = _mm_setzero_pd();
+        for (col, &x) in v.iter().enumerate() {
+            let x = _mm_set1_pd(x);
+            lo = _mm_add_pd(lo, _mm_mul_pd(_mm_set_pd(m[4 + col], m[col]), x));
+            hi = _mm_add_pd(hi, _mm_mul_pd(_mm_set_pd(m[12 + col], m[8 + col]), x));
+        }
+
+        let mut out = [0.0; 4];
+        store(&mut out, 0, lo);
+        store(&mut out, 2, hi);
+        out
+    }
+}
+
+pub fn mat4_mul_mat4(a: &[f64; 16], b: &[f64; 16]) -> [f64; 16] {
+    // SAFETY: sse2 is always enabled on x86_64.
+    unsafe {
+        let mut out = [0.0; 16];
+        for row in 0..4 {
+            let mut lo = _mm_setzero_pd();
+            let mut hi = _mm_setzero_pd();
+            for k in 0..4 {
+                let x = _mm_set1_pd(a[row * 4 + k]);
+                lo = _mm_add_pd(lo, _mm_mul_pd(x, load(b, k * 4)));
+                hi = _mm_add_pd(hi, _mm_mul_pd(x, load(b, k * 4 + 2)));
+            }
+            store(&mut out, row * 4, lo);
+            store(&mut out, row * 4 + 2, hi);
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scalar;
+
+    // small deterministic generator so the comparisons cover a spread of
+    // magnitudes and signs without pulling in a dependency.
+    fn values(seed: u64, n: usize) -> Vec<f64> {
+        let mut state = seed;
+        (0..n)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let unit = (state >> 11) as f64 / (1u64 << 53) as f64;
+                (unit - 0.5) * 10f64.powi((state % 7) as i32 - 3)
+            })
+            .collect()
+    }
+
+    fn vec4(seed: u64) -> [f64; 4] {
+        values(seed, 4).try_into().unwrap()
+    }
+
+    fn mat4(seed: u64) -> [f64; 16] {
+        values(seed, 16).try_into().unwrap()
+    }
+
+    fn bits<const N: usize>(a: [f64; N]) -> [u64; N] {
+        a.map(f64::to_bits)
+    }
+
+    #[test]
+    fn test_simd_tuple_kernels_match_scalar() {
+        for seed in 0..256 {
+            let a = vec4(seed);
+            let b = vec4(seed + 1000);
+
+            assert_eq!(bits(add4(a, b)), bits(scalar::add4(a, b)));
+            assert_eq!(bits(sub4(a, b)), bits(scalar::sub4(a, b)));
+            assert_eq!(dot4(a, b).to_bits(), scalar::dot4(a, b).to_bits());
+            assert_eq!(bits(cross3(a, b)), bits(scalar::cross3(a, b)));
+            assert_eq!(bits(normalize4(a)), bits(scalar::normalize4(a)));
+        }
+    }
+
+    #[test]
+    fn test_simd_matrix_kernels_match_scalar() {
+        for seed in 0..256 {
+            let m = mat4(seed);
+            let n = mat4(seed + 1000);
+            let v = vec4(seed + 2000);
+
+            assert_eq!(
+                bits(mat4_mul_vec4(&m, v)),
+                bits(scalar::mat4_mul_vec4(&m, v))
+            );
+            assert_eq!(
+                bits(mat4_mul_mat4(&m, &n)),
+                bits(scalar::mat4_mul_mat4(&m, &n))
+            );
+        }
+    }
+}
@@ -0,0 +1,462 @@
+//! spectral color, for effects rgb can't represent like dispersion.
+//!
+//! a spectral render traces a handful of wavelengths per camera ray (hero
+//! wavelength sampling), carrying a `SampledSpectrum` instead of a `Color`.
+//! each sample is splatted into a `SpectralCanvas` as cie xyz, and only turned
+//! into rgb when the finished `Canvas` is produced.
+
+use crate::{
+    canvas::Canvas,
+    color::{cie_1931_cmf, Color, Xyz},
+};
+use std::{
+    ops::{Add, Mul},
+    sync::OnceLock,
+};
+
+/// the visible range wavelengths are sampled from, in nanometres.
+pub const LAMBDA_MIN: f64 = 360.0;
+pub const LAMBDA_MAX: f64 = 830.0;
+
+/// how many wavelengths travel together along each path.
+pub const SAMPLES: usize = 4;
+
+/// wavelengths, in nanometres, chosen for one camera ray.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SampledWavelengths {
+    pub lambda: [f64; SAMPLES],
+    pub pdf: [f64; SAMPLES],
+}
+
+impl SampledWavelengths {
+    /// hero wavelength sampling: `u` in `[0, 1)` picks the first wavelength
+    /// and the rest are spread evenly after it, wrapping around the visible
+    /// range, so every path covers the whole spectrum.
+    pub fn sample_uniform(u: f64) -> Self {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let hero = LAMBDA_MIN + u * range;
+
+        let mut lambda = [0.0; SAMPLES];
+        for (i, l) in lambda.iter_mut().enumerate() {
+            let offset = hero - LAMBDA_MIN + i as f64 * range / SAMPLES as f64;
+            *l = LAMBDA_MIN + offset % range;
+        }
+
+        Self {
+            lambda,
+            pdf: [1.0 / range; SAMPLES],
+        }
+    }
+
+    /// drops every wavelength but the hero, e.g. when a ray refracts through a
+    /// dispersive medium and the wavelengths would need different paths.
+    pub fn terminate_secondary(&mut self) {
+        if self.secondary_terminated() {
+            return;
+        }
+
+        for pdf in self.pdf.iter_mut().skip(1) {
+            *pdf = 0.0;
+        }
+        // the hero now stands in for all of them.
+        self.pdf[0] /= SAMPLES as f64;
+    }
+
+    pub fn secondary_terminated(&self) -> bool {
+        self.pdf[1..].iter().all(|&p| p == 0.0)
+    }
+}
+
+/// a spectrum's values at the wavelengths of a `SampledWavelengths`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SampledSpectrum(pub [f64; SAMPLES]);
+
+impl SampledSpectrum {
+    pub fn constant(value: f64) -> Self {
+        Self([value; SAMPLES])
+    }
+
+    /// the monte carlo estimate of this sample's xyz, normalized so a spectrum
+    /// of constant 1.0 has a luminance `y` of 1.0.
+    pub fn to_xyz(&self, wavelengths: &SampledWavelengths) -> Xyz {
+        let mut xyz = Xyz::new(0.0, 0.0, 0.0);
+        for i in 0..SAMPLES {
+            if wavelengths.pdf[i] == 0.0 {
+                continue;
+            }
+
+            let cmf = cie_1931_cmf(wavelengths.lambda[i]);
+            let weight = self.0[i] / wavelengths.pdf[i] / SAMPLES as f64;
+            xyz.x += cmf.x * weight;
+            xyz.y += cmf.y * weight;
+            xyz.z += cmf.z * weight;
+        }
+
+        let white = white_xyz();
+        Xyz::new(xyz.x / white.y, xyz.y / white.y, xyz.z / white.y)
+    }
+}
+
+impl Add for SampledSpectrum {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] + other.0[i]))
+    }
+}
+
+impl Mul for SampledSpectrum {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] * other.0[i]))
+    }
+}
+
+impl Mul<f64> for SampledSpectrum {
+    type Output = Self;
+
+    fn mul(self, other: f64) -> Self {
+        Self(self.0.map(|v| v * other))
+    }
+}
+
+/// a continuous spectrum that can be evaluated at any wavelength.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Spectrum {
+    Constant(f64),
+    /// an rgb reflectance, upsampled with smits' method.
+    Rgb(Color),
+    /// a blackbody at the given kelvin, scaled so its peak is 1.0.
+    Blackbody(f64),
+    /// measured data, e.g. a glass's transmittance, with `values[i]` at
+    /// `start + i * step` nanometres and linear interpolation in between.
+    Sampled {
+        start: f64,
+        step: f64,
+        values: Vec<f64>,
+    },
+}
+
+// smits' basis spectra, sampled at 10 evenly spaced wavelengths from 380 to
+// 720nm, from "an rgb-to-spectrum conversion for reflectances".
+const SMITS_START: f64 = 380.0;
+const SMITS_STEP: f64 = 340.0 / 9.0;
+const SMITS_WHITE: [f64; 10] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const SMITS_CYAN: [f64; 10] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [f64; 10] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [f64; 10] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [f64; 10] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [f64; 10] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const SMITS_BLUE: [f64; 10] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+// linear interpolation into evenly spaced samples, holding the end values
+// outside them.
+fn interpolate(start: f64, step: f64, values: &[f64], lambda: f64) -> f64 {
+    let Some(&last) = values.last() else {
+        return 0.0;
+    };
+
+    let t = (lambda - start) / step;
+    if t <= 0.0 {
+        return values[0];
+    }
+    if t >= (values.len() - 1) as f64 {
+        return last;
+    }
+
+    let i = t as usize;
+    let frac = t - i as f64;
+    values[i] * (1.0 - frac) + values[i + 1] * frac
+}
+
+fn smits(c: Color, lambda: f64) -> f64 {
+    let basis = |spectrum: &[f64; 10]| interpolate(SMITS_START, SMITS_STEP, spectrum, lambda);
+    let (r, g, b) = (c.r, c.g, c.b);
+
+    // white covers the smallest component, then a secondary color the middle
+    // one, then a primary whatever is left of the largest.
+    if r <= g && r <= b {
+        let (secondary, primary) = if g <= b {
+            ((g - r) * basis(&SMITS_CYAN), (b - g) * basis(&SMITS_BLUE))
+        } else {
+            ((b - r) * basis(&SMITS_CYAN), (g - b) * basis(&SMITS_GREEN))
+        };
+        r * basis(&SMITS_WHITE) + secondary + primary
+    } else if g <= r && g <= b {
+        let (secondary, primary) = if r <= b {
+            (
+                (r - g) * basis(&SMITS_MAGENTA),
+                (b - r) * basis(&SMITS_BLUE),
+            )
+        } else {
+            ((b - g) * basis(&SMITS_MAGENTA), (r - b) * basis(&SMITS_RED))
+        };
+        g * basis(&SMITS_WHITE) + secondary + primary
+    } else {
+        let (secondary, primary) = if r <= g {
+            (
+                (r - b) * basis(&SMITS_YELLOW),
+                (g - r) * basis(&SMITS_GREEN),
+            )
+        } else {
+            ((g - b) * basis(&SMITS_YELLOW), (r - g) * basis(&SMITS_RED))
+        };
+        b * basis(&SMITS_WHITE) + secondary + primary
+    }
+}
+
+fn planck(lambda: f64, temperature: f64) -> f64 {
+    // hc / k, in nanometre kelvin.
+    const C2: f64 = 1.438_776_9e7;
+    lambda.powi(-5) / ((C2 / (lambda * temperature)).exp() - 1.0)
+}
+
+impl Spectrum {
+    pub fn eval(&self, lambda: f64) -> f64 {
+        match self {
+            Spectrum::Constant(value) => *value,
+            Spectrum::Rgb(c) => smits(*c, lambda),
+            Spectrum::Blackbody(temperature) => {
+                if *temperature <= 0.0 {
+                    return 0.0;
+                }
+                // wien's displacement law gives the peak.
+                let peak = 2.897_772_1e6 / temperature;
+                planck(lambda, *temperature) / planck(peak, *temperature)
+            }
+            Spectrum::Sampled {
+                start,
+                step,
+                values,
+            } => interpolate(*start, *step, values, lambda),
+        }
+    }
+
+    pub fn sample(&self, wavelengths: &SampledWavelengths) -> SampledSpectrum {
+        SampledSpectrum(wavelengths.lambda.map(|l| self.eval(l)))
+    }
+
+    /// integrates the spectrum against the cie observer at 1nm steps, for
+    /// when there's no need to estimate it one path at a time.
+    pub fn to_color(&self) -> Color {
+        let mut xyz = Xyz::new(0.0, 0.0, 0.0);
+        for lambda in (LAMBDA_MIN as u32..=LAMBDA_MAX as u32).map(|l| l as f64) {
+            let cmf = cie_1931_cmf(lambda);
+            let value = self.eval(lambda);
+            xyz.x += cmf.x * value;
+            xyz.y += cmf.y * value;
+            xyz.z += cmf.z * value;
+        }
+
+        let white = white_xyz();
+        xyz_to_color(Xyz::new(xyz.x / white.y, xyz.y / white.y, xyz.z / white.y))
+    }
+}
+
+/// the index of refraction at `lambda` nanometres from cauchy's equation
+/// `n = a + b / λ²`, with `b` in square micrometres. bk7 glass is roughly
+/// `a = 1.5046, b = 0.0042`.
+pub fn cauchy_ior(a: f64, b: f64, lambda: f64) -> f64 {
+    let micrometres = lambda / 1000.0;
+    a + b / (micrometres * micrometres)
+}
+
+// the xyz of a constant 1.0 spectrum, integrated at 1nm like `to_color`.
+fn white_xyz() -> Xyz {
+    static WHITE: OnceLock<Xyz> = OnceLock::new();
+    *WHITE.get_or_init(|| {
+        let mut xyz = Xyz::new(0.0, 0.0, 0.0);
+        for lambda in (LAMBDA_MIN as u32..=LAMBDA_MAX as u32).map(|l| l as f64) {
+            let cmf = cie_1931_cmf(lambda);
+            xyz.x += cmf.x;
+            xyz.y += cmf.y;
+            xyz.z += cmf.z;
+        }
+        xyz
+    })
+}
+
+/// converts normalized xyz to the renderer's rgb. a constant spectrum has the
+/// equal-energy white point rather than d65, so each channel is rescaled to
+/// map it to rgb white, the same as a von kries adaptation in rgb.
+pub fn xyz_to_color(xyz: Xyz) -> Color {
+    let white = white_xyz();
+    let white = Color::from_xyz(Xyz::new(white.x / white.y, 1.0, white.z / white.y));
+    let c = Color::from_xyz(xyz);
+    Color::new(c.r / white.r, c.g / white.g, c.b / white.b)
+}
+
+/// a render target for spectral samples. each pixel accumulates xyz so no
+/// color is lost until `to_canvas` converts it to rgb.
+#[derive(Clone, Debug)]
+pub struct SpectralCanvas {
+    pub width: usize,
+    pub height: usize,
+    xyz: Vec<Xyz>,
+    samples: Vec<u32>,
+}
+
+impl SpectralCanvas {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            xyz: vec![Xyz::new(0.0, 0.0, 0.0); width * height],
+            samples: vec![0; width * height],
+        }
+    }
+
+    pub fn add_sample(
+        &mut self,
+        x: usize,
+        y: usize,
+        spectrum: &SampledSpectrum,
+        wavelengths: &SampledWavelengths,
+    ) {
+        assert!(
+            x < self.width && y < self.height,
+            "out of bounds! tried to add a sample at ({}, {}) for canvas size ({}, {})",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+
+        let i = x + y * self.width;
+        let xyz = spectrum.to_xyz(wavelengths);
+        self.xyz[i].x += xyz.x;
+        self.xyz[i].y += xyz.y;
+        self.xyz[i].z += xyz.z;
+        self.samples[i] += 1;
+    }
+
+    /// averages each pixel's samples and converts them to rgb. pixels without
+    /// samples are black.
+    pub fn to_canvas(&self) -> Canvas {
+        let mut canvas = Canvas::new(self.width, self.height);
+        for (i, (xyz, &n)) in self.xyz.iter().zip(self.samples.iter()).enumerate() {
+            if n > 0 {
+                let n = n as f64;
+                canvas.pixels[i] = xyz_to_color(Xyz::new(xyz.x / n, xyz.y / n, xyz.z / n));
+            }
+        }
+
+        canvas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    #[test]
+    fn test_sample_wavelengths() {
+        let w = SampledWavelengths::sample_uniform(0.0);
+        assert_eq!(w.lambda, [360.0, 477.5, 595.0, 712.5]);
+
+        let w = SampledWavelengths::sample_uniform(0.9);
+        assert_approx_eq!(w.lambda[0], 783.0);
+        assert_approx_eq!(w.lambda[1], 430.5);
+        assert!(w
+            .lambda
+            .iter()
+            .all(|&l| (LAMBDA_MIN..LAMBDA_MAX).contains(&l)));
+
+        let mut w = w;
+        w.terminate_secondary();
+        assert!(w.secondary_terminated());
+        assert_eq!(w.pdf[0], 1.0 / 470.0 / 4.0);
+        assert_eq!(&w.pdf[1..], &[0.0; 3]);
+    }
+
+    #[test]
+    fn test_sampled_spectrum_ops() {
+        let a = SampledSpectrum([1.0, 2.0, 3.0, 4.0]);
+        let b = SampledSpectrum::constant(0.5);
+
+        assert_eq!((a + b).0, [1.5, 2.5, 3.5, 4.5]);
+        assert_eq!((a * b).0, [0.5, 1.0, 1.5, 2.0]);
+        assert_eq!((a * 2.0).0, [2.0, 4.0, 6.0, 8.0]);
+    }
+
+    #[test]
+    fn test_constant_spectrum_is_white() {
+        assert_eq!(
+            Spectrum::Constant(1.0).to_color(),
+            Color::new(1.0, 1.0, 1.0)
+        );
+        assert_eq!(
+            Spectrum::Constant(0.5).to_color(),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        let white = Spectrum::Rgb(Color::new(1.0, 1.0, 1.0));
+        assert_approx_eq!(white.eval(500.0), 1.0, abs <= 0.001);
+    }
+
+    #[test]
+    fn test_rgb_roundtrip() {
+        for c in [
+            Color::new(0.8, 0.3, 0.1),
+            Color::new(0.1, 0.6, 0.2),
+            Color::new(0.2, 0.3, 0.9),
+            Color::new(0.5, 0.5, 0.5),
+        ] {
+            let roundtrip = Spectrum::Rgb(c).to_color();
+            assert_approx_eq!(roundtrip, c, abs <= 0.05);
+        }
+    }
+
+    #[test]
+    fn test_blackbody() {
+        let b = Spectrum::Blackbody(5000.0);
+        assert_approx_eq!(b.eval(2.897_772_1e6 / 5000.0), 1.0);
+        assert!(b.eval(400.0) < b.eval(550.0));
+
+        // white is balanced to the equal-energy illuminant, which sits close
+        // to a 5455K blackbody.
+        let c = Spectrum::Blackbody(5455.0).to_color();
+        let max = c.r.max(c.g).max(c.b);
+        assert_approx_eq!(c * (1.0 / max), Color::new(1.0, 1.0, 1.0), abs <= 0.1);
+
+        let warm = Spectrum::Blackbody(3200.0).to_color();
+        assert!(warm.r > warm.g && warm.g > warm.b);
+    }
+
+    #[test]
+    fn test_cauchy_ior() {
+        assert_approx_eq!(cauchy_ior(1.5046, 0.0042, 1000.0), 1.5088);
+        assert!(cauchy_ior(1.5046, 0.0042, 400.0) > cauchy_ior(1.5046, 0.0042, 700.0));
+    }
+
+    #[test]
+    fn test_spectral_canvas() {
+        let mut canvas = SpectralCanvas::new(2, 1);
+        let red = Spectrum::Rgb(Color::new(0.8, 0.1, 0.1));
+
+        // stratified hero wavelengths converge quickly.
+        for i in 0..1000 {
+            let w = SampledWavelengths::sample_uniform((i as f64 + 0.5) / 1000.0);
+            canvas.add_sample(0, 0, &red.sample(&w), &w);
+        }
+
+        let c = canvas.to_canvas();
+        assert_approx_eq!(c[(0, 0)], red.to_color(), abs <= 0.01);
+        assert_eq!(c[(1, 0)], Color::new(0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,155 @@
+//! operators for squeezing high dynamic range colors into [0, 1] before a
+//! canvas is quantized for export.
+
+use crate::{canvas::Canvas, color::Color, rgba::Rgba};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMap {
+    /// scales by `2^stops`, leaving anything still above 1.0 to be clipped.
+    Exposure(f64),
+    /// `c / (1 + c)`, which never quite reaches white.
+    Reinhard,
+    /// reinhard rescaled so that `white` maps to exactly 1.0. a `white` that
+    /// isn't positive falls back to plain reinhard.
+    ReinhardExtended { white: f64 },
+    /// krzysztof narkowicz's curve fit of the aces reference rendering.
+    AcesFilmic,
+    /// john hable's filmic curve from uncharted 2, normalized so `white` maps
+    /// to 1.0. hable used a white of 11.2 after doubling the exposure. a
+    /// `white` that isn't positive and finite falls back to plain reinhard.
+    Uncharted2 { white: f64 },
+}
+
+fn uncharted2_curve(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f
+}
+
+impl ToneMap {
+    pub fn map(&self, color: Color) -> Color {
+        // a white point of 0 would divide by zero.
+        let op = match *self {
+            ToneMap::ReinhardExtended { white } if white <= 0.0 || white.is_nan() => {
+                ToneMap::Reinhard
+            }
+            ToneMap::Uncharted2 { white } if white <= 0.0 || !white.is_finite() => {
+                ToneMap::Reinhard
+            }
+            op => op,
+        };
+
+        let channel = |x: f64| -> f64 {
+            let x = x.max(0.0);
+            match op {
+                ToneMap::Exposure(stops) => x * 2f64.powf(stops),
+                ToneMap::Reinhard => x / (1.0 + x),
+                ToneMap::ReinhardExtended { white } => x * (1.0 + x / (white * white)) / (1.0 + x),
+                ToneMap::AcesFilmic => {
+                    let mapped = (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+                    mapped.clamp(0.0, 1.0)
+                }
+                ToneMap::Uncharted2 { white } => uncharted2_curve(x) / uncharted2_curve(white),
+            }
+        };
+
+        Color::new(channel(color.r), channel(color.g), channel(color.b))
+    }
+}
+
+impl Canvas {
+    /// returns a copy of the canvas with `op` applied to every pixel. meant to
+    /// run just before writing, e.g. `canvas.tone_mapped(ToneMap::AcesFilmic)
+    /// .write_to_png(path)`. partially covered pixels are mapped by their
+    /// straight color, so edges don't darken.
+    pub fn tone_mapped(&self, op: ToneMap) -> Canvas {
+        let pixels = self
+            .pixels
+            .iter()
+            .zip(self.alpha.iter())
+            .map(|(&c, &a)| {
+                let straight = Rgba::premultiplied(c, a).to_straight();
+                Rgba::from_straight(op.map(straight), a).color
+            })
+            .collect();
+
+        Canvas {
+            width: self.width,
+            height: self.height,
+            pixels,
+            alpha: self.alpha.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    #[test]
+    fn test_exposure() {
+        let c = Color::new(0.25, 1.0, 3.0);
+        assert_eq!(ToneMap::Exposure(1.0).map(c), Color::new(0.5, 2.0, 6.0));
+        assert_eq!(
+            ToneMap::Exposure(-2.0).map(c),
+            Color::new(0.0625, 0.25, 0.75)
+        );
+        assert_eq!(ToneMap::Exposure(0.0).map(c), c);
+    }
+
+    #[test]
+    fn test_reinhard() {
+        let c = ToneMap::Reinhard.map(Color::new(0.0, 1.0, 3.0));
+        assert_eq!(c, Color::new(0.0, 0.5, 0.75));
+
+        let op = ToneMap::ReinhardExtended { white: 4.0 };
+        assert_eq!(op.map(Color::new(4.0, 8.0, 0.0)).r, 1.0);
+        assert!(op.map(Color::new(8.0, 0.0, 0.0)).r > 1.0);
+        assert_approx_eq!(op.map(Color::new(1.0, 1.0, 1.0)).r, 0.53125);
+
+        let c = Color::new(0.0, 1.0, 3.0);
+        for white in [0.0, -1.0, f64::NAN] {
+            let op = ToneMap::ReinhardExtended { white };
+            assert_eq!(op.map(c), ToneMap::Reinhard.map(c));
+        }
+    }
+
+    #[test]
+    fn test_aces_filmic() {
+        let op = ToneMap::AcesFilmic;
+        assert_eq!(op.map(Color::new(0.0, 0.0, 0.0)), Color::new(0.0, 0.0, 0.0));
+        assert_approx_eq!(op.map(Color::new(1.0, 1.0, 1.0)).r, 0.8038);
+        assert_eq!(op.map(Color::new(100.0, 100.0, 100.0)).r, 1.0);
+
+        // the curve is monotonic, so highlights keep their ordering.
+        let (a, b) = (
+            op.map(Color::new(2.0, 0.0, 0.0)),
+            op.map(Color::new(4.0, 0.0, 0.0)),
+        );
+        assert!(a.r < b.r);
+    }
+
+    #[test]
+    fn test_uncharted2() {
+        let op = ToneMap::Uncharted2 { white: 11.2 };
+        assert_eq!(op.map(Color::new(0.0, 0.0, 0.0)), Color::new(0.0, 0.0, 0.0));
+        assert_approx_eq!(op.map(Color::new(11.2, 0.0, 0.0)).r, 1.0);
+        assert_approx_eq!(op.map(Color::new(1.0, 0.0, 0.0)).r, 0.3043);
+
+        let c = Color::new(0.0, 1.0, 3.0);
+        for white in [0.0, -1.0, f64::INFINITY, f64::NAN] {
+            let op = ToneMap::Uncharted2 { white };
+            assert_eq!(op.map(c), ToneMap::Reinhard.map(c));
+        }
+    }
+
+    #[test]
+    fn test_tone_mapped_canvas() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(1, 0, Color::new(1.0, 3.0, -1.0));
+
+        let mapped = c.tone_mapped(ToneMap::Reinhard);
+        assert_eq!(mapped[(0, 0)], Color::new(0.0, 0.0, 0.0));
+        assert_eq!(mapped[(1, 0)], Color::new(0.5, 0.75, 0.0));
+    }
+}
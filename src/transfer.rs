@@ -0,0 +1,104 @@
+//! transfer functions between the linear light the renderer works in and the
+//! non-linear values image files usually store.
+
+use crate::color::Color;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransferFunction {
+    /// values are written as-is.
+    Linear,
+    /// the piecewise curve from IEC 61966-2-1, what most viewers assume.
+    Srgb,
+    /// the ITU-R BT.709 camera curve used for hd video.
+    Rec709,
+    /// a pure power curve, `v = l^(1/gamma)`.
+    Gamma(f64),
+}
+
+impl TransferFunction {
+    /// linear light to encoded value. negative input is treated as 0.
+    pub fn encode(self, linear: f64) -> f64 {
+        let l = linear.max(0.0);
+        match self {
+            TransferFunction::Linear => l,
+            TransferFunction::Srgb if l <= 0.0031308 => l * 12.92,
+            TransferFunction::Srgb => 1.055 * l.powf(1.0 / 2.4) - 0.055,
+            TransferFunction::Rec709 if l < 0.018 => l * 4.5,
+            TransferFunction::Rec709 => 1.099 * l.powf(0.45) - 0.099,
+            TransferFunction::Gamma(gamma) => l.powf(1.0 / gamma),
+        }
+    }
+
+    /// encoded value back to linear light, the inverse of `encode`.
+    pub fn decode(self, encoded: f64) -> f64 {
+        let v = encoded.max(0.0);
+        match self {
+            TransferFunction::Linear => v,
+            TransferFunction::Srgb if v <= 0.04045 => v / 12.92,
+            TransferFunction::Srgb => ((v + 0.055) / 1.055).powf(2.4),
+            TransferFunction::Rec709 if v < 0.081 => v / 4.5,
+            TransferFunction::Rec709 => ((v + 0.099) / 1.099).powf(1.0 / 0.45),
+            TransferFunction::Gamma(gamma) => v.powf(gamma),
+        }
+    }
+
+    pub fn encode_color(self, c: Color) -> Color {
+        Color::new(self.encode(c.r), self.encode(c.g), self.encode(c.b))
+    }
+
+    pub fn decode_color(self, c: Color) -> Color {
+        Color::new(self.decode(c.r), self.decode(c.g), self.decode(c.b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_approx_eq;
+
+    #[test]
+    fn test_srgb() {
+        let srgb = TransferFunction::Srgb;
+        assert_eq!(srgb.encode(0.0), 0.0);
+        assert_approx_eq!(srgb.encode(1.0), 1.0);
+        assert_approx_eq!(srgb.encode(0.18), 0.46135);
+        assert_approx_eq!(srgb.encode(0.001), 0.01292);
+        assert_approx_eq!(srgb.decode(0.5), 0.21404);
+        assert_eq!(srgb.encode(-1.0), 0.0);
+    }
+
+    #[test]
+    fn test_rec709() {
+        let rec709 = TransferFunction::Rec709;
+        assert_approx_eq!(rec709.encode(1.0), 1.0);
+        assert_approx_eq!(rec709.encode(0.01), 0.045);
+        assert_approx_eq!(rec709.encode(0.18), 0.40901);
+    }
+
+    #[test]
+    fn test_gamma() {
+        let gamma = TransferFunction::Gamma(2.2);
+        assert_approx_eq!(gamma.encode(0.5), 0.72974);
+        assert_approx_eq!(gamma.decode(0.5), 0.21764);
+        assert_eq!(TransferFunction::Linear.encode(0.3), 0.3);
+    }
+
+    #[test]
+    fn test_transfer_inverse() {
+        for tf in [
+            TransferFunction::Linear,
+            TransferFunction::Srgb,
+            TransferFunction::Rec709,
+            TransferFunction::Gamma(2.2),
+            TransferFunction::Gamma(1.8),
+        ] {
+            for i in 0..=100 {
+                let l = i as f64 / 50.0;
+                assert_approx_eq!(tf.decode(tf.encode(l)), l, abs <= 1e-9);
+            }
+
+            let c = Color::new(0.1, 0.5, 0.9);
+            assert_eq!(tf.decode_color(tf.encode_color(c)), c);
+        }
+    }
+}
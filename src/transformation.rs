@@ -1,11 +1,12 @@
-use crate::matrix::Matrix;
+use crate::matrix::GenericMatrix;
+use crate::scalar::Scalar;
 
 // TODO: implement a fluent API for this
 // e.g. transform = matrix.rotate_x().scale().translate();
 // basically letting u chain method calls instead of just functions.
 // good exercise for refactoring :p
-pub fn translation(x: f64, y: f64, z: f64) -> Matrix {
-    let mut matrix = Matrix::identity_matrix(4);
+pub fn translation<T: Scalar>(x: T, y: T, z: T) -> GenericMatrix<T> {
+    let mut matrix = GenericMatrix::identity_matrix(4);
     matrix[(0, 3)] = x;
     matrix[(1, 3)] = y;
     matrix[(2, 3)] = z;
@@ -13,8 +14,8 @@ pub fn translation(x: f64, y: f64, z: f64) -> Matrix {
     matrix
 }
 
-pub fn scaling(x: f64, y: f64, z: f64) -> Matrix {
-    let mut matrix = Matrix::identity_matrix(4);
+pub fn scaling<T: Scalar>(x: T, y: T, z: T) -> GenericMatrix<T> {
+    let mut matrix = GenericMatrix::identity_matrix(4);
     matrix[(0, 0)] = x;
     matrix[(1, 1)] = y;
     matrix[(2, 2)] = z;
@@ -22,8 +23,8 @@ pub fn scaling(x: f64, y: f64, z: f64) -> Matrix {
     matrix
 }
 
-pub fn rotation_x(angle: f64) -> Matrix {
-    let mut matrix = Matrix::identity_matrix(4);
+pub fn rotation_x<T: Scalar>(angle: T) -> GenericMatrix<T> {
+    let mut matrix = GenericMatrix::identity_matrix(4);
     matrix[(1, 1)] = angle.cos();
     matrix[(1, 2)] = -angle.sin();
     matrix[(2, 1)] = angle.sin();
@@ -32,8 +33,8 @@ pub fn rotation_x(angle: f64) -> Matrix {
     matrix
 }
 
-pub fn rotation_y(angle: f64) -> Matrix {
-    let mut matrix = Matrix::identity_matrix(4);
+pub fn rotation_y<T: Scalar>(angle: T) -> GenericMatrix<T> {
+    let mut matrix = GenericMatrix::identity_matrix(4);
     matrix[(0, 0)] = angle.cos();
     matrix[(0, 2)] = angle.sin();
     matrix[(2, 0)] = -angle.sin();
@@ -42,8 +43,8 @@ pub fn rotation_y(angle: f64) -> Matrix {
     matrix
 }
 
-pub fn rotation_z(angle: f64) -> Matrix {
-    let mut matrix = Matrix::identity_matrix(4);
+pub fn rotation_z<T: Scalar>(angle: T) -> GenericMatrix<T> {
+    let mut matrix = GenericMatrix::identity_matrix(4);
     matrix[(0, 0)] = angle.cos();
     matrix[(0, 1)] = -angle.sin();
     matrix[(1, 0)] = angle.sin();
@@ -52,8 +53,8 @@ pub fn rotation_z(angle: f64) -> Matrix {
     matrix
 }
 
-pub fn shearing(xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Matrix {
-    let mut matrix = Matrix::identity_matrix(4);
+pub fn shearing<T: Scalar>(xy: T, xz: T, yx: T, yz: T, zx: T, zy: T) -> GenericMatrix<T> {
+    let mut matrix = GenericMatrix::identity_matrix(4);
     matrix[(0, 1)] = xy;
     matrix[(0, 2)] = xz;
     matrix[(1, 0)] = yx;
@@ -69,7 +70,9 @@ mod tests {
     use std::f64::consts::PI;
 
     use super::*;
-    use crate::tuple::Tuple;
+    use crate::point::Point3;
+    use crate::tuple::{Tuple, Tuple32};
+    use crate::vector::Vector3;
 
     #[test]
     fn test_mul_translation_matrix() {
@@ -96,6 +99,16 @@ mod tests {
         assert_eq!(transform * v, v);
     }
 
+    #[test]
+    fn test_translation_point3_vector3() {
+        let transform = translation(5.0, -3.0, 2.0);
+        let p = Point3::new(-3.0, 4.0, 5.0);
+        let v = Vector3::new(-3.0, 4.0, 5.0);
+
+        assert_eq!(transform.clone() * p, Point3::new(2.0, 1.0, 7.0));
+        assert_eq!(transform * v, v);
+    }
+
     #[test]
     fn test_mul_scaling_matrix_point() {
         let transform = scaling(2.0, 3.0, 4.0);
@@ -112,7 +125,6 @@ mod tests {
         assert_eq!(transform * v, Tuple::vector(-8.0, 18.0, 32.0));
     }
 
-    // FIXME: this fails due to floating point imprecision and all of that.
     #[test]
     fn test_mul_scaling_matrix_inverse() {
         let transform = scaling(2.0, 3.0, 4.0);
@@ -272,4 +284,13 @@ mod tests {
 
         assert_eq!(t * p, Tuple::point(15.0, 0.0, 7.0));
     }
+
+    #[test]
+    fn test_transform_f32() {
+        let p = Tuple32::point(1.0, 0.0, 1.0);
+        let t =
+            translation(10.0_f32, 5.0, 7.0) * scaling(5.0, 5.0, 5.0) * rotation_x(PI as f32 / 2.0);
+
+        assert_eq!(t * p, Tuple32::point(15.0, 0.0, 7.0));
+    }
 }
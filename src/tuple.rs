@@ -1,129 +1,124 @@
-use float_eq::{derive_float_eq, float_eq};
-use std::f64::{self, EPSILON};
+use crate::approx::{float_eq_generic, ApproxEq};
+use crate::scalar::Scalar;
 use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
 
-#[derive_float_eq(
-    ulps_tol = "TupleUlps",
-    ulps_tol_derive = "Clone, Copy, Debug, PartialEq",
-    debug_ulps_diff = "TupleDebugUlpsDiff",
-    debug_ulps_diff_derive = "Clone, Copy, Debug, PartialEq",
-    all_tol = "f64"
-)]
 #[derive(Clone, Copy, Debug)]
-pub struct Tuple {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
-    pub w: f64,
+pub struct GenericTuple<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+    pub w: T,
 }
 
-impl Tuple {
-    pub fn new(x: f64, y: f64, z: f64, w: f64) -> Self {
+pub type Tuple = GenericTuple<f64>;
+pub type Tuple32 = GenericTuple<f32>;
+
+float_eq_generic!(GenericTuple { x, y, z, w }, TupleUlps, TupleDebugUlpsDiff);
+
+impl<T: Scalar> GenericTuple<T> {
+    pub fn new(x: T, y: T, z: T, w: T) -> Self {
         Self { x, y, z, w }
     }
 
-    pub fn point(x: f64, y: f64, z: f64) -> Self {
-        Self { x, y, z, w: 1.0 }
+    pub fn point(x: T, y: T, z: T) -> Self {
+        Self {
+            x,
+            y,
+            z,
+            w: T::one(),
+        }
     }
 
-    pub fn vector(x: f64, y: f64, z: f64) -> Self {
-        Self { x, y, z, w: 0.0 }
+    pub fn vector(x: T, y: T, z: T) -> Self {
+        Self {
+            x,
+            y,
+            z,
+            w: T::zero(),
+        }
     }
 
     pub fn is_point(&self) -> bool {
-        self.w == 1.0
+        self.w == T::one()
     }
 
     pub fn is_vector(&self) -> bool {
-        self.w == 0.0
+        self.w == T::zero()
     }
 
-    pub fn magnitude(&self) -> f64 {
-        (self.x.powf(2.0) + self.y.powf(2.0) + self.z.powf(2.0) + self.w.powf(2.0)).sqrt()
+    pub fn magnitude(&self) -> T {
+        T::dot4((*self).into(), (*self).into()).sqrt()
     }
 
     pub fn normalize(&self) -> Self {
-        Self {
-            x: self.x / self.magnitude(),
-            y: self.y / self.magnitude(),
-            z: self.z / self.magnitude(),
-            w: self.w / self.magnitude(),
-        }
+        T::normalize4((*self).into()).into()
     }
 
-    pub fn cross(&self, other: &Tuple) -> Self {
-        Self::vector(
-            self.y * other.z - self.z * other.y,
-            self.z * other.x - self.x * other.z,
-            self.x * other.y - self.y * other.x,
-        )
+    pub fn cross(&self, other: &Self) -> Self {
+        T::cross3((*self).into(), (*other).into()).into()
     }
 }
 
-impl PartialEq for Tuple {
-    fn eq(&self, other: &Self) -> bool {
-        let cmp = Tuple {
-            x: 1.0 * EPSILON,
-            y: 1.0 * EPSILON,
-            z: 1.0 * EPSILON,
-            w: 1.0 * EPSILON,
-        };
+impl<T> From<[T; 4]> for GenericTuple<T> {
+    fn from([x, y, z, w]: [T; 4]) -> Self {
+        Self { x, y, z, w }
+    }
+}
+
+impl<T> From<GenericTuple<T>> for [T; 4] {
+    fn from(t: GenericTuple<T>) -> Self {
+        [t.x, t.y, t.z, t.w]
+    }
+}
+
+impl<T: Scalar> ApproxEq for GenericTuple<T> {
+    type Scalar = T;
+
+    fn all_components<F>(&self, other: &Self, f: F) -> bool
+    where
+        F: Fn(&T, &T) -> bool,
+    {
+        f(&self.x, &other.x) && f(&self.y, &other.y) && f(&self.z, &other.z) && f(&self.w, &other.w)
+    }
+}
 
-        float_eq!(self, other, abs <= cmp)
+impl<T: Scalar> PartialEq for GenericTuple<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.approx_eq(other)
     }
 }
 
-impl Eq for Tuple {}
+impl<T: Scalar> Eq for GenericTuple<T> {}
 
-impl Add for Tuple {
+impl<T: Scalar> Add for GenericTuple<T> {
     type Output = Self;
 
     fn add(self, other: Self) -> Self {
-        Self {
-            x: self.x + other.x,
-            y: self.y + other.y,
-            z: self.z + other.z,
-            w: self.w + other.w,
-        }
+        T::add4(self.into(), other.into()).into()
     }
 }
 
-impl AddAssign for Tuple {
+impl<T: Scalar> AddAssign for GenericTuple<T> {
     fn add_assign(&mut self, other: Self) {
-        *self = Self {
-            x: self.x + other.x,
-            y: self.y + other.y,
-            z: self.z + other.z,
-            w: self.w + other.w,
-        }
+        *self = *self + other
     }
 }
 
-impl Sub for Tuple {
+impl<T: Scalar> Sub for GenericTuple<T> {
     type Output = Self;
 
     fn sub(self, other: Self) -> Self {
-        Self {
-            x: self.x - other.x,
-            y: self.y - other.y,
-            z: self.z - other.z,
-            w: self.w - other.w,
-        }
+        T::sub4(self.into(), other.into()).into()
     }
 }
 
-impl SubAssign for Tuple {
+impl<T: Scalar> SubAssign for GenericTuple<T> {
     fn sub_assign(&mut self, other: Self) {
-        *self = Self {
-            x: self.x - other.x,
-            y: self.y - other.y,
-            z: self.z - other.z,
-            w: self.w - other.w,
-        }
+        *self = *self - other
     }
 }
 
-impl Neg for Tuple {
+impl<T: Scalar> Neg for GenericTuple<T> {
     type Output = Self;
 
     fn neg(self) -> Self {
@@ -136,10 +131,10 @@ impl Neg for Tuple {
     }
 }
 
-impl Mul<f64> for Tuple {
+impl<T: Scalar> Mul<T> for GenericTuple<T> {
     type Output = Self;
 
-    fn mul(self, other: f64) -> Self {
+    fn mul(self, other: T) -> Self {
         Self {
             x: self.x * other,
             y: self.y * other,
@@ -149,18 +144,18 @@ impl Mul<f64> for Tuple {
     }
 }
 
-impl Mul<Tuple> for Tuple {
-    type Output = f64;
+impl<T: Scalar> Mul<GenericTuple<T>> for GenericTuple<T> {
+    type Output = T;
 
-    fn mul(self, other: Tuple) -> f64 {
-        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    fn mul(self, other: Self) -> T {
+        T::dot4(self.into(), other.into())
     }
 }
 
-impl Div<f64> for Tuple {
+impl<T: Scalar> Div<T> for GenericTuple<T> {
     type Output = Self;
 
-    fn div(self, other: f64) -> Self {
+    fn div(self, other: T) -> Self {
         Self {
             x: self.x / other,
             y: self.y / other,
@@ -171,9 +166,11 @@ impl Div<f64> for Tuple {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::legacy_numeric_constants)]
 mod tests {
     use super::*;
-    use float_eq::assert_float_eq;
+    use float_eq::{assert_float_eq, assert_float_ne};
+    use std::f64::EPSILON;
 
     #[test]
     fn test_point() {
@@ -314,4 +311,37 @@ mod tests {
         assert_eq!(a.cross(&b), result_ab);
         assert_eq!(b.cross(&a), result_ba);
     }
+
+    #[test]
+    fn test_tuple_f32() {
+        let a = Tuple32::vector(1.0, 2.0, 3.0);
+        let b = Tuple32::vector(2.0, 3.0, 4.0);
+
+        assert_eq!(a + b, Tuple32::vector(3.0, 5.0, 7.0));
+        assert_eq!(a.cross(&b), Tuple32::vector(-1.0, 2.0, -1.0));
+        assert_float_eq!(a * b, 20.0_f32, abs <= f32::EPSILON);
+    }
+
+    #[test]
+    fn test_tuple_float_eq() {
+        let a = Tuple::vector(1.0, 2.0, 3.0);
+        let b = Tuple::vector(1.0, 2.0 + f64::EPSILON * 2.0, 3.0);
+
+        assert_float_eq!(a, b, abs_all <= 1e-12);
+        assert_float_eq!(a, b, ulps_all <= 1);
+        assert_float_eq!(
+            a,
+            b,
+            ulps <= TupleUlps {
+                x: 0,
+                y: 1,
+                z: 0,
+                w: 0
+            }
+        );
+        assert_float_ne!(a, b, abs_all <= 0.0);
+
+        let c = Tuple32::point(1.0, 2.0, 3.0);
+        assert_float_eq!(c, Tuple32::point(1.0, 2.0, 3.001), rmax_all <= 0.001);
+    }
 }
@@ -0,0 +1,254 @@
+use crate::approx::ApproxEq;
+use crate::tuple::Tuple;
+use float_eq::derive_float_eq;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+/// a direction in 3D space. translations don't affect it, and only vectors can
+/// be added together, normalized or crossed.
+#[derive_float_eq(
+    ulps_tol = "Vector3Ulps",
+    ulps_tol_derive = "Clone, Copy, Debug, PartialEq",
+    debug_ulps_diff = "Vector3DebugUlpsDiff",
+    debug_ulps_diff_derive = "Clone, Copy, Debug, PartialEq",
+    all_tol = "f64"
+)]
+#[derive(Clone, Copy, Debug)]
+pub struct Vector3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Vector3 {
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn zero() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn magnitude(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Self {
+        *self / self.magnitude()
+    }
+
+    pub fn dot(&self, other: &Vector3) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(&self, other: &Vector3) -> Self {
+        Self::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+}
+
+impl ApproxEq for Vector3 {
+    type Scalar = f64;
+
+    fn all_components<F>(&self, other: &Self, f: F) -> bool
+    where
+        F: Fn(&f64, &f64) -> bool,
+    {
+        f(&self.x, &other.x) && f(&self.y, &other.y) && f(&self.z, &other.z)
+    }
+}
+
+impl PartialEq for Vector3 {
+    fn eq(&self, other: &Self) -> bool {
+        self.approx_eq(other)
+    }
+}
+
+impl Eq for Vector3 {}
+
+impl Add for Vector3 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+impl AddAssign for Vector3 {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Vector3 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+    }
+}
+
+impl SubAssign for Vector3 {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+impl Neg for Vector3 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
+impl Mul<f64> for Vector3 {
+    type Output = Self;
+
+    fn mul(self, other: f64) -> Self {
+        Self {
+            x: self.x * other,
+            y: self.y * other,
+            z: self.z * other,
+        }
+    }
+}
+
+impl Mul<Vector3> for Vector3 {
+    type Output = f64;
+
+    fn mul(self, other: Vector3) -> f64 {
+        self.dot(&other)
+    }
+}
+
+impl Div<f64> for Vector3 {
+    type Output = Self;
+
+    fn div(self, other: f64) -> Self {
+        Self {
+            x: self.x / other,
+            y: self.y / other,
+            z: self.z / other,
+        }
+    }
+}
+
+impl From<Vector3> for Tuple {
+    fn from(v: Vector3) -> Self {
+        Tuple::vector(v.x, v.y, v.z)
+    }
+}
+
+impl TryFrom<Tuple> for Vector3 {
+    type Error = Tuple;
+
+    /// fails with the original tuple if it isn't a vector (`w != 0.0`).
+    fn try_from(t: Tuple) -> Result<Self, Self::Error> {
+        if t.is_vector() {
+            Ok(Self::new(t.x, t.y, t.z))
+        } else {
+            Err(t)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use float_eq::assert_float_eq;
+
+    #[test]
+    fn test_add_vectors() {
+        let a = Vector3::new(3.0, -2.0, 5.0);
+        let b = Vector3::new(-2.0, 3.0, 1.0);
+
+        assert_eq!(a + b, Vector3::new(1.0, 1.0, 6.0));
+    }
+
+    #[test]
+    fn test_sub_vectors() {
+        let a = Vector3::new(3.0, 2.0, 1.0);
+        let b = Vector3::new(5.0, 6.0, 7.0);
+
+        assert_eq!(a - b, Vector3::new(-2.0, -4.0, -6.0));
+    }
+
+    #[test]
+    fn test_neg_vector() {
+        let v = Vector3::new(1.0, -2.0, 3.0);
+
+        assert_eq!(-v, Vector3::new(-1.0, 2.0, -3.0));
+    }
+
+    #[test]
+    fn test_scalar_mul_div() {
+        let v = Vector3::new(1.0, -2.0, 3.0);
+
+        assert_eq!(v * 3.5, Vector3::new(3.5, -7.0, 10.5));
+        assert_eq!(v / 2.0, Vector3::new(0.5, -1.0, 1.5));
+    }
+
+    #[test]
+    fn test_magnitude() {
+        let a = Vector3::new(1.0, 0.0, 0.0);
+        let b = Vector3::new(-1.0, -2.0, -3.0);
+
+        assert_float_eq!(a.magnitude(), 1.0, abs <= f64::EPSILON);
+        assert_float_eq!(b.magnitude(), (14.0_f64).sqrt(), abs <= f64::EPSILON);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let v = Vector3::new(4.0, 0.0, 0.0);
+
+        assert_eq!(v.normalize(), Vector3::new(1.0, 0.0, 0.0));
+        assert_float_eq!(
+            Vector3::new(1.0, 2.0, 3.0).normalize().magnitude(),
+            1.0,
+            abs <= f64::EPSILON
+        );
+    }
+
+    #[test]
+    fn test_dot() {
+        let a = Vector3::new(1.0, 2.0, 3.0);
+        let b = Vector3::new(2.0, 3.0, 4.0);
+
+        assert_float_eq!(a.dot(&b), 20.0, abs <= f64::EPSILON);
+        assert_float_eq!(a * b, 20.0, abs <= f64::EPSILON);
+    }
+
+    #[test]
+    fn test_cross() {
+        let a = Vector3::new(1.0, 2.0, 3.0);
+        let b = Vector3::new(2.0, 3.0, 4.0);
+
+        assert_eq!(a.cross(&b), Vector3::new(-1.0, 2.0, -1.0));
+        assert_eq!(b.cross(&a), Vector3::new(1.0, -2.0, 1.0));
+    }
+
+    #[test]
+    fn test_vector_tuple_conversion() {
+        let v = Vector3::new(4.0, -4.0, 3.0);
+        let t: Tuple = v.into();
+
+        assert_eq!(t, Tuple::vector(4.0, -4.0, 3.0));
+        assert_eq!(Vector3::try_from(t), Ok(v));
+        assert!(Vector3::try_from(Tuple::point(4.0, -4.0, 3.0)).is_err());
+    }
+}
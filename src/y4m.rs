@@ -0,0 +1,234 @@
+//! yuv4mpeg2 output, for piping animation renders straight into a video
+//! encoder, e.g. `render | ffmpeg -i - out.mp4`.
+//!
+//! colors are encoded as studio range rec.709 y'cbcr, which is what encoders
+//! assume for hd video.
+
+use crate::{canvas::Canvas, color::Color, transfer::TransferFunction};
+use std::io::{BufWriter, Write};
+
+/// how finely the chroma planes are sampled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subsampling {
+    /// one chroma sample for each 2x2 block, as nearly every encoder and player
+    /// expects.
+    Yuv420,
+    /// full resolution chroma, for lossless or intermediate encodes.
+    Yuv444,
+}
+
+impl Subsampling {
+    fn tag(self) -> &'static str {
+        match self {
+            Subsampling::Yuv420 => "420jpeg",
+            Subsampling::Yuv444 => "444",
+        }
+    }
+}
+
+/// the rec.709 luma weights for r' and b', with g' taking the rest.
+const KR: f64 = 0.2126;
+const KB: f64 = 0.0722;
+
+/// converts a linear color to rec.709 y'cbcr, with y' in [0, 1] and the
+/// chroma in [-0.5, 0.5].
+fn to_ycbcr(c: Color) -> (f64, f64, f64) {
+    let c = TransferFunction::Rec709.encode_color(c);
+    let (r, g, b) = (
+        c.r.clamp(0.0, 1.0),
+        c.g.clamp(0.0, 1.0),
+        c.b.clamp(0.0, 1.0),
+    );
+
+    let y = KR * r + (1.0 - KR - KB) * g + KB * b;
+    let cb = (b - y) / (2.0 * (1.0 - KB));
+    let cr = (r - y) / (2.0 * (1.0 - KR));
+    (y, cb, cr)
+}
+
+/// quantizes to studio range: 16 to 235 for luma, 16 to 240 for chroma.
+fn luma_byte(y: f64) -> u8 {
+    (16.0 + 219.0 * y).round().clamp(0.0, 255.0) as u8
+}
+
+fn chroma_byte(c: f64) -> u8 {
+    (128.0 + 224.0 * c).round().clamp(0.0, 255.0) as u8
+}
+
+/// streams frames as an uncompressed yuv4mpeg2 video. each frame is flushed
+/// as soon as it's written, so whatever's reading can start encoding.
+pub struct Y4mWriter<W: Write> {
+    writer: BufWriter<W>,
+    width: usize,
+    height: usize,
+    subsampling: Subsampling,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    /// writes the stream header for `width` by `height` frames, playing at
+    /// `fps.0 / fps.1` frames per second, e.g. `(30000, 1001)` for ntsc.
+    pub fn new(
+        writer: W,
+        width: usize,
+        height: usize,
+        fps: (u32, u32),
+        subsampling: Subsampling,
+    ) -> std::io::Result<Self> {
+        let mut w = BufWriter::new(writer);
+        writeln!(
+            w,
+            "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C{} XCOLORRANGE=LIMITED",
+            width,
+            height,
+            fps.0,
+            fps.1,
+            subsampling.tag()
+        )?;
+        w.flush()?;
+
+        Ok(Self {
+            writer: w,
+            width,
+            height,
+            subsampling,
+        })
+    }
+
+    /// appends `frame`, which must be the size given to `new`.
+    pub fn write_frame(&mut self, frame: &Canvas) -> std::io::Result<()> {
+        assert!(
+            frame.width == self.width && frame.height == self.height,
+            "can't add a {}x{} frame to a {}x{} video",
+            frame.width,
+            frame.height,
+            self.width,
+            self.height
+        );
+
+        let samples: Vec<(f64, f64, f64)> = frame.pixels.iter().map(|&c| to_ycbcr(c)).collect();
+        let luma: Vec<u8> = samples.iter().map(|&(y, _, _)| luma_byte(y)).collect();
+
+        let (cb, cr): (Vec<u8>, Vec<u8>) = match self.subsampling {
+            Subsampling::Yuv444 => samples
+                .iter()
+                .map(|&(_, cb, cr)| (chroma_byte(cb), chroma_byte(cr)))
+                .unzip(),
+            Subsampling::Yuv420 => {
+                // average each 2x2 block, or what's left of one at an odd edge.
+                let (w, h) = (self.width, self.height);
+                let mut planes = (vec![], vec![]);
+                for by in (0..h).step_by(2) {
+                    for bx in (0..w).step_by(2) {
+                        let (mut cb, mut cr, mut n) = (0.0, 0.0, 0.0);
+                        for y in by..(by + 2).min(h) {
+                            for x in bx..(bx + 2).min(w) {
+                                let (_, b, r) = samples[x + y * w];
+                                cb += b;
+                                cr += r;
+                                n += 1.0;
+                            }
+                        }
+                        planes.0.push(chroma_byte(cb / n));
+                        planes.1.push(chroma_byte(cr / n));
+                    }
+                }
+                planes
+            }
+        };
+
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(&luma)?;
+        self.writer.write_all(&cb)?;
+        self.writer.write_all(&cr)?;
+        self.writer.flush()
+    }
+
+    /// hands back the underlying writer. y4m has no trailer, so there's
+    /// nothing left to write.
+    pub fn into_inner(self) -> std::io::Result<W> {
+        self.writer.into_inner().map_err(|e| e.into_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(frames: &[Canvas], subsampling: Subsampling) -> Vec<u8> {
+        let (w, h) = (frames[0].width, frames[0].height);
+        let mut y4m = Y4mWriter::new(vec![], w, h, (24, 1), subsampling).unwrap();
+        for frame in frames {
+            y4m.write_frame(frame).unwrap();
+        }
+        y4m.into_inner().unwrap()
+    }
+
+    /// the header line, and the bytes of each `frame_len` byte frame.
+    fn split(data: &[u8], frame_len: usize) -> (String, Vec<&[u8]>) {
+        let end = data.iter().position(|&b| b == b'\n').unwrap();
+        let header = String::from_utf8(data[..end].to_vec()).unwrap();
+        let frames = data[end + 1..]
+            .chunks(6 + frame_len)
+            .map(|f| f.strip_prefix(b"FRAME\n").unwrap())
+            .collect();
+        (header, frames)
+    }
+
+    #[test]
+    fn test_ycbcr() {
+        let bytes = |c: Color| {
+            let (y, cb, cr) = to_ycbcr(c);
+            (luma_byte(y), chroma_byte(cb), chroma_byte(cr))
+        };
+
+        assert_eq!(bytes(Color::new(0.0, 0.0, 0.0)), (16, 128, 128));
+        assert_eq!(bytes(Color::new(1.0, 1.0, 1.0)), (235, 128, 128));
+        assert_eq!(bytes(Color::new(5.0, 5.0, 5.0)), (235, 128, 128));
+        assert_eq!(bytes(Color::new(1.0, 0.0, 0.0)), (63, 102, 240));
+        assert_eq!(bytes(Color::new(0.0, 1.0, 0.0)), (173, 42, 26));
+        assert_eq!(bytes(Color::new(0.0, 0.0, 1.0)), (32, 240, 118));
+    }
+
+    #[test]
+    fn test_y4m_444() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(1, 0, Color::new(1.0, 1.0, 1.0));
+
+        let data = stream(&[c.clone(), c], Subsampling::Yuv444);
+        let (header, frames) = split(&data, 6);
+
+        assert_eq!(
+            header,
+            "YUV4MPEG2 W2 H1 F24:1 Ip A1:1 C444 XCOLORRANGE=LIMITED"
+        );
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0], &[16, 235, 128, 128, 128, 128]);
+    }
+
+    #[test]
+    fn test_y4m_420() {
+        // a 3x3 frame has 2x2 chroma planes, the edge blocks being partial.
+        let mut c = Canvas::new(3, 3);
+        c.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        c.write_pixel(2, 2, Color::new(1.0, 0.0, 0.0));
+
+        let data = stream(&[c], Subsampling::Yuv420);
+        let (header, frames) = split(&data, 9 + 4 + 4);
+        assert!(header.contains(" C420jpeg "));
+
+        let frame = frames[0];
+        assert_eq!(frame.len(), 9 + 4 + 4);
+        assert_eq!(&frame[..9], &[63, 16, 16, 16, 16, 16, 16, 16, 63]);
+
+        // a quarter red in the first block, all red in the last.
+        let (cb, cr) = (&frame[9..13], &frame[13..]);
+        assert_eq!(cb, &[122, 128, 128, 102]);
+        assert_eq!(cr, &[156, 128, 128, 240]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_y4m_frame_size_mismatch() {
+        stream(&[Canvas::new(2, 2), Canvas::new(2, 1)], Subsampling::Yuv444);
+    }
+}